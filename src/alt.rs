@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+
+use solana_client_api::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    clock::Slot,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::{AccountMeta, CompiledInstruction, Instruction},
+    message::{
+        v0::{self, MessageAddressTableLookup},
+        MessageHeader, VersionedMessage,
+    },
+    pubkey::Pubkey,
+    signature::Signature,
+    signers::Signers,
+    system_program,
+};
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// Id of the address lookup table program.
+pub mod address_lookup_table_program {
+    solana_sdk::declare_id!("AddressLookupTab1e1111111111111111111111111");
+}
+
+/// Size of the metadata preceding the addresses stored in an address lookup table account.
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// Maximum number of addresses added by an extend instruction that still fits in a transaction.
+pub const MAX_EXTEND_ADDRESSES: usize = 30;
+
+/// Maximum number of accounts a message can refer to.
+const MAX_MESSAGE_ACCOUNTS: usize = 256;
+
+/// Discriminators of the lookup table program instructions.
+const CREATE_LOOKUP_TABLE: u32 = 0;
+const EXTEND_LOOKUP_TABLE: u32 = 2;
+
+/// Address lookup table account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTable {
+    pub key: Pubkey,
+
+    /// Slot the table was deactivated in, `Slot::MAX` while it is active.
+    pub deactivation_slot: Slot,
+    pub last_extended_slot: Slot,
+
+    /// Authority allowed to extend the table, `None` if the table is frozen.
+    pub authority: Option<Pubkey>,
+    pub addresses: Vec<Pubkey>,
+}
+
+impl LookupTable {
+    /// Parses the lookup table account data, `None` if it is not an initialized lookup table.
+    pub fn parse(key: Pubkey, data: &[u8]) -> Option<Self> {
+        let meta = data.get(..LOOKUP_TABLE_META_SIZE)?;
+        if meta[..4] != [1, 0, 0, 0] {
+            return None;
+        }
+
+        Some(Self {
+            key,
+            deactivation_slot: u64::from_le_bytes(meta[4..12].try_into().ok()?),
+            last_extended_slot: u64::from_le_bytes(meta[12..20].try_into().ok()?),
+            authority: (meta[21] == 1).then(|| Pubkey::new(&meta[22..54])),
+            addresses: data[LOOKUP_TABLE_META_SIZE..]
+                .chunks_exact(32)
+                .map(Pubkey::new)
+                .collect(),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.deactivation_slot == Slot::MAX
+    }
+}
+
+/// Derives the address of the lookup table created by `authority` in `recent_slot`, with its bump seed.
+pub fn derive_lookup_table_address(authority: &Pubkey, recent_slot: Slot) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &address_lookup_table_program::id(),
+    )
+}
+
+/// Instruction creating a lookup table of `authority` paid by `payer`, returned with the table address.
+/// The `recent_slot` must be a recent slot of the cluster.
+pub fn create_lookup_table(authority: &Pubkey, payer: &Pubkey, recent_slot: Slot) -> (Instruction, Pubkey) {
+    let (table, bump_seed) = derive_lookup_table_address(authority, recent_slot);
+    let mut data = CREATE_LOOKUP_TABLE.to_le_bytes().to_vec();
+    data.extend_from_slice(&recent_slot.to_le_bytes());
+    data.push(bump_seed);
+
+    let instruction = Instruction::new_with_bytes(address_lookup_table_program::id(), &data, vec![
+        AccountMeta::new(table, false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]);
+    (instruction, table)
+}
+
+/// Instruction appending `new_addresses` to the lookup `table`, the rent of the growth paid by `payer`.
+pub fn extend_lookup_table(
+    table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    let mut data = EXTEND_LOOKUP_TABLE.to_le_bytes().to_vec();
+    data.extend_from_slice(&(new_addresses.len() as u64).to_le_bytes());
+    for address in new_addresses {
+        data.extend_from_slice(address.as_ref());
+    }
+
+    Instruction::new_with_bytes(address_lookup_table_program::id(), &data, vec![
+        AccountMeta::new(*table, false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ])
+}
+
+/// Compiles the `instructions` paid by `payer` into a v0 message, loading the accounts found in the
+/// `lookup_tables` from them. Signers and invoked programs always stay among the static account keys.
+pub fn compile_v0_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[LookupTable],
+    recent_blockhash: Hash,
+) -> ClientResult<VersionedMessage> {
+    // Unique keys in the order of their first use, with their merged flags
+    let mut keys: Vec<KeyFlags> = vec![KeyFlags {
+        pubkey: *payer,
+        is_signer: true,
+        is_writable: true,
+        is_invoked: false,
+    }];
+    let mut add_key = |pubkey: Pubkey, is_signer: bool, is_writable: bool, is_invoked: bool| match keys
+        .iter_mut()
+        .find(|key| key.pubkey == pubkey)
+    {
+        Some(key) => {
+            key.is_signer |= is_signer;
+            key.is_writable |= is_writable;
+            key.is_invoked |= is_invoked;
+        },
+        None => keys.push(KeyFlags {
+            pubkey,
+            is_signer,
+            is_writable,
+            is_invoked,
+        }),
+    };
+    for instruction in instructions {
+        add_key(instruction.program_id, false, false, true);
+        for account in &instruction.accounts {
+            add_key(account.pubkey, account.is_signer, account.is_writable, false);
+        }
+    }
+
+    let mut static_keys = Vec::new();
+    let mut lookups: Vec<(MessageAddressTableLookup, Vec<Pubkey>, Vec<Pubkey>)> = lookup_tables
+        .iter()
+        .map(|table| {
+            let lookup = MessageAddressTableLookup {
+                account_key: table.key,
+                writable_indexes: Vec::new(),
+                readonly_indexes: Vec::new(),
+            };
+            (lookup, Vec::new(), Vec::new())
+        })
+        .collect();
+    for key in keys {
+        let position = (!key.is_signer && !key.is_invoked)
+            .then(|| {
+                lookup_tables.iter().enumerate().find_map(|(table_index, table)| {
+                    table
+                        .addresses
+                        .iter()
+                        .position(|address| *address == key.pubkey)
+                        .filter(|&address_index| address_index <= u8::MAX as usize)
+                        .map(|address_index| (table_index, address_index as u8))
+                })
+            })
+            .flatten();
+
+        match position {
+            Some((table_index, address_index)) => {
+                let (lookup, writable, readonly) = &mut lookups[table_index];
+                if key.is_writable {
+                    lookup.writable_indexes.push(address_index);
+                    writable.push(key.pubkey);
+                } else {
+                    lookup.readonly_indexes.push(address_index);
+                    readonly.push(key.pubkey);
+                }
+            },
+            None => static_keys.push(key),
+        }
+    }
+
+    // Stable, so the payer stays the first signer
+    static_keys.sort_by_key(|key| (!key.is_signer, !key.is_writable));
+    let header = MessageHeader {
+        num_required_signatures: static_keys.iter().filter(|key| key.is_signer).count() as u8,
+        num_readonly_signed_accounts: static_keys
+            .iter()
+            .filter(|key| key.is_signer && !key.is_writable)
+            .count() as u8,
+        num_readonly_unsigned_accounts: static_keys
+            .iter()
+            .filter(|key| !key.is_signer && !key.is_writable)
+            .count() as u8,
+    };
+
+    let account_keys: Vec<Pubkey> = static_keys.iter().map(|key| key.pubkey).collect();
+    let loaded_writable = lookups.iter().flat_map(|(_, writable, _)| writable.iter());
+    let loaded_readonly = lookups.iter().flat_map(|(_, _, readonly)| readonly.iter());
+    let all_keys: Vec<Pubkey> = account_keys
+        .iter()
+        .chain(loaded_writable)
+        .chain(loaded_readonly)
+        .copied()
+        .collect();
+    if all_keys.len() > MAX_MESSAGE_ACCOUNTS {
+        return Err(ClientErrorKind::Custom(format!(
+            "message refers to {} accounts, more than the maximum of {}",
+            all_keys.len(),
+            MAX_MESSAGE_ACCOUNTS
+        ))
+        .into());
+    }
+    let indexes: HashMap<Pubkey, u8> = all_keys
+        .iter()
+        .enumerate()
+        .map(|(index, pubkey)| (*pubkey, index as u8))
+        .collect();
+
+    let instructions = instructions
+        .iter()
+        .map(|instruction| CompiledInstruction {
+            program_id_index: indexes[&instruction.program_id],
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|account| indexes[&account.pubkey])
+                .collect(),
+            data: instruction.data.clone(),
+        })
+        .collect();
+
+    Ok(VersionedMessage::V0(v0::Message {
+        header,
+        account_keys,
+        recent_blockhash,
+        instructions,
+        address_table_lookups: lookups
+            .into_iter()
+            .map(|(lookup, ..)| lookup)
+            .filter(|lookup| !lookup.writable_indexes.is_empty() || !lookup.readonly_indexes.is_empty())
+            .collect(),
+    }))
+}
+
+struct KeyFlags {
+    pubkey: Pubkey,
+    is_signer: bool,
+    is_writable: bool,
+    is_invoked: bool,
+}
+
+impl WasmRpcClient {
+    /// Fetches and parses the lookup table accounts at `keys`, failing if any of them is missing.
+    pub fn get_lookup_tables(&self, keys: &[Pubkey]) -> ClientResult<Vec<LookupTable>> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let accounts = self.get_multiple_accounts(keys)?;
+        keys.iter()
+            .zip(accounts)
+            .map(|(key, account)| {
+                let account = account
+                    .ok_or_else(|| ClientErrorKind::Custom(format!("address lookup table {} not found", key)))?;
+                if account.owner != address_lookup_table_program::id() {
+                    return Err(RpcError::ForUser(format!("account {} is not an address lookup table", key)).into());
+                }
+                LookupTable::parse(*key, &account.data)
+                    .ok_or_else(|| RpcError::ParseError(format!("address lookup table {} is invalid", key)).into())
+            })
+            .collect()
+    }
+
+    pub fn get_lookup_table(&self, key: &Pubkey) -> ClientResult<LookupTable> {
+        Ok(self.get_lookup_tables(&[*key])?.remove(0))
+    }
+
+    /// Compiles the `instructions` paid by `payer` into a v0 message with the latest blockhash, loading the
+    /// accounts found in the lookup tables at `table_keys` from them.
+    pub fn compile_v0_message(
+        &self,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        table_keys: &[Pubkey],
+    ) -> ClientResult<VersionedMessage> {
+        let lookup_tables = self.get_lookup_tables(table_keys)?;
+        compile_v0_message(payer, instructions, &lookup_tables, self.get_cached_blockhash()?)
+    }
+
+    /// Creates a lookup table of `authority` paid by `payer`, returning the table address. The `signers` must
+    /// include `payer` and `authority`.
+    pub fn create_lookup_table<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        authority: &Pubkey,
+    ) -> ClientResult<(Pubkey, Signature)> {
+        let recent_slot = self.get_slot_with_commitment(CommitmentConfig::finalized())?;
+        let (instruction, table) = create_lookup_table(authority, payer, recent_slot);
+        let signature = self.sign_and_confirm(signers, payer, &[instruction])?;
+        Ok((table, signature))
+    }
+
+    /// Appends `addresses` to the lookup `table`, in as many transactions as needed. The `signers` must include
+    /// `payer` and the table authority.
+    pub fn extend_lookup_table<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        table: &Pubkey,
+        authority: &Pubkey,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<Signature>> {
+        addresses
+            .chunks(MAX_EXTEND_ADDRESSES)
+            .map(|chunk| {
+                let instruction = extend_lookup_table(table, authority, payer, chunk);
+                self.sign_and_confirm(signers, payer, &[instruction])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data of an initialized lookup table account with the `authority` and the `addresses`.
+    fn lookup_table_data(deactivation_slot: Slot, authority: Option<Pubkey>, addresses: &[Pubkey]) -> Vec<u8> {
+        let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+        data[..4].copy_from_slice(&[1, 0, 0, 0]);
+        data[4..12].copy_from_slice(&deactivation_slot.to_le_bytes());
+        data[12..20].copy_from_slice(&42_u64.to_le_bytes());
+        data[20] = 3;
+        if let Some(authority) = authority {
+            data[21] = 1;
+            data[22..54].copy_from_slice(authority.as_ref());
+        }
+        for address in addresses {
+            data.extend_from_slice(address.as_ref());
+        }
+        data
+    }
+
+    #[test]
+    fn lookup_table_is_parsed_at_its_offsets() {
+        let key = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let table = LookupTable::parse(key, &lookup_table_data(Slot::MAX, Some(authority), &addresses)).unwrap();
+        assert_eq!(table, LookupTable {
+            key,
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: 42,
+            authority: Some(authority),
+            addresses: addresses.to_vec(),
+        });
+        assert!(table.is_active());
+
+        let frozen = LookupTable::parse(key, &lookup_table_data(7, None, &[])).unwrap();
+        assert_eq!(frozen.authority, None);
+        assert!(frozen.addresses.is_empty());
+        assert!(!frozen.is_active());
+
+        let mut uninitialized = lookup_table_data(Slot::MAX, None, &[]);
+        uninitialized[0] = 0;
+        assert_eq!(LookupTable::parse(key, &uninitialized), None);
+        assert_eq!(LookupTable::parse(key, &[1, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn table_addresses_are_loaded_except_the_signers_and_the_programs() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let writable = Pubkey::new_unique();
+        let readonly = Pubkey::new_unique();
+        let table = LookupTable {
+            key: Pubkey::new_unique(),
+            deactivation_slot: Slot::MAX,
+            last_extended_slot: 0,
+            authority: None,
+            addresses: vec![program_id, readonly, payer, writable],
+        };
+        let instruction = Instruction::new_with_bytes(program_id, &[1], vec![
+            AccountMeta::new(writable, false),
+            AccountMeta::new_readonly(readonly, false),
+        ]);
+
+        let message = match compile_v0_message(&payer, &[instruction], &[table.clone()], Hash::default()).unwrap() {
+            VersionedMessage::V0(message) => message,
+            VersionedMessage::Legacy(_) => panic!("expected a v0 message"),
+        };
+        assert_eq!(message.account_keys, [payer, program_id]);
+        assert_eq!(message.address_table_lookups, [MessageAddressTableLookup {
+            account_key: table.key,
+            writable_indexes: vec![3],
+            readonly_indexes: vec![1],
+        }]);
+        // Static keys, then the loaded writable and readonly ones
+        assert_eq!(message.instructions[0].program_id_index, 1);
+        assert_eq!(message.instructions[0].accounts, [2, 3]);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use solana_client_api::{client_error::Result, rpc_request::RpcRequest};
+
+pub use crate::transport::{HostHttpRequest, HostHttpResponse};
+use crate::{
+    is_endpoint_failure, no_endpoint_error, observer::RpcEvent, retry::RetryPolicy, sanitize_url, AttemptOutcome,
+    HttpSender, StatsUpdater,
+};
+
+/// Future of the non-blocking calls, not `Send` since the WASM host runs a single thread.
+pub type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// Non-blocking host HTTP call.
+pub type AsyncInvoke =
+    Box<dyn Fn(HostHttpRequest) -> LocalBoxFuture<'static, std::result::Result<HostHttpResponse, String>>>;
+
+/// Non-blocking host sleep.
+pub type AsyncSleep = Box<dyn Fn(Duration) -> LocalBoxFuture<'static, ()>>;
+
+/// Non-blocking counterpart of `RpcSender`.
+pub trait AsyncRpcSender {
+    fn send(&self, request: RpcRequest, params: Value) -> LocalBoxFuture<'_, Result<Value>>;
+
+    fn url(&self) -> String;
+
+    /// Waits for the `duration` without blocking, e.g. between status polls.
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'_, ()>;
+}
+
+/// Sender making the requests through a non-blocking host HTTP call, so the lapp UI is not frozen while waiting for
+/// responses or rate limiting delays. The requests go through the same pipeline as the ones of the wrapped
+/// `HttpSender`: its middleware, connectivity check, request budget, memory ceiling, fallback endpoints, timeout and
+/// retry policies, rate limiter, headers, trace id, observer, body builder and hook, response handling and
+/// statistics all apply.
+///
+/// The requests are not coalesced, since waiting for an identical request in flight would block the thread, and
+/// the transport and the sleeper of the `HttpSender` are replaced by the non-blocking `invoke` and `sleep`.
+pub struct AsyncHttpSender {
+    sender: HttpSender,
+    invoke: AsyncInvoke,
+    sleep: AsyncSleep,
+}
+
+impl AsyncHttpSender {
+    pub fn new(
+        sender: HttpSender,
+        invoke: impl Fn(HostHttpRequest) -> LocalBoxFuture<'static, std::result::Result<HostHttpResponse, String>> + 'static,
+        sleep: impl Fn(Duration) -> LocalBoxFuture<'static, ()> + 'static,
+    ) -> Self {
+        Self {
+            sender,
+            invoke: Box::new(invoke),
+            sleep: Box::new(sleep),
+        }
+    }
+
+    pub fn http_sender(&self) -> &HttpSender {
+        &self.sender
+    }
+
+    async fn send_request(&self, mut request: RpcRequest, mut params: Value) -> Result<Value> {
+        for middleware in &self.sender.middleware {
+            middleware.on_request(&mut request, &mut params)?;
+        }
+        let request_id = self.sender.next_request_id();
+        let request_json = self.sender.build_request_json(request_id, request, params).to_string();
+        let result = self.send_body(request, request_id, &request_json).await;
+        self.sender.apply_response_middleware(request, result)
+    }
+
+    async fn send_body(&self, request: RpcRequest, request_id: u64, request_json: &str) -> Result<Value> {
+        let retry_policy = self.sender.retry_policy_for(request);
+        let mut stats_updater = self.sender.start_send(request, request_json)?;
+        let mut result = Err(no_endpoint_error(request));
+        for url in self.sender.send_urls() {
+            self.sender.observe(|| RpcEvent::RequestStarted {
+                method: request.to_string(),
+                url: sanitize_url(&url).into_owned(),
+            });
+            result = self
+                .exchange(
+                    request,
+                    request_id,
+                    &url,
+                    request_json,
+                    retry_policy,
+                    &mut stats_updater,
+                )
+                .await;
+            if !matches!(&result, Err(err) if is_endpoint_failure(err)) {
+                break;
+            }
+        }
+        self.sender.finish_send(result, stats_updater, request_json)
+    }
+
+    async fn exchange(
+        &self,
+        request: RpcRequest,
+        request_id: u64,
+        url: &str,
+        request_json: &str,
+        retry_policy: &RetryPolicy,
+        stats_updater: &mut StatsUpdater<'_>,
+    ) -> Result<Value> {
+        let mut exchange =
+            self.sender
+                .start_exchange(request, request_id, url, request_json, retry_policy, stats_updater);
+        loop {
+            let delay = self.sender.before_attempt(&exchange, stats_updater)?;
+            if !delay.is_zero() {
+                (self.sleep)(delay).await;
+            }
+
+            let http_request = self.sender.attempt_request(&exchange, stats_updater);
+            let exchange_start_time = Instant::now();
+            let http_response = (self.invoke)(http_request).await;
+            let outcome = self.sender.after_attempt(
+                &mut exchange,
+                stats_updater,
+                http_response,
+                exchange_start_time,
+                HttpSender::parse_response,
+            )?;
+            match outcome {
+                AttemptOutcome::Parsed(result) => {
+                    if let Some(delay) = self.sender.artificial_delay {
+                        (self.sleep)(delay).await;
+                    }
+                    return Ok(result);
+                },
+                AttemptOutcome::Retry(delay) => (self.sleep)(delay).await,
+            }
+        }
+    }
+}
+
+impl AsyncRpcSender for AsyncHttpSender {
+    fn send(&self, request: RpcRequest, params: Value) -> LocalBoxFuture<'_, Result<Value>> {
+        Box::pin(self.send_request(request, params))
+    }
+
+    fn url(&self) -> String {
+        self.sender.url()
+    }
+
+    fn sleep(&self, duration: Duration) -> LocalBoxFuture<'_, ()> {
+        (self.sleep)(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    use serde_json::json;
+    use solana_client_api::{client_error::ClientErrorKind, rpc_sender::RpcSender};
+
+    use super::*;
+    use crate::{middleware::RenameMethod, observer::RpcEventObserver};
+
+    struct NoOpWaker;
+
+    impl Wake for NoOpWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls the future to completion, the futures of the tests never wait on a wakeup.
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        let waker = Waker::from(Arc::new(NoOpWaker));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    fn async_sender(
+        sender: HttpSender,
+        respond: impl Fn(&HostHttpRequest) -> HostHttpResponse + 'static,
+    ) -> (AsyncHttpSender, Rc<RefCell<Vec<HostHttpRequest>>>) {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let recorded_requests = requests.clone();
+        let async_sender = AsyncHttpSender::new(
+            sender,
+            move |request| {
+                let response = respond(&request);
+                recorded_requests.borrow_mut().push(request);
+                Box::pin(async move { Ok(response) })
+            },
+            |_| Box::pin(async {}),
+        );
+        (async_sender, requests)
+    }
+
+    fn response(status: u16, body: Value) -> HostHttpResponse {
+        HostHttpResponse {
+            status,
+            headers: Vec::new(),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    #[test]
+    fn requests_go_through_the_sender_pipeline() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let observer: Arc<dyn RpcEventObserver> = {
+            let events = events.clone();
+            Arc::new(move |event: &RpcEvent| events.lock().unwrap().push(event.clone()))
+        };
+        let sender = HttpSender::new("http://primary.test")
+            .with_fallback_urls(["http://fallback.test"])
+            .with_retry_policy(RetryPolicy::none())
+            .with_request_timeout(Duration::from_secs(10))
+            .with_trace_id("X-Trace-Id", || "trace".to_string())
+            .with_middleware(vec![Box::new(RenameMethod::new("getSlot", "getBlockHeight"))])
+            .with_observer(observer);
+        let (async_sender, requests) = async_sender(sender, |request| {
+            if request.url.contains("primary") {
+                response(503, Value::Null)
+            } else {
+                response(200, json!({"jsonrpc": "2.0", "id": 0, "result": 42}))
+            }
+        });
+
+        let result = block_on(async_sender.send(RpcRequest::GetSlot, Value::Null)).unwrap();
+        assert_eq!(result, json!(42));
+
+        let requests = requests.borrow();
+        let urls: Vec<_> = requests.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, ["http://primary.test", "http://fallback.test"]);
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["method"], "getBlockHeight");
+        assert!(requests[0]
+            .headers
+            .contains(&("X-Trace-Id".to_string(), "trace".to_string())));
+        assert!(requests[0].timeout.unwrap() <= Duration::from_secs(10));
+
+        let stats = async_sender.http_sender().get_transport_stats();
+        assert_eq!(stats.request_count, 1);
+        let started = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| matches!(event, RpcEvent::RequestStarted { .. }))
+            .count();
+        assert_eq!(started, 2);
+    }
+
+    #[test]
+    fn requests_are_checked_before_being_sent() {
+        let sender = HttpSender::new("http://primary.test").with_request_budget(1);
+        let (async_sender, requests) = async_sender(sender, |_| {
+            response(200, json!({"jsonrpc": "2.0", "id": 0, "result": 1}))
+        });
+
+        assert!(block_on(async_sender.send(RpcRequest::GetSlot, Value::Null)).is_ok());
+        let err = block_on(async_sender.send(RpcRequest::GetSlot, Value::Null)).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message.starts_with("BudgetExhausted:")));
+        assert_eq!(requests.borrow().len(), 1);
+
+        let sender = HttpSender::new("http://primary.test").with_connectivity_check(|| false);
+        let (async_sender, requests) = async_sender(sender, |_| response(200, Value::Null));
+        let err = block_on(async_sender.send(RpcRequest::GetSlot, Value::Null)).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message.starts_with("Offline:")));
+        assert!(requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn retries_wait_with_the_async_sleep() {
+        let sleeps = Rc::new(RefCell::new(Vec::new()));
+        let attempts = Rc::new(RefCell::new(0));
+        let async_sender = AsyncHttpSender::new(
+            HttpSender::new("http://primary.test"),
+            {
+                let attempts = attempts.clone();
+                move |_| {
+                    *attempts.borrow_mut() += 1;
+                    let http_response = if *attempts.borrow() == 1 {
+                        HostHttpResponse {
+                            status: 429,
+                            headers: vec![("Retry-After".to_string(), "3".to_string())],
+                            body: Vec::new(),
+                        }
+                    } else {
+                        response(200, json!({"jsonrpc": "2.0", "id": 0, "result": 7}))
+                    };
+                    Box::pin(async move { Ok(http_response) })
+                }
+            },
+            {
+                let sleeps = sleeps.clone();
+                move |duration| {
+                    sleeps.borrow_mut().push(duration);
+                    Box::pin(async {})
+                }
+            },
+        );
+
+        let result = block_on(async_sender.send(RpcRequest::GetSlot, Value::Null)).unwrap();
+        assert_eq!(result, json!(7));
+        assert_eq!(*sleeps.borrow(), [Duration::from_secs(3)]);
+        assert_eq!(
+            async_sender.http_sender().get_transport_stats().rate_limited_time,
+            Duration::from_secs(3)
+        );
+    }
+}
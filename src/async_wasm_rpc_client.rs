@@ -0,0 +1,278 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_request::{RpcError, RpcRequest},
+    rpc_response::{Response, RpcBlockhash},
+};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{self, Transaction},
+};
+use solana_transaction_status::{TransactionStatus, UiTransactionEncoding};
+
+use crate::{
+    async_sender::AsyncRpcSender,
+    wasm_rpc_client::{nonce_lifetime_commitment, unable_to_confirm_error, STATUS_POLL_INTERVAL},
+};
+
+/// Non-blocking counterpart of `WasmRpcClient` over an `AsyncRpcSender`, covering the calls a lapp usually awaits.
+pub struct AsyncWasmRpcClient {
+    sender: Box<dyn AsyncRpcSender>,
+    commitment: CommitmentConfig,
+    nonce_blockhash_commitment: CommitmentConfig,
+}
+
+impl AsyncWasmRpcClient {
+    pub fn new(sender: impl AsyncRpcSender + 'static, commitment: CommitmentConfig) -> Self {
+        Self {
+            sender: Box::new(sender),
+            commitment,
+            nonce_blockhash_commitment: CommitmentConfig::processed(),
+        }
+    }
+
+    /// Sets the commitment used to fetch the blockhash confirming durable-nonce transactions, as
+    /// `WasmRpcClient::with_nonce_blockhash_commitment` does.
+    pub fn with_nonce_blockhash_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.nonce_blockhash_commitment = commitment;
+        self
+    }
+
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    pub fn url(&self) -> String {
+        self.sender.url()
+    }
+
+    /// Sends the request and deserializes its result into `T`.
+    pub async fn send<T: DeserializeOwned>(&self, request: RpcRequest, params: Value) -> ClientResult<T> {
+        let result = self.sender.send(request, params).await?;
+        serde_json::from_value(result)
+            .map_err(|err| ClientError::new_with_request(ClientErrorKind::SerdeJson(err), request))
+    }
+
+    pub async fn get_slot(&self) -> ClientResult<u64> {
+        self.send(RpcRequest::GetSlot, json!([self.commitment])).await
+    }
+
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        let response: Response<u64> = self
+            .send(RpcRequest::GetBalance, json!([pubkey.to_string(), self.commitment]))
+            .await?;
+        Ok(response.value)
+    }
+
+    /// Fetches the account at `pubkey`, or `None` if it doesn't exist.
+    pub async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Option<Account>> {
+        let response: Response<Option<UiAccount>> = self
+            .send(
+                RpcRequest::GetAccountInfo,
+                json!([pubkey.to_string(), {
+                    "encoding": UiAccountEncoding::Base64,
+                    "commitment": self.commitment.commitment,
+                }]),
+            )
+            .await?;
+
+        response
+            .value
+            .map(|account| {
+                account
+                    .decode()
+                    .ok_or_else(|| RpcError::ParseError(format!("failed to decode account {}", pubkey)).into())
+            })
+            .transpose()
+    }
+
+    pub async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.get_latest_blockhash_with_commitment(self.commitment).await
+    }
+
+    pub async fn get_latest_blockhash_with_commitment(&self, commitment: CommitmentConfig) -> ClientResult<Hash> {
+        let response: Response<RpcBlockhash> = self.send(RpcRequest::GetLatestBlockhash, json!([commitment])).await?;
+        response
+            .value
+            .blockhash
+            .parse()
+            .map_err(|_| RpcError::ParseError(format!("invalid blockhash {}", response.value.blockhash)).into())
+    }
+
+    pub async fn is_blockhash_valid(&self, blockhash: &Hash, commitment: CommitmentConfig) -> ClientResult<bool> {
+        let response: Response<bool> = self
+            .send(RpcRequest::IsBlockhashValid, json!([blockhash.to_string(), commitment]))
+            .await?;
+        Ok(response.value)
+    }
+
+    /// Sends the transaction, with preflight checks at the client's commitment.
+    pub async fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let serialized = bincode::serialize(transaction)
+            .map_err(|err| ClientErrorKind::Custom(format!("transaction serialization failed: {}", err)))?;
+        let signature: String = self
+            .send(
+                RpcRequest::SendTransaction,
+                json!([base64::encode(serialized), {
+                    "encoding": UiTransactionEncoding::Base64,
+                    "preflightCommitment": self.commitment.commitment,
+                }]),
+            )
+            .await?;
+        signature
+            .parse()
+            .map_err(|_| RpcError::ParseError(format!("invalid transaction signature {}", signature)).into())
+    }
+
+    /// Returns the status of the transaction once it reaches the client's commitment.
+    pub async fn get_signature_status(&self, signature: &Signature) -> ClientResult<Option<transaction::Result<()>>> {
+        let response: Response<Vec<Option<TransactionStatus>>> = self
+            .send(RpcRequest::GetSignatureStatuses, json!([[signature.to_string()]]))
+            .await?;
+        Ok(response
+            .value
+            .into_iter()
+            .next()
+            .flatten()
+            .filter(|status| status.satisfies_commitment(self.commitment))
+            .map(|status| status.status))
+    }
+
+    /// Sends the transaction and polls its status until it reaches the client's commitment or its blockhash
+    /// expires, yielding to the host between the polls. As with `WasmRpcClient`, a durable nonce transaction waits on
+    /// the latest blockhash fetched at the nonce blockhash commitment.
+    pub async fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let signature = self.send_transaction(transaction).await?;
+        let recent_blockhash = match nonce_lifetime_commitment(transaction, self.nonce_blockhash_commitment) {
+            Some(commitment) => self.get_latest_blockhash_with_commitment(commitment).await?,
+            None => transaction.message.recent_blockhash,
+        };
+
+        loop {
+            match self.get_signature_status(&signature).await? {
+                Some(Ok(())) => return Ok(signature),
+                Some(Err(err)) => return Err(err.into()),
+                None => {
+                    if !self
+                        .is_blockhash_valid(&recent_blockhash, CommitmentConfig::processed())
+                        .await?
+                    {
+                        return Err(unable_to_confirm_error());
+                    }
+                    self.sender.sleep(STATUS_POLL_INTERVAL).await;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    use solana_sdk::{message::Message, system_instruction};
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    struct NoOpWaker;
+
+    impl Wake for NoOpWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<T>(future: impl Future<Output = T>) -> T {
+        let waker = Waker::from(Arc::new(NoOpWaker));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    fn context_response(value: Value) -> Value {
+        json!({"context": {"slot": 1}, "value": value})
+    }
+
+    fn transfer(recent_blockhash: Hash) -> Transaction {
+        let payer = Pubkey::new_unique();
+        let instruction = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let mut message = Message::new(&[instruction], Some(&payer));
+        message.recent_blockhash = recent_blockhash;
+        Transaction::new_unsigned(message)
+    }
+
+    #[test]
+    fn sent_transaction_is_polled_until_confirmed() {
+        let signature = Signature::new_unique();
+        let transaction = transfer(Hash::new_unique());
+        let mock = MockSender::new()
+            .with_response("sendTransaction", json!(signature.to_string()))
+            .with_response("isBlockhashValid", context_response(json!(true)))
+            .with_response(
+                "getSignatureStatuses",
+                context_response(json!([{
+                    "slot": 1,
+                    "confirmations": 0,
+                    "status": {"Ok": null},
+                    "err": null,
+                    "confirmationStatus": "confirmed",
+                }])),
+            );
+        mock.push_response("getSignatureStatuses", Ok(context_response(json!([null]))));
+        let client = AsyncWasmRpcClient::new(mock.clone(), CommitmentConfig::confirmed());
+
+        assert_eq!(
+            block_on(client.send_and_confirm_transaction(&transaction)).unwrap(),
+            signature
+        );
+        assert_eq!(mock.call_count("getSignatureStatuses"), 2);
+        mock.assert_called_with(
+            "isBlockhashValid",
+            &json!([transaction.message.recent_blockhash.to_string(), {"commitment": "processed"}]),
+        );
+        assert_eq!(mock.calls_to("sendTransaction")[0][1]["encoding"], "base64");
+    }
+
+    #[test]
+    fn durable_nonce_transaction_waits_on_the_latest_blockhash_at_the_nonce_commitment() {
+        let latest_blockhash = Hash::new_unique();
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let instruction = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let message = Message::new_with_nonce(vec![instruction], Some(&payer), &nonce_account, &payer);
+        let transaction = Transaction::new_unsigned(message);
+        let mock = MockSender::new()
+            .with_response("sendTransaction", json!(Signature::new_unique().to_string()))
+            .with_response(
+                "getLatestBlockhash",
+                context_response(json!({
+                    "blockhash": latest_blockhash.to_string(),
+                    "lastValidBlockHeight": 100,
+                })),
+            )
+            .with_response("getSignatureStatuses", context_response(json!([null])))
+            .with_response("isBlockhashValid", context_response(json!(false)));
+        let client = AsyncWasmRpcClient::new(mock.clone(), CommitmentConfig::confirmed())
+            .with_nonce_blockhash_commitment(CommitmentConfig::finalized());
+
+        assert!(block_on(client.send_and_confirm_transaction(&transaction)).is_err());
+        mock.assert_called_with("getLatestBlockhash", &json!([{"commitment": "finalized"}]));
+        mock.assert_called_with(
+            "isBlockhashValid",
+            &json!([latest_blockhash.to_string(), {"commitment": "processed"}]),
+        );
+    }
+}
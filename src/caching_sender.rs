@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use solana_client_api::{
+    client_error::Result,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+/// Default time a response of a cached method is reused for.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Default maximum number of cached responses.
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Sender caching the successful responses of idempotent reads of the wrapped sender. Only the methods with a TTL
+/// are cached, by default `getAccountInfo`, `getMultipleAccounts` and `getTokenAccountBalance`. The least recently
+/// used response is evicted when the cache is full.
+pub struct CachingSender<S> {
+    sender: S,
+    method_ttls: HashMap<String, Duration>,
+    max_entries: usize,
+    cache: Mutex<Cache>,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<(String, String), CacheEntry>,
+    /// Counter ordering the uses of the entries.
+    clock: u64,
+}
+
+struct CacheEntry {
+    value: Value,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+impl<S: RpcSender> CachingSender<S> {
+    pub fn new(sender: S) -> Self {
+        let method_ttls = [
+            RpcRequest::GetAccountInfo,
+            RpcRequest::GetMultipleAccounts,
+            RpcRequest::GetTokenAccountBalance,
+        ]
+        .iter()
+        .map(|request| (request.to_string(), DEFAULT_CACHE_TTL))
+        .collect();
+
+        Self {
+            sender,
+            method_ttls,
+            max_entries: DEFAULT_MAX_CACHE_ENTRIES,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    /// Caches the responses of `method` for `ttl`.
+    pub fn with_method_ttl(mut self, method: impl Into<String>, ttl: Duration) -> Self {
+        self.method_ttls.insert(method.into(), ttl);
+        self
+    }
+
+    /// Stops caching the responses of `method`.
+    pub fn without_method(mut self, method: &str) -> Self {
+        self.method_ttls.remove(method);
+        self
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    pub fn cached_count(&self) -> usize {
+        self.cache.lock().unwrap().entries.len()
+    }
+
+    /// Drops the cached response of the request.
+    pub fn invalidate(&self, request: RpcRequest, params: &Value) {
+        self.cache
+            .lock()
+            .unwrap()
+            .entries
+            .remove(&(request.to_string(), params.to_string()));
+    }
+
+    /// Drops the cached responses of `method`.
+    pub fn invalidate_method(&self, method: &str) {
+        self.cache
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|(entry_method, _), _| entry_method != method);
+    }
+
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().entries.clear();
+    }
+
+    fn cached(&self, key: &(String, String), ttl: Duration) -> Option<Value> {
+        let mut cache = self.cache.lock().unwrap();
+        cache.clock += 1;
+        let clock = cache.clock;
+        match cache.entries.get_mut(key) {
+            Some(entry) if entry.inserted_at.elapsed() < ttl => {
+                entry.last_used = clock;
+                Some(entry.value.clone())
+            },
+            Some(_) => {
+                cache.entries.remove(key);
+                None
+            },
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: (String, String), value: Value) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.entries.len() >= self.max_entries && !cache.entries.contains_key(&key) {
+            let least_recently_used = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                cache.entries.remove(&least_recently_used);
+            }
+        }
+
+        cache.clock += 1;
+        let last_used = cache.clock;
+        cache.entries.insert(key, CacheEntry {
+            value,
+            inserted_at: Instant::now(),
+            last_used,
+        });
+    }
+}
+
+impl<S: RpcSender> RpcSender for CachingSender<S> {
+    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        let ttl = match self.method_ttls.get(&request.to_string()) {
+            Some(ttl) => *ttl,
+            None => return self.sender.send(request, params),
+        };
+
+        let key = (request.to_string(), params.to_string());
+        if let Some(value) = self.cached(&key, ttl) {
+            return Ok(value);
+        }
+
+        let value = self.sender.send(request, params)?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.sender.get_transport_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    #[test]
+    fn cached_responses_are_reused_until_they_expire() {
+        let mock = MockSender::new()
+            .with_response("getAccountInfo", json!({"value": null}))
+            .with_response("getSlot", json!(10));
+        let sender = CachingSender::new(mock.clone()).with_method_ttl("getAccountInfo", Duration::from_millis(30));
+        let params = json!(["11111111111111111111111111111111"]);
+
+        for _ in 0..3 {
+            sender.send(RpcRequest::GetAccountInfo, params.clone()).unwrap();
+        }
+        assert_eq!(mock.call_count("getAccountInfo"), 1);
+        assert_eq!(sender.cached_count(), 1);
+
+        // Other params are another entry
+        sender.send(RpcRequest::GetAccountInfo, json!([])).unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 2);
+
+        // Uncached methods always reach the sender
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(mock.call_count("getSlot"), 2);
+
+        std::thread::sleep(Duration::from_millis(40));
+        sender.send(RpcRequest::GetAccountInfo, params.clone()).unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 3);
+
+        sender.invalidate(RpcRequest::GetAccountInfo, &params);
+        sender.send(RpcRequest::GetAccountInfo, params).unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 4);
+    }
+
+    #[test]
+    fn least_recently_used_response_is_evicted() {
+        let mock = MockSender::new().with_response("getAccountInfo", json!({"value": null}));
+        let sender = CachingSender::new(mock.clone()).with_max_entries(2);
+
+        for key in ["a", "b", "a", "c"] {
+            sender.send(RpcRequest::GetAccountInfo, json!([key])).unwrap();
+        }
+        assert_eq!(sender.cached_count(), 2);
+        assert_eq!(mock.call_count("getAccountInfo"), 3);
+
+        // "b" was evicted, "a" was kept
+        sender.send(RpcRequest::GetAccountInfo, json!(["a"])).unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 3);
+        sender.send(RpcRequest::GetAccountInfo, json!(["b"])).unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 4);
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client_api::{
+    client_error::{ClientError, Result as ClientResult},
+    rpc_request::{RpcError, RpcRequest, MAX_MULTIPLE_ACCOUNTS},
+    rpc_response::Response,
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::{async_sender::LocalBoxFuture, async_wasm_rpc_client::AsyncWasmRpcClient, wasm_rpc_client::WasmRpcClient};
+
+/// Configuration of `WasmRpcClient::get_accounts_chunked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedFetchConfig {
+    /// Number of accounts fetched per `getMultipleAccounts` call, capped by the RPC limit.
+    pub chunk_size: usize,
+
+    /// Whether all the chunks are sent in a single JSON-RPC batch, if the client has an HTTP sender.
+    pub batched: bool,
+
+    /// Commitment of the fetch, the client's commitment if `None`.
+    pub commitment: Option<CommitmentConfig>,
+}
+
+impl Default for ChunkedFetchConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: MAX_MULTIPLE_ACCOUNTS,
+            batched: false,
+            commitment: None,
+        }
+    }
+}
+
+impl ChunkedFetchConfig {
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn batched(mut self, batched: bool) -> Self {
+        self.batched = batched;
+        self
+    }
+
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    fn chunks<'a>(&self, pubkeys: &'a [Pubkey]) -> std::slice::Chunks<'a, Pubkey> {
+        pubkeys.chunks(self.chunk_size.clamp(1, MAX_MULTIPLE_ACCOUNTS))
+    }
+}
+
+impl WasmRpcClient {
+    /// Fetches the accounts at `pubkeys` in chunks of the `getMultipleAccounts` limit, returning the result for each
+    /// key in the input order. A failed chunk fails only the results of its keys.
+    pub fn get_accounts_chunked(
+        &self,
+        pubkeys: &[Pubkey],
+        config: ChunkedFetchConfig,
+    ) -> Vec<ClientResult<Option<Account>>> {
+        let commitment = config.commitment.unwrap_or_else(|| self.commitment());
+        let chunks = config.chunks(pubkeys);
+
+        if config.batched && self.http_sender().is_some() {
+            let batch = chunks.clone().fold(self.batch(), |batch, chunk| {
+                batch.add(RpcRequest::GetMultipleAccounts, chunk_params(chunk, commitment))
+            });
+            match batch.send() {
+                Ok(results) => {
+                    return chunks
+                        .zip(results)
+                        .flat_map(|(chunk, result)| {
+                            chunk_results(chunk, result.and_then(|result| parse_chunk(chunk, result)))
+                        })
+                        .collect()
+                },
+                Err(err) => {
+                    return chunks
+                        .flat_map(|chunk| chunk_results(chunk, Err(copy_error(&err))))
+                        .collect()
+                },
+            }
+        }
+
+        chunks
+            .flat_map(|chunk| {
+                let result = self
+                    .send(RpcRequest::GetMultipleAccounts, chunk_params(chunk, commitment))
+                    .and_then(|result| parse_chunk(chunk, result));
+                chunk_results(chunk, result)
+            })
+            .collect()
+    }
+}
+
+impl AsyncWasmRpcClient {
+    /// Non-blocking counterpart of `WasmRpcClient::get_accounts_chunked`, issuing the chunks concurrently.
+    /// Batching is not supported by the async sender, so `config.batched` is ignored.
+    pub async fn get_accounts_chunked(
+        &self,
+        pubkeys: &[Pubkey],
+        config: ChunkedFetchConfig,
+    ) -> Vec<ClientResult<Option<Account>>> {
+        let commitment = config.commitment.unwrap_or_else(|| self.commitment());
+        let chunks: Vec<_> = config.chunks(pubkeys).collect();
+        let requests: Vec<LocalBoxFuture<'_, ClientResult<Value>>> = chunks
+            .iter()
+            .map(|chunk| {
+                Box::pin(self.send::<Value>(RpcRequest::GetMultipleAccounts, chunk_params(chunk, commitment)))
+                    as LocalBoxFuture<'_, _>
+            })
+            .collect();
+
+        JoinAll::new(requests)
+            .await
+            .into_iter()
+            .zip(chunks)
+            .flat_map(|(result, chunk)| chunk_results(chunk, result.and_then(|result| parse_chunk(chunk, result))))
+            .collect()
+    }
+}
+
+fn chunk_params(chunk: &[Pubkey], commitment: CommitmentConfig) -> Value {
+    let pubkeys: Vec<_> = chunk.iter().map(Pubkey::to_string).collect();
+    json!([pubkeys, {
+        "encoding": UiAccountEncoding::Base64,
+        "commitment": commitment.commitment,
+    }])
+}
+
+fn parse_chunk(chunk: &[Pubkey], result: Value) -> ClientResult<Vec<ClientResult<Option<Account>>>> {
+    let response: Response<Vec<Option<UiAccount>>> = serde_json::from_value(result)
+        .map_err(|err| RpcError::ParseError(format!("invalid getMultipleAccounts result: {}", err)))?;
+    if response.value.len() != chunk.len() {
+        return Err(RpcError::ParseError(format!(
+            "getMultipleAccounts returned {} accounts for {} keys",
+            response.value.len(),
+            chunk.len()
+        ))
+        .into());
+    }
+
+    Ok(chunk
+        .iter()
+        .zip(response.value)
+        .map(|(pubkey, account)| {
+            account
+                .map(|account| {
+                    account
+                        .decode()
+                        .ok_or_else(|| RpcError::ParseError(format!("failed to decode account {}", pubkey)).into())
+                })
+                .transpose()
+        })
+        .collect())
+}
+
+/// Spreads the result of a chunk over its keys.
+fn chunk_results(
+    chunk: &[Pubkey],
+    result: ClientResult<Vec<ClientResult<Option<Account>>>>,
+) -> Vec<ClientResult<Option<Account>>> {
+    match result {
+        Ok(results) => results,
+        Err(err) => chunk
+            .iter()
+            .map(|pubkey| Err(RpcError::ForUser(format!("failed to fetch account {}: {}", pubkey, err)).into()))
+            .collect(),
+    }
+}
+
+/// Copies the description of an error shared by several results, since client errors can't be cloned.
+fn copy_error(err: &ClientError) -> ClientError {
+    RpcError::ForUser(err.to_string()).into()
+}
+
+/// Future awaiting all the `futures` concurrently, resolving to their outputs in order.
+struct JoinAll<'a, T> {
+    futures: Vec<Option<LocalBoxFuture<'a, T>>>,
+    outputs: Vec<Option<T>>,
+}
+
+impl<'a, T> JoinAll<'a, T> {
+    fn new(futures: Vec<LocalBoxFuture<'a, T>>) -> Self {
+        let outputs = futures.iter().map(|_| None).collect();
+        Self {
+            futures: futures.into_iter().map(Some).collect(),
+            outputs,
+        }
+    }
+}
+
+impl<T> Future for JoinAll<'_, T> {
+    type Output = Vec<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Only boxed futures and plain outputs are stored, so the join is `Unpin`
+        let this = self.get_mut();
+        for (future, output) in this.futures.iter_mut().zip(&mut this.outputs) {
+            if let Some(pending) = future {
+                if let Poll::Ready(value) = pending.as_mut().poll(cx) {
+                    *output = Some(value);
+                    *future = None;
+                }
+            }
+        }
+
+        if this.futures.iter().all(Option::is_none) {
+            Poll::Ready(this.outputs.iter_mut().map(|output| output.take().unwrap()).collect())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+
+    use super::*;
+    use crate::mock_sender::{MockError, MockSender};
+
+    fn accounts_response(accounts: Vec<Value>) -> Value {
+        json!({"context": {"slot": 1}, "value": accounts})
+    }
+
+    #[test]
+    fn keys_are_fetched_in_chunks_of_the_rpc_limit() {
+        let owner = Pubkey::new_unique();
+        let pubkeys: Vec<_> = (0..250).map(|_| Pubkey::new_unique()).collect();
+        let mut last_chunk = vec![Value::Null; 50];
+        last_chunk[49] = json!({
+            "lamports": 7,
+            "data": ["", "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        });
+        let mock = MockSender::new();
+        mock.push_response("getMultipleAccounts", Ok(accounts_response(vec![Value::Null; 100])));
+        mock.push_response(
+            "getMultipleAccounts",
+            Err(MockError::Transport("connection reset".to_string())),
+        );
+        mock.push_response("getMultipleAccounts", Ok(accounts_response(last_chunk)));
+        let client = WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ));
+
+        let results = client.get_accounts_chunked(&pubkeys, ChunkedFetchConfig::default());
+        let chunk_sizes: Vec<_> = mock
+            .calls_to("getMultipleAccounts")
+            .iter()
+            .map(|params| params[0].as_array().unwrap().len())
+            .collect();
+        assert_eq!(chunk_sizes, [100, 100, 50]);
+        assert_eq!(mock.calls_to("getMultipleAccounts")[2][0][0], pubkeys[200].to_string());
+
+        assert_eq!(results.len(), 250);
+        assert!(results[..100].iter().all(|result| matches!(result, Ok(None))));
+        // Only the keys of the failed chunk fail
+        assert!(results[100..200].iter().all(Result::is_err));
+        assert!(results[200..249].iter().all(|result| matches!(result, Ok(None))));
+        assert_eq!(results[249].as_ref().unwrap().as_ref().unwrap().lamports, 7);
+    }
+
+    #[test]
+    fn results_of_the_wrong_length_fail_their_chunk() {
+        let pubkeys: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mock = MockSender::new().with_response("getMultipleAccounts", accounts_response(vec![Value::Null]));
+        let client = WasmRpcClient::new(RpcClient::new_sender(
+            mock,
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ));
+
+        let results = client.get_accounts_chunked(&pubkeys, ChunkedFetchConfig::default().chunk_size(2));
+        assert!(results[..2].iter().all(Result::is_err));
+        assert!(matches!(results[2], Ok(None)));
+    }
+}
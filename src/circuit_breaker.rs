@@ -0,0 +1,308 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind, Result},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+use crate::{is_endpoint_failure, wasm_rpc_client::is_node_unhealthy};
+
+/// Default number of consecutive failures after which the circuit opens.
+pub const DEFAULT_TRIP_THRESHOLD: u32 = 5;
+
+/// Default time the requests fail fast after the circuit opens, before the endpoint is probed.
+pub const DEFAULT_OPEN_COOLDOWN: Duration = Duration::from_secs(15);
+
+/// State of the circuit of a `CircuitBreakerSender`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// The endpoint is considered available, the requests are sent.
+    Closed,
+
+    /// The endpoint is considered unavailable, the requests fail without being sent.
+    Open,
+
+    /// The cooldown has passed and the endpoint is being probed with `getHealth`.
+    HalfOpen,
+}
+
+/// State of the circuit along with the failures leading to it, see `CircuitBreakerSender::status`.
+#[derive(Debug, Clone)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+
+    /// Time the circuit was last opened, `None` while it is closed.
+    pub opened_at: Option<Instant>,
+
+    /// Time left until the endpoint is probed, `None` unless the circuit is open.
+    pub retry_in: Option<Duration>,
+}
+
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl Circuit {
+    /// Returns the new state if it differs from the current one.
+    fn transition(&mut self, state: CircuitState) -> Option<CircuitState> {
+        (self.state != state).then(|| {
+            self.state = state;
+            state
+        })
+    }
+}
+
+/// Sender failing fast while the endpoint of the wrapped sender is unavailable, instead of waiting through the full
+/// retry cycles of every request. The circuit opens after a number of consecutive transport failures or
+/// `NodeUnhealthy` responses. Once the cooldown passes, the next request probes the endpoint with `getHealth` and the
+/// circuit closes if the probe succeeds, or stays open for another cooldown otherwise.
+pub struct CircuitBreakerSender<S> {
+    sender: S,
+    trip_threshold: u32,
+    open_cooldown: Duration,
+    on_state_change: Option<Box<dyn Fn(CircuitState) + Send + Sync>>,
+    circuit: Mutex<Circuit>,
+}
+
+impl<S: RpcSender> CircuitBreakerSender<S> {
+    pub fn new(sender: S) -> Self {
+        Self {
+            sender,
+            trip_threshold: DEFAULT_TRIP_THRESHOLD,
+            open_cooldown: DEFAULT_OPEN_COOLDOWN,
+            on_state_change: None,
+            circuit: Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Sets the number of consecutive failures after which the circuit opens.
+    pub fn with_trip_threshold(mut self, trip_threshold: u32) -> Self {
+        self.trip_threshold = trip_threshold.max(1);
+        self
+    }
+
+    /// Sets the time the requests fail fast after the circuit opens.
+    pub fn with_open_cooldown(mut self, open_cooldown: Duration) -> Self {
+        self.open_cooldown = open_cooldown;
+        self
+    }
+
+    /// Sets a callback receiving the new state of the circuit on each transition, e.g. to show that the RPC is
+    /// unavailable.
+    pub fn with_on_state_change(mut self, on_state_change: impl Fn(CircuitState) + Send + Sync + 'static) -> Self {
+        self.on_state_change = Some(Box::new(on_state_change));
+        self
+    }
+
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.circuit.lock().unwrap().state
+    }
+
+    /// Returns `false` while the requests fail without being sent.
+    pub fn is_available(&self) -> bool {
+        self.state() == CircuitState::Closed
+    }
+
+    pub fn status(&self) -> CircuitStatus {
+        let circuit = self.circuit.lock().unwrap();
+        let retry_in = match (circuit.state, circuit.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => Some(self.open_cooldown.saturating_sub(opened_at.elapsed())),
+            _ => None,
+        };
+
+        CircuitStatus {
+            state: circuit.state,
+            consecutive_failures: circuit.consecutive_failures,
+            opened_at: circuit.opened_at,
+            retry_in,
+        }
+    }
+
+    /// Closes the circuit, e.g. when the host regains connectivity.
+    pub fn reset(&self) {
+        self.record_success();
+    }
+
+    /// Notifies of the state change outside of the circuit lock, so the callback can query the sender.
+    fn notify(&self, changed: Option<CircuitState>) {
+        if let (Some(state), Some(on_state_change)) = (changed, &self.on_state_change) {
+            on_state_change(state);
+        }
+    }
+
+    /// Returns whether the endpoint should be probed before sending, or an error if the request should fail fast.
+    fn check_circuit(&self, request: RpcRequest) -> Result<bool> {
+        let mut circuit = self.circuit.lock().unwrap();
+        match circuit.state {
+            CircuitState::Closed => Ok(false),
+            CircuitState::Open => {
+                let elapsed = circuit
+                    .opened_at
+                    .map_or(self.open_cooldown, |opened_at| opened_at.elapsed());
+                if elapsed < self.open_cooldown {
+                    return Err(circuit_open_error(request, Some(self.open_cooldown - elapsed)));
+                }
+                let changed = circuit.transition(CircuitState::HalfOpen);
+                drop(circuit);
+                self.notify(changed);
+                Ok(true)
+            },
+            // Another request is probing the endpoint
+            CircuitState::HalfOpen => Err(circuit_open_error(request, None)),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.consecutive_failures = 0;
+        circuit.opened_at = None;
+        let changed = circuit.transition(CircuitState::Closed);
+        drop(circuit);
+        self.notify(changed);
+    }
+
+    fn record_failure(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        circuit.consecutive_failures += 1;
+        let mut changed = None;
+        if circuit.state == CircuitState::HalfOpen || circuit.consecutive_failures >= self.trip_threshold {
+            // Restarts the cooldown of an endpoint failing again when probed
+            circuit.opened_at = Some(Instant::now());
+            changed = circuit.transition(CircuitState::Open);
+        }
+        drop(circuit);
+        self.notify(changed);
+    }
+}
+
+impl<S: RpcSender> RpcSender for CircuitBreakerSender<S> {
+    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        if self.check_circuit(request)? {
+            match self.sender.send(RpcRequest::GetHealth, Value::Null) {
+                Ok(_) => self.record_success(),
+                Err(_) => {
+                    self.record_failure();
+                    return Err(circuit_open_error(request, Some(self.open_cooldown)));
+                },
+            }
+        }
+
+        let result = self.sender.send(request, params);
+        match &result {
+            Err(err) if trips_circuit(err) => self.record_failure(),
+            _ => self.record_success(),
+        }
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.sender.get_transport_stats()
+    }
+}
+
+/// Returns `true` if the error is a sign of the endpoint being unavailable.
+fn trips_circuit(err: &ClientError) -> bool {
+    is_node_unhealthy(err) || is_endpoint_failure(err)
+}
+
+fn circuit_open_error(request: RpcRequest, retry_in: Option<Duration>) -> ClientError {
+    let message = match retry_in {
+        Some(retry_in) => format!(
+            "CircuitOpen: the RPC endpoint is unavailable, retrying in {:?}",
+            retry_in
+        ),
+        None => "CircuitOpen: the RPC endpoint is unavailable, it is being probed".to_string(),
+    };
+    ClientError::new_with_request(ClientErrorKind::Custom(message), request)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::mock_sender::{MockError, MockSender};
+
+    fn unreachable() -> MockError {
+        MockError::Transport("connection refused".to_string())
+    }
+
+    #[test]
+    fn circuit_trips_after_consecutive_failures_and_recovers_when_probed() {
+        let mock = MockSender::new()
+            .with_response("getSlot", json!(10))
+            .with_response("getHealth", json!("ok"));
+        mock.push_response("getSlot", Err(unreachable()));
+        mock.push_response("getSlot", Err(MockError::Status(503)));
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let breaker = CircuitBreakerSender::new(mock.clone())
+            .with_trip_threshold(2)
+            .with_open_cooldown(Duration::from_millis(30))
+            .with_on_state_change({
+                let states = states.clone();
+                move |state| states.lock().unwrap().push(state)
+            });
+
+        assert!(breaker.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.status().consecutive_failures, 2);
+
+        // Fails fast during the cooldown
+        let err = breaker.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(err.to_string().contains("CircuitOpen"), "{}", err);
+        assert_eq!(mock.call_count("getSlot"), 2);
+        assert!(breaker.status().retry_in.is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(breaker.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(10));
+        assert_eq!(mock.call_count("getHealth"), 1);
+        assert!(breaker.is_available());
+        assert_eq!(breaker.status().consecutive_failures, 0);
+        assert_eq!(*states.lock().unwrap(), [
+            CircuitState::Open,
+            CircuitState::HalfOpen,
+            CircuitState::Closed
+        ]);
+    }
+
+    #[test]
+    fn failed_probe_reopens_the_circuit() {
+        let mock = MockSender::new()
+            .with_response("getSlot", json!(10))
+            .with_error("getHealth", MockError::rpc(-32005, "Node is unhealthy"));
+        mock.push_response("getSlot", Err(unreachable()));
+        let breaker = CircuitBreakerSender::new(mock.clone())
+            .with_trip_threshold(1)
+            .with_open_cooldown(Duration::ZERO);
+
+        assert!(breaker.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let err = breaker.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(err.to_string().contains("CircuitOpen"), "{}", err);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(mock.call_count("getHealth"), 1);
+        // The probe failed, so the request itself wasn't sent
+        assert_eq!(mock.call_count("getSlot"), 1);
+    }
+}
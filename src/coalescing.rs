@@ -0,0 +1,176 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+};
+
+use serde_json::Value;
+use solana_client_api::client_error::Result;
+
+/// Methods not coalesced by default, since each call has an effect of its own.
+pub const DEFAULT_NON_COALESCED_METHODS: &[&str] = &["sendTransaction", "requestAirdrop"];
+
+/// Registry of the requests in flight, letting concurrent identical requests share a single round trip.
+#[derive(Default)]
+pub(crate) struct RequestCoalescer {
+    in_flight: Mutex<HashMap<(String, String), Arc<InFlight>>>,
+}
+
+/// Request in flight, `None` until completed, then `Some(None)` if it failed.
+#[derive(Default)]
+struct InFlight {
+    result: Mutex<Option<Option<Value>>>,
+    completed: Condvar,
+}
+
+impl RequestCoalescer {
+    /// Runs `send` unless an identical request is already in flight, in which case its result is awaited
+    /// instead. Errors are not shared, the callers waiting for a failed request run `send` themselves.
+    pub(crate) fn run(&self, method: String, params: &Value, send: impl FnOnce() -> Result<Value>) -> Result<Value> {
+        let key = (method, params.to_string());
+        let (in_flight, leader) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(request) => (Arc::clone(request), false),
+                None => {
+                    let request = Arc::new(InFlight::default());
+                    in_flight.insert(key.clone(), Arc::clone(&request));
+                    (request, true)
+                },
+            }
+        };
+
+        if !leader {
+            let mut result = in_flight.result.lock().unwrap();
+            while result.is_none() {
+                result = in_flight.completed.wait(result).unwrap();
+            }
+            return match result.clone().flatten() {
+                Some(value) => Ok(value),
+                None => {
+                    drop(result);
+                    send()
+                },
+            };
+        }
+
+        // The waiting callers are released even if the request panics
+        let result = panic::catch_unwind(AssertUnwindSafe(send));
+        self.in_flight.lock().unwrap().remove(&key);
+        // Once removed from the registry, the request is only referenced by the callers waiting for it, the result is
+        // cloned for them only if there are some
+        if Arc::strong_count(&in_flight) > 1 {
+            *in_flight.result.lock().unwrap() =
+                Some(result.as_ref().ok().and_then(|result| result.as_ref().ok().cloned()));
+            in_flight.completed.notify_all();
+        }
+        result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    use serde_json::json;
+    use solana_client_api::{client_error::ClientErrorKind, rpc_request::RpcError};
+
+    use super::*;
+
+    /// Waits until `waiters` callers wait for the request in flight with the `params`.
+    fn wait_for_waiters(coalescer: &RequestCoalescer, params: &Value, waiters: usize) {
+        let key = ("getSlot".to_string(), params.to_string());
+        // The registry and the leader hold the other references
+        while coalescer
+            .in_flight
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map_or(0, |request| Arc::strong_count(request) - 2)
+            < waiters
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn concurrent_identical_requests_share_the_result() {
+        let coalescer = RequestCoalescer::default();
+        let params = json!([{"commitment": "finalized"}]);
+        let sends = AtomicUsize::new(0);
+        let send = || {
+            sends.fetch_add(1, Ordering::SeqCst);
+            Ok(json!(42))
+        };
+
+        thread::scope(|scope| {
+            let leader = scope.spawn(|| {
+                coalescer.run("getSlot".to_string(), &params, || {
+                    wait_for_waiters(&coalescer, &params, 2);
+                    send()
+                })
+            });
+            while coalescer.in_flight.lock().unwrap().is_empty() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let followers: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| coalescer.run("getSlot".to_string(), &params, send)))
+                .collect();
+
+            assert_eq!(leader.join().unwrap().unwrap(), json!(42));
+            for follower in followers {
+                assert_eq!(follower.join().unwrap().unwrap(), json!(42));
+            }
+        });
+
+        assert_eq!(sends.load(Ordering::SeqCst), 1);
+        assert!(coalescer.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors_are_not_shared() {
+        let coalescer = RequestCoalescer::default();
+        let params = json!([]);
+        let sends = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let leader = scope.spawn(|| {
+                coalescer.run("getSlot".to_string(), &params, || {
+                    wait_for_waiters(&coalescer, &params, 1);
+                    sends.fetch_add(1, Ordering::SeqCst);
+                    Err(RpcError::ForUser("503 Service Unavailable".to_string()).into())
+                })
+            });
+            while coalescer.in_flight.lock().unwrap().is_empty() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let follower = scope.spawn(|| {
+                coalescer.run("getSlot".to_string(), &params, || {
+                    sends.fetch_add(1, Ordering::SeqCst);
+                    Ok(json!(7))
+                })
+            });
+
+            assert!(matches!(
+                leader.join().unwrap().unwrap_err().kind(),
+                ClientErrorKind::RpcError(RpcError::ForUser(_))
+            ));
+            assert_eq!(follower.join().unwrap().unwrap(), json!(7));
+        });
+
+        assert_eq!(sends.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn lone_request_is_not_kept() {
+        let coalescer = RequestCoalescer::default();
+        let result = coalescer.run("getSlot".to_string(), &json!([]), || Ok(json!(1)));
+
+        assert_eq!(result.unwrap(), json!(1));
+        assert!(coalescer.in_flight.lock().unwrap().is_empty());
+    }
+}
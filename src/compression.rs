@@ -0,0 +1,82 @@
+use std::io::{self, Read};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+/// Value of the `Accept-Encoding` header sent when the response compression is enabled.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate";
+
+/// Decompresses the response `body` of the `content_encoding`, failing once it decompresses to more than `limit`
+/// bytes. Returns `None` if the body is not compressed, e.g. when the host has already decompressed it but kept the
+/// header, or if the encoding is not supported.
+pub(crate) fn decompress(content_encoding: &str, body: &[u8], limit: usize) -> io::Result<Option<Vec<u8>>> {
+    let decompressed = match content_encoding.trim().to_ascii_lowercase().as_str() {
+        "gzip" | "x-gzip" if body.starts_with(&[0x1f, 0x8b]) => read_limited(GzDecoder::new(body), limit)?,
+        // Zlib wrapped as the standard requires, JSON starts with a byte that is not a valid zlib header
+        "deflate" if is_zlib_header(body) => read_limited(ZlibDecoder::new(body), limit)?,
+        // Raw deflate sent by some servers instead
+        "deflate" if !starts_as_json(body) => read_limited(DeflateDecoder::new(body), limit)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(decompressed))
+}
+
+/// Reads the `decoder` to the end, stopping at the first byte past the `limit` so that a small body can't expand
+/// into an unbounded allocation.
+fn read_limited(decoder: impl Read, limit: usize) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    decoder
+        .take(limit.saturating_add(1) as u64)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the response body decompresses to more than {} bytes", limit),
+        ));
+    }
+    Ok(decompressed)
+}
+
+fn is_zlib_header(body: &[u8]) -> bool {
+    match body {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+fn starts_as_json(body: &[u8]) -> bool {
+    matches!(
+        body.iter().find(|byte| !byte.is_ascii_whitespace()),
+        Some(b'{' | b'[') | None
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn bodies_decompressing_past_the_limit_are_rejected() {
+        let json = br#"{"jsonrpc":"2.0","id":0,"result":"ok"}"#;
+        let body = gzip(json);
+
+        assert_eq!(decompress("gzip", &body, json.len()).unwrap().unwrap(), json);
+        let err = decompress("gzip", &body, json.len() - 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A megabyte of zeros compresses to about a kilobyte
+        let body = gzip(&vec![0; 1 << 20]);
+        assert!(body.len() < 4096);
+        assert!(decompress("gzip", &body, 1 << 16).is_err());
+        assert!(decompress("gzip", json, 1 << 16).unwrap().is_none());
+    }
+}
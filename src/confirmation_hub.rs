@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use solana_client_api::{
+    client_error::Result as ClientResult, rpc_client::RpcClient, rpc_request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
+};
+use solana_sdk::{signature::Signature, transaction::Result as TransactionResult};
+use solana_transaction_status::TransactionStatus;
+
+/// Shared poller of signature statuses, coalescing the confirmation polls of concurrent sends and of the
+/// `ConfirmationWatcher`s into batched `getSignatureStatuses` calls.
+pub struct ConfirmationHub {
+    poll_interval: Duration,
+    state: Mutex<HubState>,
+}
+
+#[derive(Default)]
+struct HubState {
+    /// Registered signatures with their last fetched statuses, `None` until processed.
+    statuses: HashMap<Signature, Option<TransactionStatus>>,
+    /// End of the last successful poll.
+    last_poll: Option<Instant>,
+    /// Whether a poll is fetching the statuses, the other callers meanwhile getting the last fetched ones.
+    polling: bool,
+}
+
+impl ConfirmationHub {
+    /// Creates a hub polling statuses at most once per `poll_interval`.
+    pub fn new(poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            state: Mutex::new(HubState::default()),
+        }
+    }
+
+    /// Number of registered signatures, whose statuses are fetched by each poll.
+    pub fn pending_count(&self) -> usize {
+        self.state.lock().unwrap().statuses.len()
+    }
+
+    /// Registers `signature`, so its status is fetched by the next polls.
+    pub fn register(&self, signature: Signature) {
+        self.state.lock().unwrap().statuses.entry(signature).or_insert(None);
+    }
+
+    /// Returns the status of `signature` once it is processed at the client's commitment, registering it if needed.
+    /// The signature is unregistered once its status is returned.
+    pub fn signature_status(
+        &self,
+        client: &RpcClient,
+        signature: &Signature,
+    ) -> ClientResult<Option<TransactionResult<()>>> {
+        let status = self
+            .statuses(client, &[*signature])?
+            .remove(0)
+            .filter(|status| status.satisfies_commitment(client.commitment()));
+        if status.is_some() {
+            self.unregister(signature);
+        }
+        Ok(status.map(|status| status.status))
+    }
+
+    /// Returns the last fetched statuses of the `signatures`, registering them if needed. Statuses of all registered
+    /// signatures are fetched at once if the poll interval has elapsed since the last successful poll and no other
+    /// poll is in progress, otherwise the result of the last poll is used. The signatures stay registered until
+    /// `unregister` is called.
+    pub fn statuses(
+        &self,
+        client: &RpcClient,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        let mut state = self.state.lock().unwrap();
+        for signature in signatures {
+            state.statuses.entry(*signature).or_insert(None);
+        }
+
+        let poll_due = !state.polling
+            && state
+                .last_poll
+                .map_or(true, |last_poll| last_poll.elapsed() >= self.poll_interval);
+        if poll_due {
+            state.polling = true;
+            let registered: Vec<_> = state.statuses.keys().copied().collect();
+            // The statuses are fetched without holding the lock, so concurrent callers aren't blocked by the request
+            drop(state);
+            let fetched = fetch_statuses(client, &registered);
+
+            state = self.state.lock().unwrap();
+            state.polling = false;
+            for (signature, status) in fetched? {
+                // Signatures unregistered during the poll stay unregistered
+                if let Some(registered_status) = state.statuses.get_mut(&signature) {
+                    *registered_status = status;
+                }
+            }
+            state.last_poll = Some(Instant::now());
+        }
+
+        Ok(signatures
+            .iter()
+            .map(|signature| state.statuses.get(signature).cloned().flatten())
+            .collect())
+    }
+
+    /// Stops tracking `signature`, e.g. when its blockhash expired.
+    pub fn unregister(&self, signature: &Signature) {
+        self.state.lock().unwrap().statuses.remove(signature);
+    }
+}
+
+fn fetch_statuses(
+    client: &RpcClient,
+    signatures: &[Signature],
+) -> ClientResult<Vec<(Signature, Option<TransactionStatus>)>> {
+    let mut fetched = Vec::with_capacity(signatures.len());
+    for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+        let statuses = client.get_signature_statuses(chunk)?.value;
+        fetched.extend(chunk.iter().copied().zip(statuses));
+    }
+    Ok(fetched)
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use solana_client_api::client_error::Result as ClientResult;
+use solana_sdk::{
+    clock::{Slot, MAX_PROCESSING_AGE},
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    transaction::TransactionError,
+};
+
+use crate::{
+    confirmation_hub::ConfirmationHub,
+    wasm_rpc_client::{TrackedSignature, WasmRpcClient},
+};
+
+/// Default interval between the status polls of a `ConfirmationWatcher`.
+pub const DEFAULT_WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Final state of a signature watched by a `ConfirmationWatcher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchOutcome {
+    /// The transaction reached the commitment of the watcher.
+    Confirmed { slot: Slot },
+
+    /// The transaction was executed and failed.
+    Failed(TransactionError),
+
+    /// The blockhash of the transaction expired before it was processed, it will never land.
+    Expired,
+
+    /// The transaction was neither confirmed nor expired within the watcher's timeout.
+    TimedOut,
+}
+
+struct Watched {
+    last_valid_block_height: u64,
+    watched_since: Instant,
+}
+
+/// Watcher confirming many signatures at once, e.g. the transactions of a multi-step swap. Each poll fetches the
+/// statuses of all the pending signatures with batched `getSignatureStatuses` calls and, while some are not
+/// processed yet, the block height to detect the expired ones. Completions are reported as each signature settles.
+///
+/// The statuses are polled through the client's `ConfirmationHub` if it has one, so the watched signatures share the
+/// batched calls with the confirmations of concurrent sends, and a poll within the hub's interval reuses the
+/// statuses of the last one. Otherwise the watcher polls through a hub of its own.
+pub struct ConfirmationWatcher<'a> {
+    client: &'a WasmRpcClient,
+    hub: Arc<ConfirmationHub>,
+    commitment: CommitmentConfig,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+    on_complete: Option<Box<dyn FnMut(&Signature, &WatchOutcome) + 'a>>,
+    pending: HashMap<Signature, Watched>,
+}
+
+impl<'a> ConfirmationWatcher<'a> {
+    pub fn new(client: &'a WasmRpcClient) -> Self {
+        Self {
+            client,
+            hub: client
+                .confirmation_hub()
+                .cloned()
+                .unwrap_or_else(|| Arc::new(ConfirmationHub::new(Duration::ZERO))),
+            commitment: client.commitment(),
+            poll_interval: DEFAULT_WATCH_POLL_INTERVAL,
+            timeout: None,
+            on_complete: None,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Sets the commitment the signatures are confirmed at, the client's one by default.
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the time a signature is watched for at most, in addition to its blockhash validity.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a callback receiving each signature with its outcome as soon as it settles.
+    pub fn with_on_complete(mut self, on_complete: impl FnMut(&Signature, &WatchOutcome) + 'a) -> Self {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    /// Watches the signature of a transaction whose blockhash is valid up to the `last_valid_block_height`.
+    pub fn watch(&mut self, signature: Signature, last_valid_block_height: u64) {
+        self.hub.register(signature);
+        self.pending.insert(signature, Watched {
+            last_valid_block_height,
+            watched_since: Instant::now(),
+        });
+    }
+
+    /// Watches the tracked transaction. Its blockhash is assumed to be valid for the maximum processing age from
+    /// now, so it may be reported as expired later than it actually expires, but never earlier.
+    pub fn watch_tracked(&mut self, tracked: &TrackedSignature) -> ClientResult<()> {
+        let block_height = self.client.get_block_height_with_commitment(self.commitment)?;
+        self.watch(tracked.signature, block_height + MAX_PROCESSING_AGE as u64);
+        Ok(())
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Fetches the statuses of the pending signatures once and returns the ones that settled.
+    pub fn poll(&mut self) -> ClientResult<Vec<(Signature, WatchOutcome)>> {
+        let signatures: Vec<_> = self.pending.keys().copied().collect();
+        let statuses = self.hub.statuses(self.client, &signatures)?;
+        let mut completed = Vec::new();
+        let mut unprocessed = Vec::new();
+
+        for (signature, status) in signatures.into_iter().zip(statuses) {
+            match status {
+                Some(status) => match &status.err {
+                    Some(err) => completed.push((signature, WatchOutcome::Failed(err.clone()))),
+                    None if status.satisfies_commitment(self.commitment) => {
+                        completed.push((signature, WatchOutcome::Confirmed { slot: status.slot }))
+                    },
+                    // Once processed, the transaction no longer depends on the blockhash validity
+                    None => {},
+                },
+                None => unprocessed.push(signature),
+            }
+        }
+
+        if !unprocessed.is_empty() {
+            let block_height = self.client.get_block_height_with_commitment(self.commitment)?;
+            for signature in unprocessed {
+                if block_height > self.pending[&signature].last_valid_block_height {
+                    completed.push((signature, WatchOutcome::Expired));
+                }
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            for (signature, watched) in &self.pending {
+                let is_completed = completed.iter().any(|(completed, _)| completed == signature);
+                if !is_completed && watched.watched_since.elapsed() >= timeout {
+                    completed.push((*signature, WatchOutcome::TimedOut));
+                }
+            }
+        }
+
+        for (signature, outcome) in &completed {
+            self.pending.remove(signature);
+            self.settle(signature, outcome);
+        }
+        Ok(completed)
+    }
+
+    /// Polls until all the watched signatures settle and returns their outcomes. Stops with an error if the
+    /// client's confirmation is cancelled.
+    pub fn run(mut self) -> ClientResult<HashMap<Signature, WatchOutcome>> {
+        let mut outcomes = HashMap::new();
+        while !self.pending.is_empty() {
+            self.client.check_overall_deadline(None)?;
+            outcomes.extend(self.poll()?);
+            if !self.pending.is_empty() {
+                self.client.sleep(self.poll_interval);
+            }
+        }
+        Ok(outcomes)
+    }
+
+    fn settle(&mut self, signature: &Signature, outcome: &WatchOutcome) {
+        self.hub.unregister(signature);
+        let confirmed: Option<ClientResult<bool>> = match outcome {
+            WatchOutcome::Confirmed { .. } => Some(Ok(true)),
+            WatchOutcome::Expired => Some(Ok(false)),
+            WatchOutcome::Failed(err) => Some(Err(err.clone().into())),
+            // Still may land, so it stays pending in the signature ledger
+            WatchOutcome::TimedOut => None,
+        };
+        if let Some(confirmed) = confirmed {
+            self.client.settle_pending(signature, &confirmed);
+        }
+
+        if let Some(on_complete) = &mut self.on_complete {
+            on_complete(signature, outcome);
+        }
+    }
+}
+
+impl Drop for ConfirmationWatcher<'_> {
+    /// Stops polling the signatures left pending, e.g. after a failed poll or a timeout.
+    fn drop(&mut self) {
+        for signature in self.pending.keys() {
+            self.hub.unregister(signature);
+        }
+    }
+}
+
+impl WasmRpcClient {
+    /// Returns a watcher confirming many signatures with batched status polls.
+    pub fn confirmation_watcher(&self) -> ConfirmationWatcher<'_> {
+        ConfirmationWatcher::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::{json, Value};
+    use solana_client_api::rpc_client::RpcClientConfig;
+    use solana_sdk::instruction::InstructionError;
+
+    use super::*;
+    use crate::{transport::HostHttpResponse, HttpSender};
+
+    /// Client answering each request with the result computed by `respond` from the method and the params.
+    fn client(respond: impl Fn(&str, &Value) -> Value + Send + Sync + 'static) -> WasmRpcClient {
+        let sender = HttpSender::new("http://localhost:8899")
+            .with_sleeper(|_| {})
+            .with_transport(move |request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                let result = match body["method"].as_str().unwrap() {
+                    "getVersion" => json!({"solana-core": "1.9.0"}),
+                    method => respond(method, &body["params"]),
+                };
+                Ok(HostHttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: json!({"jsonrpc": "2.0", "id": body["id"], "result": result})
+                        .to_string()
+                        .into_bytes(),
+                })
+            });
+        WasmRpcClient::with_http_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed()))
+    }
+
+    fn status_responder(
+        statuses: HashMap<String, Value>,
+        block_height: u64,
+        status_calls: Arc<Mutex<usize>>,
+    ) -> impl Fn(&str, &Value) -> Value + Send + Sync + 'static {
+        move |method, params| match method {
+            "getSignatureStatuses" => {
+                *status_calls.lock().unwrap() += 1;
+                let value: Vec<_> = params[0]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|signature| {
+                        statuses
+                            .get(signature.as_str().unwrap())
+                            .cloned()
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect();
+                json!({"context": {"slot": 10}, "value": value})
+            },
+            "getBlockHeight" => json!(block_height),
+            method => panic!("unexpected {} request", method),
+        }
+    }
+
+    fn status(slot: Slot, confirmation_status: &str, err: Option<TransactionError>) -> Value {
+        json!({
+            "slot": slot,
+            "confirmations": null,
+            "status": match &err {
+                Some(err) => json!({ "Err": err }),
+                None => json!({ "Ok": null }),
+            },
+            "err": err,
+            "confirmationStatus": confirmation_status,
+        })
+    }
+
+    #[test]
+    fn signatures_settle_from_a_single_batched_poll() {
+        let confirmed = Signature::new_unique();
+        let failed = Signature::new_unique();
+        let expired = Signature::new_unique();
+        let processed = Signature::new_unique();
+        let error = TransactionError::InstructionError(0, InstructionError::Custom(1));
+        let statuses = HashMap::from([
+            (confirmed.to_string(), status(5, "confirmed", None)),
+            (failed.to_string(), status(6, "processed", Some(error.clone()))),
+            (processed.to_string(), status(7, "processed", None)),
+        ]);
+        let status_calls = Arc::new(Mutex::new(0));
+        let client = client(status_responder(statuses, 200, status_calls.clone()));
+
+        let mut completions = Vec::new();
+        let mut watcher = client
+            .confirmation_watcher()
+            .with_on_complete(|signature, outcome| completions.push((*signature, outcome.clone())));
+        watcher.watch(confirmed, 300);
+        watcher.watch(failed, 300);
+        watcher.watch(expired, 150);
+        watcher.watch(processed, 150);
+
+        let mut completed = watcher.poll().unwrap();
+        completed.sort_by_key(|(signature, _)| *signature);
+        let mut expected = vec![
+            (confirmed, WatchOutcome::Confirmed { slot: 5 }),
+            (failed, WatchOutcome::Failed(error)),
+            (expired, WatchOutcome::Expired),
+        ];
+        expected.sort_by_key(|(signature, _)| *signature);
+        assert_eq!(completed, expected);
+        assert_eq!(watcher.pending_count(), 1);
+        assert_eq!(*status_calls.lock().unwrap(), 1);
+        drop(watcher);
+        assert_eq!(completions.len(), 3);
+    }
+
+    #[test]
+    fn shared_hub_polls_the_watched_signatures() {
+        let signature = Signature::new_unique();
+        let statuses = HashMap::from([(signature.to_string(), status(5, "finalized", None))]);
+        let status_calls = Arc::new(Mutex::new(0));
+        let hub = Arc::new(ConfirmationHub::new(Duration::ZERO));
+        let client = client(status_responder(statuses, 100, status_calls.clone())).with_confirmation_hub(hub.clone());
+
+        let mut watcher = client.confirmation_watcher();
+        watcher.watch(signature, 300);
+        watcher.watch(Signature::new_unique(), 300);
+        assert_eq!(hub.pending_count(), 2);
+
+        let outcomes = watcher.poll().unwrap();
+        assert_eq!(outcomes, [(signature, WatchOutcome::Confirmed { slot: 5 })]);
+        assert_eq!(hub.pending_count(), 1);
+
+        // The signatures left pending are no longer polled once the watcher is dropped
+        drop(watcher);
+        assert_eq!(hub.pending_count(), 0);
+    }
+}
@@ -0,0 +1,200 @@
+use serde::de::DeserializeOwned;
+use solana_account_decoder::{
+    parse_account_data::ParsedAccount, parse_nonce::UiNonceState, parse_stake::StakeAccountType,
+    parse_token::TokenAccountType, parse_vote::VoteAccountType, UiAccount, UiAccountData, UiAccountEncoding,
+};
+use solana_client_api::{client_error::Result as ClientResult, rpc_request::RpcError};
+use solana_sdk::account::Account;
+
+/// Decoder of binary account data returned by the RPC.
+pub trait DataDecoder: Send + Sync {
+    fn decode_base58(&self, data: &str) -> Result<Vec<u8>, String>;
+
+    fn decode_base64(&self, data: &str) -> Result<Vec<u8>, String>;
+
+    /// Decodes `base64` data compressed with zstd, which is supported with the `zstd` feature.
+    fn decode_base64_zstd(&self, data: &str) -> Result<Vec<u8>, String> {
+        let compressed = self.decode_base64(data)?;
+        decompress_zstd(&compressed)
+    }
+}
+
+/// Decoder backed by the `bs58` and `base64` crates.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultDataDecoder;
+
+impl DataDecoder for DefaultDataDecoder {
+    fn decode_base58(&self, data: &str) -> Result<Vec<u8>, String> {
+        bs58::decode(data).into_vec().map_err(|err| err.to_string())
+    }
+
+    fn decode_base64(&self, data: &str) -> Result<Vec<u8>, String> {
+        base64::decode(data).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(compressed: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut decoder = ruzstd::StreamingDecoder::new(compressed).map_err(|err| format!("{:?}", err))?;
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data).map_err(|err| err.to_string())?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_compressed: &[u8]) -> Result<Vec<u8>, String> {
+    Err("base64+zstd account data requires the zstd feature".to_string())
+}
+
+/// Returns the most compact binary encoding the account data can be requested and decoded with, `base64+zstd` with
+/// the `zstd` feature and `base64` otherwise.
+pub fn compact_account_encoding() -> UiAccountEncoding {
+    if cfg!(feature = "zstd") {
+        UiAccountEncoding::Base64Zstd
+    } else {
+        UiAccountEncoding::Base64
+    }
+}
+
+/// Decodes the binary account `data` with the `decoder`. Parsed JSON data can't be decoded into bytes.
+pub fn decode_account_data(decoder: &dyn DataDecoder, data: &UiAccountData) -> ClientResult<Vec<u8>> {
+    let decoded = match data {
+        UiAccountData::LegacyBinary(data) => decoder.decode_base58(data),
+        UiAccountData::Binary(data, UiAccountEncoding::Base58 | UiAccountEncoding::Binary) => {
+            decoder.decode_base58(data)
+        },
+        UiAccountData::Binary(data, UiAccountEncoding::Base64) => decoder.decode_base64(data),
+        UiAccountData::Binary(data, UiAccountEncoding::Base64Zstd) => decoder.decode_base64_zstd(data),
+        UiAccountData::Binary(_, encoding) => Err(format!("unsupported account data encoding {:?}", encoding)),
+        UiAccountData::Json(_) => Err("parsed account data can't be decoded into bytes".to_string()),
+    };
+
+    decoded.map_err(|err| RpcError::ParseError(format!("failed to decode account data: {}", err)).into())
+}
+
+/// Converts the account in the UI representation, decoding its data with the `decoder`.
+pub fn decode_account(decoder: &dyn DataDecoder, account: &UiAccount) -> ClientResult<Account> {
+    let owner = account
+        .owner
+        .parse()
+        .map_err(|_| RpcError::ParseError(format!("invalid account owner {}", account.owner)))?;
+
+    Ok(Account {
+        lamports: account.lamports,
+        data: decode_account_data(decoder, &account.data)?,
+        owner,
+        executable: account.executable,
+        rent_epoch: account.rent_epoch,
+    })
+}
+
+/// Decodes the binary account `data` and deserializes it with `bincode`, as the native programs store their state.
+pub fn decode_account_bincode<T: DeserializeOwned>(decoder: &dyn DataDecoder, data: &UiAccountData) -> ClientResult<T> {
+    let data = decode_account_data(decoder, data)?;
+    bincode::deserialize(&data)
+        .map_err(|err| RpcError::ParseError(format!("failed to deserialize account data: {}", err)).into())
+}
+
+/// Account data parsed by the node with the `jsonParsed` encoding, typed for the common programs.
+#[derive(Debug, PartialEq)]
+pub enum ParsedProgramAccount {
+    Stake(StakeAccountType),
+    Vote(VoteAccountType),
+    Nonce(UiNonceState),
+    Token(TokenAccountType),
+
+    /// Account of another program the node has a parser for.
+    Other(ParsedAccount),
+}
+
+/// Converts the `jsonParsed` account `data` into the typed representation of its program. Fails if the data is
+/// binary, i.e. the node has no parser for the account's program.
+pub fn parse_program_account(data: &UiAccountData) -> ClientResult<ParsedProgramAccount> {
+    let parsed_account = match data {
+        UiAccountData::Json(parsed_account) => parsed_account,
+        _ => return Err(RpcError::ParseError("account data is not parsed to JSON".to_string()).into()),
+    };
+
+    fn typed<T: DeserializeOwned>(parsed_account: &ParsedAccount) -> ClientResult<T> {
+        serde_json::from_value(parsed_account.parsed.clone()).map_err(|err| {
+            RpcError::ParseError(format!(
+                "invalid parsed {} account data: {}",
+                parsed_account.program, err
+            ))
+            .into()
+        })
+    }
+
+    Ok(match parsed_account.program.as_str() {
+        "stake" => ParsedProgramAccount::Stake(typed(parsed_account)?),
+        "vote" => ParsedProgramAccount::Vote(typed(parsed_account)?),
+        "nonce" => ParsedProgramAccount::Nonce(typed(parsed_account)?),
+        "spl-token" | "spl-token-2022" => ParsedProgramAccount::Token(typed(parsed_account)?),
+        _ => ParsedProgramAccount::Other(parsed_account.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parsed(program: &str, parsed: serde_json::Value) -> UiAccountData {
+        UiAccountData::Json(ParsedAccount {
+            program: program.to_string(),
+            parsed,
+            space: 80,
+        })
+    }
+
+    #[test]
+    fn binary_data_is_decoded_in_its_encoding() {
+        let data = [1, 2, 3, 250];
+        let base58 = bs58::encode(data).into_string();
+        let base64 = base64::encode(data);
+
+        for encoded in [
+            UiAccountData::LegacyBinary(base58.clone()),
+            UiAccountData::Binary(base58, UiAccountEncoding::Base58),
+            UiAccountData::Binary(base64.clone(), UiAccountEncoding::Base64),
+        ] {
+            assert_eq!(decode_account_data(&DefaultDataDecoder, &encoded).unwrap(), data);
+        }
+        assert!(decode_account_data(&DefaultDataDecoder, &parsed("vote", json!({}))).is_err());
+        let unsupported = UiAccountData::Binary(base64, UiAccountEncoding::JsonParsed);
+        assert!(decode_account_data(&DefaultDataDecoder, &unsupported).is_err());
+
+        let bincode_data = UiAccountData::Binary(base64::encode(7_u64.to_le_bytes()), UiAccountEncoding::Base64);
+        assert_eq!(
+            decode_account_bincode::<u64>(&DefaultDataDecoder, &bincode_data).unwrap(),
+            7
+        );
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_data_requires_the_feature() {
+        let data = UiAccountData::Binary(base64::encode([0]), UiAccountEncoding::Base64Zstd);
+
+        let err = decode_account_data(&DefaultDataDecoder, &data).unwrap_err();
+        assert!(err.to_string().contains("zstd feature"), "{}", err);
+        assert_eq!(compact_account_encoding(), UiAccountEncoding::Base64);
+    }
+
+    #[test]
+    fn parsed_data_is_typed_by_its_program() {
+        assert_eq!(
+            parse_program_account(&parsed("nonce", json!({"type": "uninitialized"}))).unwrap(),
+            ParsedProgramAccount::Nonce(UiNonceState::Uninitialized)
+        );
+        assert!(matches!(
+            parse_program_account(&parsed("config", json!({"keys": []}))).unwrap(),
+            ParsedProgramAccount::Other(account) if account.program == "config"
+        ));
+        assert!(parse_program_account(&parsed("nonce", json!({"type": "unknown"}))).is_err());
+        assert!(parse_program_account(&UiAccountData::LegacyBinary(String::new())).is_err());
+    }
+}
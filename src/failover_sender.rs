@@ -0,0 +1,300 @@
+use std::{
+    iter,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind, Result},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+use crate::{is_endpoint_failure, HttpSender};
+
+/// Default number of consecutive endpoint failures after which the endpoint is considered degraded.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// Default time a degraded endpoint is tried only after the healthy ones.
+pub const DEFAULT_DEGRADED_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Order in which the endpoints are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Always start from the first endpoint, the next ones are fallbacks.
+    Ordered,
+
+    /// Spread the requests over the endpoints in proportion to their weights.
+    Weighted,
+}
+
+/// Health of an endpoint, as observed by the `FailoverSender`.
+#[derive(Debug, Default, Clone)]
+pub struct EndpointHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+
+    /// Time the endpoint became degraded, `None` while it is healthy.
+    pub degraded_since: Option<Instant>,
+}
+
+/// State of an endpoint of the `FailoverSender`, see `FailoverSender::endpoint_statuses`.
+#[derive(Debug, Clone)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub weight: u32,
+    pub health: EndpointHealth,
+    pub stats: RpcTransportStats,
+}
+
+struct Endpoint {
+    url: String,
+    weight: u32,
+    health: Mutex<EndpointHealth>,
+}
+
+/// Sender over the primary and fallback endpoints of an `HttpSender`, trying the next one when an endpoint fails at
+/// the transport level or keeps rate limiting or erroring after its own retries. Unlike the plain fallback of the
+/// `HttpSender`, endpoints failing repeatedly are steered away from for a cooldown period and the requests can be
+/// spread over the endpoints. The requests are made with the configuration of the `HttpSender`, which also keeps
+/// the stats of each endpoint, and are tried on at most its `max_endpoints_per_send` endpoints.
+pub struct FailoverSender {
+    sender: HttpSender,
+    endpoints: Vec<Endpoint>,
+    load_balancing: LoadBalancing,
+    failure_threshold: u32,
+    degraded_cooldown: Duration,
+    request_counter: AtomicU64,
+}
+
+impl FailoverSender {
+    /// Creates a sender trying the endpoints of the `sender` in order, the primary one first.
+    pub fn new(sender: HttpSender) -> Self {
+        Self::with_endpoints(sender, iter::empty(), LoadBalancing::Ordered)
+    }
+
+    /// Creates a sender spreading the requests over the endpoints of the `sender` in proportion to the `weights`,
+    /// given in the order of the endpoints, the primary one first. Endpoints without a weight get a weight of 1.
+    pub fn weighted(sender: HttpSender, weights: impl IntoIterator<Item = u32>) -> Self {
+        Self::with_endpoints(sender, weights, LoadBalancing::Weighted)
+    }
+
+    fn with_endpoints(
+        sender: HttpSender,
+        weights: impl IntoIterator<Item = u32>,
+        load_balancing: LoadBalancing,
+    ) -> Self {
+        let endpoints = sender
+            .endpoint_urls()
+            .into_iter()
+            .zip(weights.into_iter().chain(iter::repeat(1)))
+            .map(|(url, weight)| Endpoint {
+                url,
+                weight,
+                health: Mutex::new(EndpointHealth::default()),
+            })
+            .collect();
+        Self {
+            sender,
+            endpoints,
+            load_balancing,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            degraded_cooldown: DEFAULT_DEGRADED_COOLDOWN,
+            request_counter: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the number of consecutive failures after which an endpoint is considered degraded.
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Sets the time a degraded endpoint is tried only after the healthy ones.
+    pub fn with_degraded_cooldown(mut self, degraded_cooldown: Duration) -> Self {
+        self.degraded_cooldown = degraded_cooldown;
+        self
+    }
+
+    pub fn http_sender(&self) -> &HttpSender {
+        &self.sender
+    }
+
+    pub fn endpoint_statuses(&self) -> Vec<EndpointStatus> {
+        let mut stats_per_endpoint = self.sender.stats_per_endpoint();
+        self.endpoints
+            .iter()
+            .map(|endpoint| EndpointStatus {
+                url: endpoint.url.clone(),
+                weight: endpoint.weight,
+                health: endpoint.health.lock().unwrap().clone(),
+                stats: stats_per_endpoint.remove(&endpoint.url).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Returns the indices of the endpoints in the order they should be tried for the next request.
+    fn candidates(&self) -> Vec<usize> {
+        let len = self.endpoints.len();
+        let first = match self.load_balancing {
+            LoadBalancing::Ordered => 0,
+            LoadBalancing::Weighted => self.weighted_start(),
+        };
+        let mut candidates: Vec<_> = (0..len).map(|offset| (first + offset) % len).collect();
+        // Stable, so healthy endpoints keep their order ahead of degraded ones
+        candidates.sort_by_key(|&index| self.is_degraded(&self.endpoints[index]));
+        candidates
+    }
+
+    fn weighted_start(&self) -> usize {
+        let total_weight: u64 = self.endpoints.iter().map(|endpoint| u64::from(endpoint.weight)).sum();
+        if total_weight == 0 {
+            return 0;
+        }
+
+        let mut ticket = self.request_counter.fetch_add(1, Ordering::Relaxed) % total_weight;
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            let weight = u64::from(endpoint.weight);
+            if ticket < weight {
+                return index;
+            }
+            ticket -= weight;
+        }
+        0
+    }
+
+    fn is_degraded(&self, endpoint: &Endpoint) -> bool {
+        endpoint
+            .health
+            .lock()
+            .unwrap()
+            .degraded_since
+            .map_or(false, |degraded_since| {
+                degraded_since.elapsed() < self.degraded_cooldown
+            })
+    }
+
+    fn record_success(&self, endpoint: &Endpoint) {
+        let mut health = endpoint.health.lock().unwrap();
+        health.successes += 1;
+        health.consecutive_failures = 0;
+        health.degraded_since = None;
+    }
+
+    fn record_failure(&self, endpoint: &Endpoint) {
+        let mut health = endpoint.health.lock().unwrap();
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.failure_threshold {
+            // Restarts the cooldown of an endpoint failing again when probed
+            health.degraded_since = Some(Instant::now());
+        }
+    }
+}
+
+impl RpcSender for FailoverSender {
+    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        let mut last_err = None;
+        for index in self.candidates().into_iter().take(self.sender.max_endpoints_per_send) {
+            let endpoint = &self.endpoints[index];
+            match self.sender.send_to(&endpoint.url, request, params.clone()) {
+                Ok(result) => {
+                    self.record_success(endpoint);
+                    return Ok(result);
+                },
+                Err(err) if is_endpoint_failure(&err) => {
+                    self.record_failure(endpoint);
+                    last_err = Some(err);
+                },
+                Err(err) => {
+                    // The endpoint responded, the request itself failed
+                    self.record_success(endpoint);
+                    return Err(err);
+                },
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            ClientError::new_with_request(
+                ClientErrorKind::Custom("The failover sender has no endpoints".to_string()),
+                request,
+            )
+        }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.sender.get_transport_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{retry::RetryPolicy, transport::HostHttpResponse};
+
+    const PRIMARY_URL: &str = "http://primary:8899";
+    const FALLBACK_URL: &str = "http://fallback:8899";
+
+    #[test]
+    fn degraded_primary_is_failed_over_until_the_cooldown_passes() {
+        let primary_down = Arc::new(AtomicBool::new(true));
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(PRIMARY_URL)
+            .with_fallback_urls([FALLBACK_URL])
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport({
+                let primary_down = primary_down.clone();
+                let attempts = attempts.clone();
+                move |request| {
+                    let primary = request.url.starts_with(PRIMARY_URL);
+                    attempts.lock().unwrap().push(primary);
+                    if primary && primary_down.load(Ordering::SeqCst) {
+                        return Err("connection refused".to_string());
+                    }
+                    let result = if primary { 1 } else { 2 };
+                    Ok(HostHttpResponse {
+                        status: 200,
+                        headers: Vec::new(),
+                        body: json!({"jsonrpc": "2.0", "id": 0, "result": result})
+                            .to_string()
+                            .into_bytes(),
+                    })
+                }
+            });
+        let sender = FailoverSender::new(sender)
+            .with_failure_threshold(2)
+            .with_degraded_cooldown(Duration::from_millis(30));
+
+        for _ in 0..2 {
+            assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(2));
+        }
+        assert_eq!(*attempts.lock().unwrap(), [true, false, true, false]);
+        let health = &sender.endpoint_statuses()[0].health;
+        assert_eq!(health.consecutive_failures, 2);
+        assert!(health.degraded_since.is_some());
+
+        // The degraded primary is tried after the fallback, so it isn't tried at all
+        primary_down.store(false, Ordering::SeqCst);
+        attempts.lock().unwrap().clear();
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(2));
+        assert_eq!(*attempts.lock().unwrap(), [false]);
+
+        std::thread::sleep(Duration::from_millis(40));
+        attempts.lock().unwrap().clear();
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(1));
+        assert_eq!(*attempts.lock().unwrap(), [true]);
+        let health = &sender.endpoint_statuses()[0].health;
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.degraded_since.is_none());
+        assert_eq!(health.failures, 2);
+    }
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_client_api::{client_error::Result as ClientResult, rpc_client::RpcClient, rpc_request::RpcRequest};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_instruction::SystemInstruction, system_program};
+
+/// Id of the compute budget program.
+pub mod compute_budget_program {
+    solana_sdk::declare_id!("ComputeBudget111111111111111111111111111111");
+}
+
+/// Maximum compute unit limit of a transaction.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Discriminators of the compute budget program instructions.
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Prioritization fee paid in a recent slot, as returned by `getRecentPrioritizationFees`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPrioritizationFee {
+    pub slot: u64,
+
+    /// Fee per compute unit, in micro-lamports.
+    pub prioritization_fee: u64,
+}
+
+/// Returns the prioritization fees paid in the recent slots by the transactions locking all the writable
+/// `accounts`, or by any transaction if `accounts` is empty.
+pub fn get_recent_prioritization_fees_for_accounts(
+    client: &RpcClient,
+    accounts: &[Pubkey],
+) -> ClientResult<Vec<RpcPrioritizationFee>> {
+    let accounts: Vec<_> = accounts.iter().map(Pubkey::to_string).collect();
+    client.send(
+        RpcRequest::Custom {
+            method: "getRecentPrioritizationFees",
+        },
+        json!([accounts]),
+    )
+}
+
+/// Returns the `percentile` (0 to 100) of the fees by the nearest-rank method, 0 if there are no fees.
+pub fn fee_percentile(fees: &[RpcPrioritizationFee], percentile: u8) -> u64 {
+    let mut fees: Vec<_> = fees.iter().map(|fee| fee.prioritization_fee).collect();
+    if fees.is_empty() {
+        return 0;
+    }
+
+    fees.sort_unstable();
+    let rank = (usize::from(percentile.min(100)) * fees.len() + 99) / 100;
+    fees[rank.saturating_sub(1)]
+}
+
+/// Instruction setting the compute unit limit of the transaction, same as
+/// `ComputeBudgetInstruction::set_compute_unit_limit` of newer SDKs.
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget_program::id(), &data, Vec::new())
+}
+
+/// Instruction setting the price of a compute unit of the transaction in micro-lamports, same as
+/// `ComputeBudgetInstruction::set_compute_unit_price` of newer SDKs.
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    Instruction::new_with_bytes(compute_budget_program::id(), &data, Vec::new())
+}
+
+/// Returns the `instructions` preceded by the compute budget instructions setting the `units` limit and the
+/// `micro_lamports` price, replacing any compute budget instructions already among them. A leading instruction
+/// advancing a nonce account is kept first, since the runtime only recognizes durable-nonce transactions by it.
+pub fn with_compute_budget(instructions: &[Instruction], units: u32, micro_lamports: u64) -> Vec<Instruction> {
+    let (advance_nonce, instructions) = match instructions.split_first() {
+        Some((first, rest)) if is_advance_nonce(first) => (Some(first), rest),
+        _ => (None, instructions),
+    };
+    advance_nonce
+        .cloned()
+        .into_iter()
+        .chain([set_compute_unit_limit(units), set_compute_unit_price(micro_lamports)])
+        .chain(
+            instructions
+                .iter()
+                .filter(|instruction| instruction.program_id != compute_budget_program::id())
+                .cloned(),
+        )
+        .collect()
+}
+
+fn is_advance_nonce(instruction: &Instruction) -> bool {
+    instruction.program_id == system_program::id()
+        && matches!(
+            bincode::deserialize(&instruction.data),
+            Ok(SystemInstruction::AdvanceNonceAccount)
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_instruction;
+
+    use super::*;
+
+    #[test]
+    fn compute_budget_is_prepended_replacing_the_existing_one() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let instructions = with_compute_budget(&[set_compute_unit_price(1), transfer.clone()], 200_000, 5);
+        assert_eq!(instructions, [
+            set_compute_unit_limit(200_000),
+            set_compute_unit_price(5),
+            transfer
+        ]);
+    }
+
+    #[test]
+    fn compute_budget_follows_a_leading_nonce_advance() {
+        let payer = Pubkey::new_unique();
+        let advance_nonce = system_instruction::advance_nonce_account(&Pubkey::new_unique(), &payer);
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let instructions = with_compute_budget(&[advance_nonce.clone(), transfer.clone()], 200_000, 5);
+        assert_eq!(instructions, [
+            advance_nonce.clone(),
+            set_compute_unit_limit(200_000),
+            set_compute_unit_price(5),
+            transfer.clone()
+        ]);
+
+        // Only a leading nonce advance makes the transaction use a durable nonce
+        let instructions = with_compute_budget(&[transfer.clone(), advance_nonce.clone()], 200_000, 5);
+        assert_eq!(instructions, [
+            set_compute_unit_limit(200_000),
+            set_compute_unit_price(5),
+            transfer,
+            advance_nonce
+        ]);
+    }
+}
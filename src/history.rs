@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+
+use serde_json::{json, Value};
+use solana_client_api::{
+    client_error::Result as ClientResult, rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_request::RpcRequest,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// Maximum number of signatures returned by a `getSignaturesForAddress` call.
+pub const MAX_SIGNATURES_PAGE_SIZE: usize = 1000;
+
+/// Configuration of `WasmRpcClient::signatures_for_address_iter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureHistoryConfig {
+    /// Number of signatures fetched per request, capped by the RPC limit.
+    pub page_size: usize,
+
+    /// Signature to start the history before, the latest transaction if `None`.
+    pub before: Option<Signature>,
+
+    /// Signature to stop at, excluded from the history.
+    pub until: Option<Signature>,
+
+    /// Slot to stop at, transactions from earlier slots are excluded from the history.
+    pub min_slot: Option<Slot>,
+
+    /// Encoding the transactions are fetched in, they are not fetched if `None`.
+    pub hydrate: Option<UiTransactionEncoding>,
+
+    /// Commitment of the fetch, `confirmed` if `None` as `processed` is not supported for history.
+    pub commitment: Option<CommitmentConfig>,
+}
+
+impl Default for SignatureHistoryConfig {
+    fn default() -> Self {
+        Self {
+            page_size: MAX_SIGNATURES_PAGE_SIZE,
+            before: None,
+            until: None,
+            min_slot: None,
+            hydrate: None,
+            commitment: None,
+        }
+    }
+}
+
+/// Entry of the transaction history of an address.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub status: RpcConfirmedTransactionStatusWithSignature,
+
+    /// Transaction as raw JSON in the requested encoding, if hydration was requested and the node still has it.
+    pub transaction: Option<Value>,
+}
+
+/// Iterator over the transaction history of an address, from the newest transaction to the oldest, see
+/// `WasmRpcClient::signatures_for_address_iter`. It ends after the first error.
+pub struct SignatureHistory<'a> {
+    client: &'a WasmRpcClient,
+    address: Pubkey,
+    config: SignatureHistoryConfig,
+    page: VecDeque<RpcConfirmedTransactionStatusWithSignature>,
+    finished: bool,
+}
+
+impl WasmRpcClient {
+    /// Returns an iterator paging through the transaction history of `address`.
+    pub fn signatures_for_address_iter(
+        &self,
+        address: &Pubkey,
+        config: SignatureHistoryConfig,
+    ) -> SignatureHistory<'_> {
+        SignatureHistory {
+            client: self,
+            address: *address,
+            config,
+            page: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl SignatureHistory<'_> {
+    fn commitment(&self) -> CommitmentConfig {
+        self.config.commitment.unwrap_or_else(CommitmentConfig::confirmed)
+    }
+
+    fn fetch_page(&mut self) -> ClientResult<()> {
+        let limit = self.config.page_size.clamp(1, MAX_SIGNATURES_PAGE_SIZE);
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before: self.config.before,
+            until: self.config.until,
+            limit: Some(limit),
+            commitment: Some(self.commitment()),
+        };
+        let page = self
+            .client
+            .get_signatures_for_address_with_config(&self.address, config)?;
+
+        if page.len() < limit {
+            self.finished = true;
+        }
+        if let Some(last) = page.last() {
+            self.config.before = last.signature.parse().ok();
+            // Without a cursor the same page would be fetched again
+            self.finished |= self.config.before.is_none();
+        }
+        self.page.extend(page);
+        Ok(())
+    }
+
+    fn hydrate(&self, signature: &str, encoding: UiTransactionEncoding) -> ClientResult<Option<Value>> {
+        let transaction: Value = self.client.send(
+            RpcRequest::GetTransaction,
+            json!([signature, {
+                "encoding": encoding,
+                "commitment": self.commitment().commitment,
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        Ok((!transaction.is_null()).then(|| transaction))
+    }
+}
+
+impl Iterator for SignatureHistory<'_> {
+    type Item = ClientResult<HistoryEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() && !self.finished {
+            if let Err(err) = self.fetch_page() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+
+        let status = self.page.pop_front()?;
+        if self.config.min_slot.map_or(false, |min_slot| status.slot < min_slot) {
+            self.page.clear();
+            self.finished = true;
+            return None;
+        }
+
+        let transaction = match self.config.hydrate {
+            Some(encoding) => match self.hydrate(&status.signature, encoding) {
+                Ok(transaction) => transaction,
+                Err(err) => {
+                    self.page.clear();
+                    self.finished = true;
+                    return Some(Err(err));
+                },
+            },
+            None => None,
+        };
+        Some(Ok(HistoryEntry { status, transaction }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    fn signature_status(signature: &Signature, slot: Slot) -> Value {
+        json!({
+            "signature": signature.to_string(),
+            "slot": slot,
+            "err": null,
+            "memo": null,
+            "blockTime": null,
+            "confirmationStatus": "finalized",
+        })
+    }
+
+    /// Client over the `mock`, which is set to answer the version checks of the `RpcClient` methods.
+    fn history_client(mock: &MockSender) -> WasmRpcClient {
+        mock.set_response("getVersion", Ok(json!({ "solana-core": "1.9.0" })));
+        WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ))
+    }
+
+    #[test]
+    fn history_is_paged_with_the_cursor_until_the_min_slot() {
+        let signatures: Vec<_> = (0..5).map(|_| Signature::new_unique()).collect();
+        let mock = MockSender::new();
+        for page in signatures.chunks(2).zip([10, 8, 6]).map(|(page, first_slot)| {
+            page.iter()
+                .zip(0..)
+                .map(|(signature, offset)| signature_status(signature, first_slot - offset))
+                .collect::<Vec<_>>()
+        }) {
+            mock.push_response("getSignaturesForAddress", Ok(json!(page)));
+        }
+        let client = history_client(&mock);
+        let config = SignatureHistoryConfig {
+            page_size: 2,
+            min_slot: Some(7),
+            ..SignatureHistoryConfig::default()
+        };
+
+        let entries: Vec<_> = client
+            .signatures_for_address_iter(&Pubkey::new_unique(), config)
+            .map(|entry| entry.unwrap().status.signature)
+            .collect();
+        let expected: Vec<_> = signatures[..4].iter().map(Signature::to_string).collect();
+        assert_eq!(entries, expected);
+
+        let cursors: Vec<_> = mock
+            .calls_to("getSignaturesForAddress")
+            .iter()
+            .map(|params| (params[1]["before"].clone(), params[1]["limit"].clone()))
+            .collect();
+        assert_eq!(cursors, [
+            (Value::Null, json!(2)),
+            (json!(signatures[1].to_string()), json!(2)),
+            (json!(signatures[3].to_string()), json!(2)),
+        ]);
+    }
+
+    #[test]
+    fn entries_are_hydrated_with_the_transactions() {
+        let signatures = [Signature::new_unique(), Signature::new_unique()];
+        let mock = MockSender::new().with_response(
+            "getSignaturesForAddress",
+            json!([signature_status(&signatures[0], 5), signature_status(&signatures[1], 4)]),
+        );
+        mock.push_response("getTransaction", Ok(json!({"slot": 5})));
+        mock.push_response("getTransaction", Ok(Value::Null));
+        let client = history_client(&mock);
+        let config = SignatureHistoryConfig {
+            hydrate: Some(UiTransactionEncoding::Base64),
+            ..SignatureHistoryConfig::default()
+        };
+
+        let transactions: Vec<_> = client
+            .signatures_for_address_iter(&Pubkey::new_unique(), config)
+            .map(|entry| entry.unwrap().transaction)
+            .collect();
+        assert_eq!(transactions, [Some(json!({"slot": 5})), None]);
+        // The short page ends the history
+        assert_eq!(mock.call_count("getSignaturesForAddress"), 1);
+        assert_eq!(mock.calls_to("getTransaction")[1][0], signatures[1].to_string());
+        assert_eq!(mock.calls_to("getTransaction")[1][1]["encoding"], "base64");
+    }
+}
@@ -1,15 +1,19 @@
 pub use solana_client_api::*;
 
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    iter,
+    panic::{self, AssertUnwindSafe},
     sync::{
-        atomic::{AtomicU64, Ordering},
-        RwLock,
+        atomic::{AtomicU16, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
     },
     time::{Duration, Instant},
 };
 
 use laplace_wasm::http;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 use solana_client_api::{
     client_error::{ClientError, ClientErrorKind, Result},
@@ -18,155 +22,3272 @@ use solana_client_api::{
     rpc_response::RpcSimulateTransactionResult,
     rpc_sender::{RpcSender, RpcTransportStats},
 };
+use solana_sdk::clock::Slot;
 
+use crate::{
+    coalescing::{RequestCoalescer, DEFAULT_NON_COALESCED_METHODS},
+    metrics::{error_kind, MethodMetrics, StatsReport},
+    middleware::RpcMiddleware,
+    observer::{RpcEvent, RpcEventObserver},
+    rate_limit::RateLimiter,
+    retry::RetryPolicy,
+    timeout::TimeoutPolicy,
+    transport::{invoke_host, HostHttpRequest, HostHttpResponse, HttpTransport},
+};
+
+pub mod alt;
+pub mod async_sender;
+pub mod async_wasm_rpc_client;
+pub mod caching_sender;
+pub mod chunked_accounts;
+pub mod circuit_breaker;
+pub mod coalescing;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod confirmation_hub;
+pub mod confirmation_watcher;
+pub mod decode;
+pub mod failover_sender;
+pub mod fees;
+pub mod history;
+pub mod metrics;
+pub mod middleware;
+pub mod mock_sender;
+pub mod nonce;
+pub mod observer;
+pub mod program_accounts;
+pub mod rate_limit;
+pub mod retry;
+pub mod signature_ledger;
+pub mod timeout;
+pub mod token;
+pub mod transport;
+pub mod wasm_pubsub_client;
 pub mod wasm_rpc_client;
 
+/// Default maximum length of a body passed to the body hook.
+pub const DEFAULT_MAX_LOGGED_BODY_LEN: usize = 4096;
+
+/// Default number of the most recent requests the rate limit ratio is computed over.
+pub const DEFAULT_RATE_LIMIT_WINDOW_LEN: usize = 100;
+
+/// Default maximum size a compressed response body may decompress to.
+pub const DEFAULT_MAX_DECOMPRESSED_BODY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Interval of waiting for the in-flight response bodies to be released.
+const IN_FLIGHT_BODY_WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Length above which `summarize_params` summarizes the key arrays.
+const SUMMARIZED_ARRAY_LEN: usize = 8;
+
+/// Builder of the JSON-RPC request body from the request id, the request and its params.
+pub type BodyBuilder = Box<dyn Fn(u64, &RpcRequest, &Value) -> Value + Send + Sync>;
+
+/// Hook receiving the request, the outgoing JSON body and the incoming response body.
+pub type BodyHook = Box<dyn Fn(RpcRequest, &str, &str) + Send + Sync>;
+
 pub struct HttpSender {
-    url: String,
+    url: RwLock<String>,
+    fallback_urls: Vec<String>,
+    max_endpoints_per_send: usize,
     request_id: AtomicU64,
+    reset_id_on_url_change: bool,
+    validate_response_id: bool,
+    capture_context_slot: bool,
+    last_context_slot: RwLock<Option<Slot>>,
+    slot_regression_tolerance: Option<u64>,
+    on_slot_regression: Option<Box<dyn Fn(Slot, Slot) + Send + Sync>>,
+    on_warning: Option<Box<dyn Fn(&str) + Send + Sync>>,
+    max_json_depth: Option<usize>,
+    on_attempt_complete: Option<Box<dyn Fn(u32, Duration, u16) + Send + Sync>>,
+    max_context_slot: AtomicU64,
+    last_http_status: AtomicU16,
+    in_flight_body_bytes: AtomicUsize,
+    max_in_flight_body_bytes: Option<usize>,
+    global_memory_ceiling: Option<usize>,
+    retry_policy: RetryPolicy,
+    method_retry_policies: HashMap<String, RetryPolicy>,
+    timeout_policy: TimeoutPolicy,
     stats: RwLock<RpcTransportStats>,
+    session_stats: RwLock<SessionStats>,
+    method_metrics: RwLock<HashMap<String, MethodMetrics>>,
+    endpoint_stats: RwLock<HashMap<String, RpcTransportStats>>,
+    body_builder: Option<BodyBuilder>,
+    body_hook: Option<BodyHook>,
+    max_logged_body_len: usize,
+    include_request_in_errors: bool,
+    sanitize_urls_in_errors: bool,
+    user_error_messages: HashMap<i64, String>,
+    retain_unknown_error_data: bool,
+    last_unknown_error_data: RwLock<Option<Value>>,
+    is_online: Option<Box<dyn Fn() -> bool + Send + Sync>>,
+    request_budget: Option<AtomicU64>,
+    rate_limiter: Option<RateLimiter>,
+    trace_id: Option<(String, Box<dyn Fn() -> String + Send + Sync>)>,
+    on_drop: Option<Box<dyn Fn(RpcTransportStats) + Send + Sync>>,
+    sleeper: Option<Box<dyn Fn(Duration) + Send + Sync>>,
+    trace_ring: Option<TraceRing>,
+    rate_limit_window_len: usize,
+    rate_limit_window: RwLock<VecDeque<bool>>,
+    traffic_log: Option<RwLock<Vec<TrafficEntry>>>,
+    capture_wire_bytes: bool,
+    last_request_bytes: RwLock<Option<Vec<u8>>>,
+    last_response_bytes: RwLock<Option<Vec<u8>>>,
+    artificial_delay: Option<Duration>,
+    headers: Vec<(String, String)>,
+    response_compression: bool,
+    #[cfg(feature = "compression")]
+    max_decompressed_body_bytes: usize,
+    bearer_token: Option<Box<dyn Fn() -> String + Send + Sync>>,
+    observer: Option<Arc<dyn RpcEventObserver>>,
+    middleware: Vec<Box<dyn RpcMiddleware>>,
+    coalescer: Option<RequestCoalescer>,
+    non_coalesced_methods: HashSet<String>,
+    transport: Option<HttpTransport>,
 }
 
 impl HttpSender {
     pub fn new(url: impl Into<String>) -> Self {
         Self {
-            url: url.into(),
+            url: RwLock::new(normalize_url(url.into())),
+            fallback_urls: Vec::new(),
+            max_endpoints_per_send: usize::MAX,
             request_id: AtomicU64::new(0),
+            reset_id_on_url_change: false,
+            validate_response_id: false,
+            capture_context_slot: false,
+            last_context_slot: RwLock::new(None),
+            slot_regression_tolerance: None,
+            on_slot_regression: None,
+            on_warning: None,
+            max_json_depth: None,
+            on_attempt_complete: None,
+            max_context_slot: AtomicU64::new(0),
+            last_http_status: AtomicU16::new(0),
+            in_flight_body_bytes: AtomicUsize::new(0),
+            max_in_flight_body_bytes: None,
+            global_memory_ceiling: None,
+            retry_policy: RetryPolicy::default(),
+            method_retry_policies: HashMap::new(),
+            timeout_policy: TimeoutPolicy::default(),
             stats: RwLock::new(RpcTransportStats::default()),
+            session_stats: RwLock::new(SessionStats::default()),
+            method_metrics: RwLock::new(HashMap::new()),
+            endpoint_stats: RwLock::new(HashMap::new()),
+            body_builder: None,
+            body_hook: None,
+            max_logged_body_len: DEFAULT_MAX_LOGGED_BODY_LEN,
+            include_request_in_errors: false,
+            sanitize_urls_in_errors: true,
+            user_error_messages: HashMap::new(),
+            retain_unknown_error_data: false,
+            last_unknown_error_data: RwLock::new(None),
+            is_online: None,
+            request_budget: None,
+            rate_limiter: None,
+            trace_id: None,
+            on_drop: None,
+            sleeper: None,
+            trace_ring: None,
+            rate_limit_window_len: DEFAULT_RATE_LIMIT_WINDOW_LEN,
+            rate_limit_window: RwLock::new(VecDeque::new()),
+            traffic_log: None,
+            capture_wire_bytes: false,
+            last_request_bytes: RwLock::new(None),
+            last_response_bytes: RwLock::new(None),
+            artificial_delay: None,
+            headers: Vec::new(),
+            response_compression: true,
+            #[cfg(feature = "compression")]
+            max_decompressed_body_bytes: DEFAULT_MAX_DECOMPRESSED_BODY_BYTES,
+            bearer_token: None,
+            observer: None,
+            middleware: Vec::new(),
+            coalescer: None,
+            non_coalesced_methods: DEFAULT_NON_COALESCED_METHODS
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+            transport: None,
         }
     }
-}
 
-impl RpcSender for HttpSender {
-    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
-        let mut stats_updater = StatsUpdater::new(&self.stats);
+    /// Sets the blocking HTTP call making the exchanges instead of `laplace_wasm::http::invoke`, e.g. a host call
+    /// applying the request timeout or a canned transport in tests.
+    pub fn with_transport(
+        mut self,
+        transport: impl Fn(HostHttpRequest) -> std::result::Result<HostHttpResponse, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.transport = Some(Box::new(transport));
+        self
+    }
 
-        let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
-        let request_json = request.build_request_json(request_id, params).to_string();
-        let mut too_many_requests_retries = 5;
+    fn invoke(&self, http_request: HostHttpRequest) -> std::result::Result<HostHttpResponse, String> {
+        match &self.transport {
+            Some(transport) => transport(http_request),
+            None => invoke_host(http_request),
+        }
+    }
 
-        loop {
-            let http_request = http::RequestBuilder::new()
-                .method(http::Method::POST)
-                .uri(&self.url)
-                .header(http::types::header::CONTENT_TYPE, "application/json")
-                .body(request_json.clone().into_bytes())
-                .map_err(|err| ClientError::new_with_request(ClientErrorKind::Custom(format!("{:?}", err)), request))?
-                .into();
-            let http_response = http::invoke(http_request)
-                .map_err(|err| ClientError::new_with_request(ClientErrorKind::Custom(format!("{:?}", err)), request))?;
-
-            if !http_response.status.is_success() {
-                if http_response.status == http::StatusCode::TOO_MANY_REQUESTS && too_many_requests_retries > 0 {
-                    let mut duration = Duration::from_millis(500);
-                    if let Some(retry_after) = http_response.headers.get(http::types::header::RETRY_AFTER) {
-                        if let Ok(retry_after) = retry_after.to_str() {
-                            if let Ok(retry_after) = retry_after.parse::<u64>() {
-                                if retry_after < 120 {
-                                    duration = Duration::from_secs(retry_after);
-                                }
-                            }
-                        }
-                    }
+    /// Creates a sender with the per-method and per-endpoint statistics maps pre-allocated for `capacity` entries,
+    /// avoiding reallocations in workloads with many distinct methods or endpoints.
+    pub fn new_with_stats_capacity(url: impl Into<String>, capacity: usize) -> Self {
+        Self::new(url).with_stats_capacity(capacity)
+    }
 
-                    too_many_requests_retries -= 1;
+    /// Returns a builder of a sender to `url`.
+    pub fn builder(url: impl Into<String>) -> HttpSenderBuilder {
+        HttpSenderBuilder { sender: Self::new(url) }
+    }
 
-                    #[cfg(feature = "laplace_sleep")]
-                    laplace_wasm::sleep::invoke(duration.as_millis() as u64);
+    /// Adds a header sent with each request, e.g. an API key required by the RPC provider.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
 
-                    #[cfg(not(feature = "laplace_sleep"))]
-                    std::thread::sleep(duration);
+    /// Sets a token sent as `Authorization: Bearer <token>` with each request.
+    pub fn with_bearer_token(self, token: impl Into<String>) -> Self {
+        let token = token.into();
+        self.with_bearer_token_provider(move || token.clone())
+    }
 
-                    stats_updater.add_rate_limited_time(duration);
-                    continue;
+    /// Sets a provider of the bearer token, called before each attempt of a request so short-lived credentials
+    /// can be refreshed.
+    pub fn with_bearer_token_provider(mut self, provider: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        self.bearer_token = Some(Box::new(provider));
+        self
+    }
+
+    /// Sets the middlewares intercepting the requests sent through `RpcSender::send`, the first one being the
+    /// outermost. The batched `send_batch` path bypasses them.
+    pub fn with_middleware(mut self, middleware: Vec<Box<dyn RpcMiddleware>>) -> Self {
+        self.middleware = middleware;
+        self
+    }
+
+    /// Sets whether concurrent identical requests, of the same method with the same params, sent through
+    /// `RpcSender::send` share a single round trip. Methods with side effects are never coalesced, see
+    /// `with_non_coalesced_method`.
+    pub fn with_request_coalescing(mut self, coalesce_requests: bool) -> Self {
+        self.coalescer = coalesce_requests.then(RequestCoalescer::default);
+        self
+    }
+
+    /// Opts `method` out of request coalescing.
+    pub fn with_non_coalesced_method(mut self, method: impl Into<String>) -> Self {
+        self.non_coalesced_methods.insert(method.into());
+        self
+    }
+
+    /// Returns the headers sent with each request in addition to the content type.
+    pub(crate) fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers = self.headers.clone();
+        if let Some(bearer_token) = &self.bearer_token {
+            headers.push((
+                http::types::header::AUTHORIZATION.to_string(),
+                format!("Bearer {}", bearer_token()),
+            ));
+        }
+        #[cfg(feature = "compression")]
+        if self.response_compression {
+            headers.push((
+                http::types::header::ACCEPT_ENCODING.to_string(),
+                compression::ACCEPT_ENCODING.to_string(),
+            ));
+        }
+        headers
+    }
+
+    /// Sets whether gzip and deflate compressed responses are requested, enabled by default.
+    #[cfg(feature = "compression")]
+    pub fn with_response_compression(mut self, response_compression: bool) -> Self {
+        self.response_compression = response_compression;
+        self
+    }
+
+    /// Sets the maximum size a compressed response body may decompress to, `DEFAULT_MAX_DECOMPRESSED_BODY_BYTES`
+    /// by default. It is lowered to what is left under the memory ceiling, larger bodies fail to decompress.
+    #[cfg(feature = "compression")]
+    pub fn with_max_decompressed_body_bytes(mut self, max_decompressed_body_bytes: usize) -> Self {
+        self.max_decompressed_body_bytes = max_decompressed_body_bytes;
+        self
+    }
+
+    fn accepts_compression(&self) -> bool {
+        cfg!(feature = "compression") && self.response_compression
+    }
+
+    /// Returns the response `body` decompressed according to its `content_encoding`.
+    #[cfg(feature = "compression")]
+    pub(crate) fn decode_body<'a>(
+        &self,
+        request: RpcRequest,
+        content_encoding: Option<&str>,
+        body: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>> {
+        match content_encoding {
+            Some(content_encoding) if self.response_compression => {
+                let limit = match self.global_memory_ceiling {
+                    Some(ceiling) => self
+                        .max_decompressed_body_bytes
+                        .min(ceiling.saturating_sub(self.in_flight_body_bytes())),
+                    None => self.max_decompressed_body_bytes,
+                };
+                match compression::decompress(content_encoding, body, limit) {
+                    Ok(Some(decompressed)) => Ok(Cow::Owned(decompressed)),
+                    Ok(None) => Ok(Cow::Borrowed(body)),
+                    Err(err) => Err(ClientError::new_with_request(ClientErrorKind::Io(err), request)),
+                }
+            },
+            _ => Ok(Cow::Borrowed(body)),
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    pub(crate) fn decode_body<'a>(
+        &self,
+        _request: RpcRequest,
+        _content_encoding: Option<&str>,
+        body: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>> {
+        Ok(Cow::Borrowed(body))
+    }
+
+    /// Creates a sender, rejecting an obviously malformed `url`.
+    pub fn try_new(url: impl Into<String>) -> Result<Self> {
+        let url = normalize_url(url.into());
+        check_url(&url)?;
+        Ok(Self::new(url))
+    }
+
+    /// Sets endpoints tried in order when a request fails on the primary one because of a transport error, a server
+    /// error or rate limiting. See `FailoverSender` to steer the requests away from degraded endpoints or spread them
+    /// over the endpoints.
+    pub fn with_fallback_urls(mut self, urls: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.fallback_urls = urls.into_iter().map(|url| normalize_url(url.into())).collect();
+        self
+    }
+
+    /// Returns the primary endpoint followed by the fallback ones.
+    pub fn endpoint_urls(&self) -> Vec<String> {
+        iter::once(self.url())
+            .chain(self.fallback_urls.iter().cloned())
+            .collect()
+    }
+
+    /// Sets the maximum number of endpoints, including the primary one, a single request is tried on.
+    pub fn with_max_endpoints_per_send(mut self, max_endpoints_per_send: usize) -> Self {
+        self.max_endpoints_per_send = max_endpoints_per_send.max(1);
+        self
+    }
+
+    /// Sets whether the request id sequence restarts from 0 when the endpoint is changed by `set_url`.
+    pub fn with_reset_id_on_url_change(mut self, reset_id_on_url_change: bool) -> Self {
+        self.reset_id_on_url_change = reset_id_on_url_change;
+        self
+    }
+
+    /// Sets whether the response `id` must match the request id, a mismatch is reported as an error.
+    pub fn with_validate_response_id(mut self, validate_response_id: bool) -> Self {
+        self.validate_response_id = validate_response_id;
+        self
+    }
+
+    /// Sets whether the `context.slot` of responses is captured, see `last_context_slot`.
+    pub fn with_capture_context_slot(mut self, capture_context_slot: bool) -> Self {
+        self.capture_context_slot = capture_context_slot;
+        self
+    }
+
+    /// Returns the context slot of the last response that carried one.
+    pub fn last_context_slot(&self) -> Option<Slot> {
+        *self.last_context_slot.read().unwrap()
+    }
+
+    /// Sets the check that response context slots don't regress by more than `tolerance` slots from the highest one
+    /// seen, which happens when an endpoint serves stale data. A regression is reported as an error.
+    pub fn with_slot_regression_tolerance(mut self, tolerance: u64) -> Self {
+        self.slot_regression_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Sets the callback that receives the highest seen and the regressed context slots instead of reporting
+    /// a regression as an error, see `with_slot_regression_tolerance`.
+    pub fn with_on_slot_regression(mut self, on_slot_regression: impl Fn(Slot, Slot) + Send + Sync + 'static) -> Self {
+        self.on_slot_regression = Some(Box::new(on_slot_regression));
+        self
+    }
+
+    /// Sets the maximum nesting depth of response JSON, deeper responses are rejected before parsing to keep
+    /// the parser from exhausting the stack. The parser itself never goes deeper than 128 levels.
+    pub fn with_max_json_depth(mut self, max_json_depth: usize) -> Self {
+        self.max_json_depth = Some(max_json_depth);
+        self
+    }
+
+    /// Sets the callback receiving the non-standard `warning` field some providers add to successful responses.
+    pub fn with_on_warning(mut self, on_warning: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.on_warning = Some(Box::new(on_warning));
+        self
+    }
+
+    /// Sets the callback receiving the number, duration and HTTP status of each attempt of a request, starting from
+    /// attempt 0.
+    pub fn with_on_attempt_complete(
+        mut self,
+        on_attempt_complete: impl Fn(u32, Duration, u16) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_attempt_complete = Some(Box::new(on_attempt_complete));
+        self
+    }
+
+    /// Sets the policy of retrying requests rejected with `429 Too Many Requests`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Sets the retry policy of the `method`, overriding the sender's one, e.g. to retry timed out
+    /// `getProgramAccounts` requests with a longer delay.
+    pub fn with_method_retry_policy(mut self, method: impl Into<String>, retry_policy: RetryPolicy) -> Self {
+        self.method_retry_policies.insert(method.into(), retry_policy);
+        self
+    }
+
+    /// Returns the retry policy applied to the `request`.
+    pub fn retry_policy_for(&self, request: RpcRequest) -> &RetryPolicy {
+        self.method_retry_policies
+            .get(&request.to_string())
+            .unwrap_or(&self.retry_policy)
+    }
+
+    /// Sets the timeouts of requests per method category. A timeout bounds the whole request, including retries and
+    /// fallback endpoints: it is checked before each attempt and the time left is passed to the transport with the
+    /// exchange. The default transport can't apply it, see `invoke_host`, so a hung exchange is only bounded by a
+    /// transport set with `with_transport`.
+    pub fn with_timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = timeout_policy;
+        self
+    }
+
+    /// Sets the same timeout for all the method categories, keeping the per-method overrides of the policy. See
+    /// `with_timeout_policy` for how it is applied.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_policy.read = Some(timeout);
+        self.timeout_policy.heavy_read = Some(timeout);
+        self.timeout_policy.write = Some(timeout);
+        self
+    }
+
+    /// Overrides the timeout of the `method`, e.g. a longer one for `getProgramAccounts`.
+    pub fn with_method_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.timeout_policy.method_timeouts.insert(method.into(), timeout);
+        self
+    }
+
+    pub fn timeout_policy(&self) -> &TimeoutPolicy {
+        &self.timeout_policy
+    }
+
+    /// Returns the HTTP status of the last received response, including intermediate rate limited ones.
+    pub fn last_http_status(&self) -> Option<u16> {
+        match self.last_http_status.load(Ordering::Relaxed) {
+            0 => None,
+            status => Some(status),
+        }
+    }
+
+    /// Limits the total size of response bodies parsed at once, parsing of further responses waits until enough
+    /// memory is released. A single body larger than the limit is still parsed when no other one is in flight.
+    pub fn with_max_in_flight_body_bytes(mut self, max_in_flight_body_bytes: usize) -> Self {
+        self.max_in_flight_body_bytes = Some(max_in_flight_body_bytes);
+        self
+    }
+
+    /// Sets the hard ceiling of the total size of response bodies held at once. Unlike
+    /// `with_max_in_flight_body_bytes`, nothing waits: requests made while the ceiling is reached and responses that
+    /// would exceed it fail immediately. As for `with_max_in_flight_body_bytes`, a body is only accounted while it is
+    /// parsed, not while it is received by the host nor once its result is returned.
+    pub fn with_global_memory_ceiling(mut self, global_memory_ceiling: usize) -> Self {
+        self.global_memory_ceiling = Some(global_memory_ceiling);
+        self
+    }
+
+    /// Fails if the memory ceiling is already reached.
+    fn check_memory_ceiling(&self, request: RpcRequest) -> Result<()> {
+        match self.global_memory_ceiling {
+            Some(ceiling) if self.in_flight_body_bytes() >= ceiling => Err(memory_ceiling_error(request, ceiling)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the total size of response bodies currently being parsed.
+    pub fn in_flight_body_bytes(&self) -> usize {
+        self.in_flight_body_bytes.load(Ordering::Acquire)
+    }
+
+    /// Accounts the `len` bytes of a body as in flight while it is parsed, waiting for the in-flight limit and
+    /// failing if the memory ceiling would be exceeded. The ceiling is checked against the same value the
+    /// reservation is made on, so concurrent reservations can't exceed it together.
+    fn reserve_body_bytes(&self, request: RpcRequest, len: usize) -> Result<BodyBytesReservation<'_>> {
+        loop {
+            let in_flight = self.in_flight_body_bytes.load(Ordering::Acquire);
+            if let Some(ceiling) = self.global_memory_ceiling {
+                if in_flight >= ceiling || in_flight + len > ceiling {
+                    return Err(memory_ceiling_error(request, ceiling));
                 }
-                return Err(ClientError::new_with_request(
-                    ClientErrorKind::RpcError(RpcError::ForUser(format!("{}", http_response.status))),
-                    request,
-                ));
             }
+            let over_budget = matches!(
+                self.max_in_flight_body_bytes,
+                Some(max) if in_flight > 0 && in_flight + len > max
+            );
 
-            let mut json: Value = serde_json::from_slice(&http_response.body)?;
-            if json["error"].is_object() {
-                return match serde_json::from_value::<RpcErrorObject>(json["error"].clone()) {
-                    Ok(rpc_error_object) => {
-                        let data = match rpc_error_object.code {
-                            rpc_custom_error::JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
-                                match serde_json::from_value::<RpcSimulateTransactionResult>(
-                                    json["error"]["data"].clone(),
-                                ) {
-                                    Ok(data) => RpcResponseErrorData::SendTransactionPreflightFailure(data),
-                                    Err(_) => RpcResponseErrorData::Empty,
-                                }
-                            },
-                            rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => {
-                                match serde_json::from_value::<rpc_custom_error::NodeUnhealthyErrorData>(
-                                    json["error"]["data"].clone(),
-                                ) {
-                                    Ok(rpc_custom_error::NodeUnhealthyErrorData { num_slots_behind }) => {
-                                        RpcResponseErrorData::NodeUnhealthy { num_slots_behind }
-                                    },
-                                    Err(_err) => RpcResponseErrorData::Empty,
-                                }
-                            },
-                            _ => RpcResponseErrorData::Empty,
-                        };
-
-                        Err(RpcError::RpcResponseError {
-                            code: rpc_error_object.code,
-                            message: rpc_error_object.message,
-                            data,
-                        }
-                        .into())
-                    },
-                    Err(err) => Err(RpcError::RpcRequestError(format!(
-                        "Failed to deserialize RPC error response: {} [{}]",
-                        serde_json::to_string(&json["error"]).unwrap(),
-                        err
-                    ))
-                    .into()),
-                };
+            if over_budget {
+                self.sleep(IN_FLIGHT_BODY_WAIT_INTERVAL);
+            } else if self
+                .in_flight_body_bytes
+                .compare_exchange(in_flight, in_flight + len, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                return Ok(BodyBytesReservation {
+                    in_flight_body_bytes: &self.in_flight_body_bytes,
+                    len,
+                });
             }
-            return Ok(json["result"].take());
         }
     }
 
-    fn get_transport_stats(&self) -> RpcTransportStats {
-        self.stats.read().unwrap().clone()
+    pub fn url(&self) -> String {
+        self.url.read().unwrap().clone()
     }
-}
 
-struct StatsUpdater<'a> {
-    stats: &'a RwLock<RpcTransportStats>,
-    request_start_time: Instant,
-    rate_limited_time: Duration,
-}
+    /// Switches the sender to another endpoint.
+    pub fn set_url(&self, url: impl Into<String>) {
+        *self.url.write().unwrap() = normalize_url(url.into());
+        if self.reset_id_on_url_change {
+            self.request_id.store(0, Ordering::Relaxed);
+        }
+    }
 
-impl<'a> StatsUpdater<'a> {
-    fn new(stats: &'a RwLock<RpcTransportStats>) -> Self {
-        Self {
-            stats,
-            request_start_time: Instant::now(),
-            rate_limited_time: Duration::default(),
+    /// Replaces the standard JSON-RPC request body construction, for providers expecting a non-standard envelope.
+    pub fn with_body_builder(
+        mut self,
+        body_builder: impl Fn(u64, &RpcRequest, &Value) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        self.body_builder = Some(Box::new(body_builder));
+        self
+    }
+
+    pub(crate) fn build_request_json(&self, request_id: u64, request: RpcRequest, params: Value) -> Value {
+        match &self.body_builder {
+            Some(body_builder) => body_builder(request_id, &request, &params),
+            None => request.build_request_json(request_id, params),
         }
     }
 
-    fn add_rate_limited_time(&mut self, duration: Duration) {
-        self.rate_limited_time += duration;
+    /// Sets a hook called with the request and response bodies of every HTTP exchange.
+    pub fn with_body_hook(mut self, hook: impl Fn(RpcRequest, &str, &str) + Send + Sync + 'static) -> Self {
+        self.body_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets the maximum length of bodies passed to the body hook, longer bodies are truncated.
+    pub fn with_max_logged_body_len(mut self, max_logged_body_len: usize) -> Self {
+        self.max_logged_body_len = max_logged_body_len;
+        self
+    }
+
+    /// Sets whether errors include the request JSON, truncated to the maximum logged body length.
+    pub fn with_include_request_in_errors(mut self, include_request_in_errors: bool) -> Self {
+        self.include_request_in_errors = include_request_in_errors;
+        self
+    }
+
+    /// Sets whether the query string, which often carries API keys, is stripped from URLs in error messages.
+    /// Enabled by default.
+    pub fn with_sanitize_urls_in_errors(mut self, sanitize_urls_in_errors: bool) -> Self {
+        self.sanitize_urls_in_errors = sanitize_urls_in_errors;
+        self
+    }
+
+    pub(crate) fn transport_error(&self, request: RpcRequest, url: &str, message: String) -> ClientError {
+        let message = if self.sanitize_urls_in_errors {
+            message.replace(url, &sanitize_url(url))
+        } else {
+            message
+        };
+        ClientError::new_with_request(ClientErrorKind::Custom(message), request)
+    }
+
+    /// Reports RPC errors with the `code` as `RpcError::ForUser` with the `message` instead of the structured
+    /// `RpcError::RpcResponseError`.
+    pub fn with_user_error_message(mut self, code: i64, message: impl Into<String>) -> Self {
+        self.user_error_messages.insert(code, message.into());
+        self
+    }
+
+    /// Sets whether the raw `data` of RPC errors, which can't be represented as `RpcResponseErrorData`, is retained.
+    /// See `last_unknown_error_data`.
+    pub fn with_retain_unknown_error_data(mut self, retain_unknown_error_data: bool) -> Self {
+        self.retain_unknown_error_data = retain_unknown_error_data;
+        self
+    }
+
+    /// Returns the raw `data` of the last RPC error whose data shape was not recognized.
+    pub fn last_unknown_error_data(&self) -> Option<Value> {
+        self.last_unknown_error_data.read().unwrap().clone()
+    }
+
+    /// Sets a host connectivity predicate, requests fail immediately without a network call while it returns `false`.
+    pub fn with_connectivity_check(mut self, is_online: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        self.is_online = Some(Box::new(is_online));
+        self
+    }
+
+    /// Limits the total number of requests the sender makes, requests beyond the budget fail immediately.
+    pub fn with_request_budget(mut self, request_budget: u64) -> Self {
+        self.request_budget = Some(AtomicU64::new(request_budget));
+        self
+    }
+
+    /// Returns the number of requests left in the budget, if one is set.
+    pub fn remaining_request_budget(&self) -> Option<u64> {
+        self.request_budget
+            .as_ref()
+            .map(|budget| budget.load(Ordering::Relaxed))
+    }
+
+    /// Paces the requests with the client-side `rate_limiter`, waiting before the attempts that would exceed it
+    /// instead of having them rejected with `429 Too Many Requests`.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// Returns the time to wait before the next attempt of the `request` according to the rate limiter.
+    pub(crate) fn rate_limit_delay(&self, request: RpcRequest) -> Duration {
+        self.rate_limiter
+            .as_ref()
+            .map(|rate_limiter| rate_limiter.reserve(&request.to_string()))
+            .unwrap_or_default()
+    }
+
+    /// Attaches a trace id produced by `trace_id_gen` to every request under the `header_name` header.
+    pub fn with_trace_id(
+        mut self,
+        header_name: impl Into<String>,
+        trace_id_gen: impl Fn() -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.trace_id = Some((header_name.into(), Box::new(trace_id_gen)));
+        self
+    }
+
+    /// Sets a callback receiving the final transport stats when the sender is dropped.
+    pub fn with_on_drop(mut self, on_drop: impl Fn(RpcTransportStats) + Send + Sync + 'static) -> Self {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+
+    /// Replaces the function used to wait between retries, `sleep` is used by default.
+    pub fn with_sleeper(mut self, sleeper: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.sleeper = Some(Box::new(sleeper));
+        self
+    }
+
+    /// Delays every successful response by `delay`.
+    ///
+    /// This is intended for development only, e.g. to see loading states of the UI with a fast local node.
+    pub fn with_artificial_delay(mut self, delay: Duration) -> Self {
+        self.artificial_delay = Some(delay);
+        self
+    }
+
+    /// Keeps traces of the last `capacity` requests, see `recent_traces`. A zero capacity disables the traces.
+    pub fn with_trace_ring(mut self, capacity: usize) -> Self {
+        self.trace_ring = (capacity > 0).then(|| TraceRing {
+            capacity,
+            traces: RwLock::new(VecDeque::with_capacity(capacity)),
+        });
+        self
+    }
+
+    /// Returns the traces of the most recent requests, oldest first.
+    pub fn recent_traces(&self) -> Vec<RequestTrace> {
+        self.trace_ring
+            .as_ref()
+            .map(|trace_ring| trace_ring.traces.read().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Sets the number of the most recent requests `recent_rate_limit_ratio` is computed over.
+    pub fn with_rate_limit_window(mut self, len: usize) -> Self {
+        self.rate_limit_window_len = len.max(1);
+        self
+    }
+
+    /// Returns the fraction of the recent requests that were rate limited at least once, a persistently high ratio
+    /// is a sign to back off or to switch the provider.
+    pub fn recent_rate_limit_ratio(&self) -> f64 {
+        let window = self.rate_limit_window.read().unwrap();
+        if window.is_empty() {
+            return 0.0;
+        }
+        window.iter().filter(|rate_limited| **rate_limited).count() as f64 / window.len() as f64
+    }
+
+    /// Sets whether every HTTP exchange is recorded in full for replaying, see `export_traffic`.
+    pub fn with_traffic_recording(mut self, record_traffic: bool) -> Self {
+        self.traffic_log = record_traffic.then(|| RwLock::new(Vec::new()));
+        self
+    }
+
+    /// Returns the recorded HTTP exchanges, oldest first, including retried ones.
+    pub fn export_traffic(&self) -> Vec<TrafficEntry> {
+        self.traffic_log
+            .as_ref()
+            .map(|traffic_log| traffic_log.read().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets whether the bodies of the last request and response are retained as sent and received, see
+    /// `last_request_bytes` and `last_response_bytes`.
+    pub fn with_capture_wire_bytes(mut self, capture_wire_bytes: bool) -> Self {
+        self.capture_wire_bytes = capture_wire_bytes;
+        self
+    }
+
+    /// Returns the body of the last sent request, if capturing is enabled.
+    pub fn last_request_bytes(&self) -> Option<Vec<u8>> {
+        self.last_request_bytes.read().unwrap().clone()
+    }
+
+    /// Returns the body of the last received response, if capturing is enabled.
+    pub fn last_response_bytes(&self) -> Option<Vec<u8>> {
+        self.last_response_bytes.read().unwrap().clone()
+    }
+
+    /// Sets the observer receiving the structured events of the requests, it can be shared with the client.
+    pub fn with_observer(mut self, observer: Arc<dyn RpcEventObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub(crate) fn observe(&self, event: impl FnOnce() -> RpcEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event());
+        }
+    }
+
+    fn record_traffic(&self, entry: impl FnOnce() -> TrafficEntry) {
+        if let Some(traffic_log) = &self.traffic_log {
+            traffic_log.write().unwrap().push(entry());
+        }
+    }
+
+    pub(crate) fn sleep(&self, duration: Duration) {
+        match &self.sleeper {
+            Some(sleeper) => sleeper(duration),
+            None => sleep(duration),
+        }
+    }
+
+    /// Pre-allocates the per-method and per-endpoint statistics maps for `capacity` entries, see
+    /// `new_with_stats_capacity`.
+    pub fn with_stats_capacity(mut self, capacity: usize) -> Self {
+        self.session_stats.get_mut().unwrap().method_counts.reserve(capacity);
+        self.method_metrics.get_mut().unwrap().reserve(capacity);
+        self.endpoint_stats.get_mut().unwrap().reserve(capacity);
+        self
+    }
+
+    /// Returns the statistics tracked in addition to `RpcTransportStats`.
+    pub fn session_stats(&self) -> SessionStats {
+        self.session_stats.read().unwrap().clone()
+    }
+
+    /// Returns the sender configuration in a serializable form, secrets kept out.
+    pub fn config_snapshot(&self) -> ClientConfigSnapshot {
+        let mut user_error_codes: Vec<_> = self.user_error_messages.keys().copied().collect();
+        user_error_codes.sort_unstable();
+
+        ClientConfigSnapshot {
+            url: self.url(),
+            fallback_urls: self.fallback_urls.clone(),
+            max_endpoints_per_send: self.max_endpoints_per_send,
+            max_retries: self.retry_policy.max_retries,
+            retry_base_delay_ms: self.retry_policy.base_delay.as_millis() as u64,
+            reset_id_on_url_change: self.reset_id_on_url_change,
+            validate_response_id: self.validate_response_id,
+            capture_context_slot: self.capture_context_slot,
+            slot_regression_tolerance: self.slot_regression_tolerance,
+            max_logged_body_len: self.max_logged_body_len,
+            include_request_in_errors: self.include_request_in_errors,
+            user_error_codes,
+            retain_unknown_error_data: self.retain_unknown_error_data,
+            remaining_request_budget: self.remaining_request_budget(),
+            trace_id_header: self.trace_id.as_ref().map(|(header_name, _)| header_name.clone()),
+            trace_ring_capacity: self.trace_ring.as_ref().map(|trace_ring| trace_ring.capacity),
+            artificial_delay_ms: self.artificial_delay.map(|delay| delay.as_millis() as u64),
+            header_names: self.headers.iter().map(|(name, _)| name.clone()).collect(),
+            has_bearer_token: self.bearer_token.is_some(),
+            accepts_compression: self.accepts_compression(),
+            rate_limit_per_second: self.rate_limiter.as_ref().map(RateLimiter::requests_per_second),
+        }
+    }
+
+    /// Returns a serializable report of the per-method counters, latency percentiles, errors by kind, retries and
+    /// transferred bytes of the requests made so far.
+    pub fn stats_snapshot(&self) -> StatsReport {
+        let method_metrics = self.method_metrics.read().unwrap();
+        StatsReport::new(
+            method_metrics
+                .iter()
+                .map(|(method, metrics)| metrics.report(method))
+                .collect(),
+        )
+    }
+
+    /// Returns the transport stats of each endpoint the sender has used, keyed by URL.
+    pub fn stats_per_endpoint(&self) -> HashMap<String, RpcTransportStats> {
+        self.endpoint_stats.read().unwrap().clone()
+    }
+
+    pub(crate) fn log_bodies(&self, request: RpcRequest, request_body: &str, response_body: &[u8]) {
+        if let Some(hook) = &self.body_hook {
+            let response_body = String::from_utf8_lossy(response_body);
+            hook(
+                request,
+                &truncate_body(request_body, self.max_logged_body_len),
+                &truncate_body(&response_body, self.max_logged_body_len),
+            );
+        }
     }
 }
 
-impl<'a> Drop for StatsUpdater<'a> {
+impl Drop for HttpSender {
     fn drop(&mut self) {
-        let mut stats = self.stats.write().unwrap();
-        stats.request_count += 1;
-        stats.elapsed_time += Instant::now().duration_since(self.request_start_time);
-        stats.rate_limited_time += self.rate_limited_time;
+        if let Some(on_drop) = self.on_drop.take() {
+            let stats = self.stats.read().map(|stats| stats.clone()).unwrap_or_default();
+            // A panicking callback must not turn the drop into an abort
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| on_drop(stats)));
+        }
     }
 }
 
-#[derive(Deserialize, Debug)]
-struct RpcErrorObject {
-    code: i64,
-    message: String,
+/// Strips the whitespace that often surrounds URLs pasted from config files.
+fn normalize_url(url: String) -> String {
+    let trimmed = url.trim();
+    if trimmed.len() == url.len() {
+        url
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Names of the custom methods requested so far, kept for the whole run since `RpcRequest::Custom` needs a
+/// `'static` name.
+static CUSTOM_METHODS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Returns the request of the custom or provider-specific `method`, e.g. `getAsset`, its name leaked once per
+/// distinct method.
+pub fn custom_request(method: &str) -> RpcRequest {
+    let mut custom_methods = CUSTOM_METHODS.lock().unwrap();
+    let method = match custom_methods.iter().find(|custom_method| **custom_method == method) {
+        Some(custom_method) => *custom_method,
+        None => {
+            let custom_method: &'static str = Box::leak(method.to_string().into_boxed_str());
+            custom_methods.push(custom_method);
+            custom_method
+        },
+    };
+    RpcRequest::Custom { method }
+}
+
+/// Strips the query string, fragment and user credentials from the `url`.
+pub fn sanitize_url(url: &str) -> Cow<'_, str> {
+    let end = url.find(|c| c == '?' || c == '#').unwrap_or(url.len());
+    let url = &url[..end];
+
+    let authority_start = url.find("://").map_or(0, |scheme_end| scheme_end + 3);
+    let authority_end = url[authority_start..]
+        .find('/')
+        .map_or(url.len(), |path_start| authority_start + path_start);
+    match url[authority_start..authority_end].rfind('@') {
+        Some(credentials_end) => Cow::Owned(format!(
+            "{}{}",
+            &url[..authority_start],
+            &url[authority_start + credentials_end + 1..]
+        )),
+        None => Cow::Borrowed(url),
+    }
+}
+
+fn check_url(url: &str) -> Result<()> {
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or_default();
+
+    if host.is_empty() || host.starts_with('/') || url.contains(char::is_whitespace) {
+        Err(ClientErrorKind::Custom(format!("Malformed RPC endpoint URL: {:?}", url)).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Blocks the current thread for `duration`, using the Laplace host sleep when the `laplace_sleep` feature is enabled.
+pub fn sleep(duration: Duration) {
+    #[cfg(feature = "laplace_sleep")]
+    laplace_wasm::sleep::invoke(duration.as_millis() as u64);
+
+    #[cfg(not(feature = "laplace_sleep"))]
+    std::thread::sleep(duration);
+}
+
+/// Truncates `body` to at most `max_len` bytes (on a char boundary), marking the truncation with an ellipsis.
+pub fn truncate_body(body: &str, max_len: usize) -> Cow<'_, str> {
+    if body.len() <= max_len {
+        return Cow::Borrowed(body);
+    }
+
+    let mut end = max_len;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}...", &body[..end]))
+}
+
+impl HttpSender {
+    /// Sends the request of the custom or provider-specific `method`, see `custom_request`.
+    pub fn send_custom(&self, method: &str, params: Value) -> Result<Value> {
+        self.send(custom_request(method), params)
+    }
+
+    /// Sends the request once, without retrying when the endpoint is rate limiting.
+    pub fn send_once(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        self.send_with_retries(request, params, &RetryPolicy::none(), Self::parse_response)
+    }
+
+    /// Sends the request to the endpoint at `url` only, instead of the primary and fallback endpoints, with the
+    /// rest of the sender's configuration: headers, authentication, middleware, retry and timeout policies,
+    /// observer and stats.
+    pub fn send_to(&self, url: &str, request: RpcRequest, params: Value) -> Result<Value> {
+        self.send_to_endpoint(url, request, params, false)
+    }
+
+    /// Sends the request once to the endpoint at `url` only, see `send_to` and `send_once`.
+    pub fn send_once_to(&self, url: &str, request: RpcRequest, params: Value) -> Result<Value> {
+        self.send_to_endpoint(url, request, params, true)
+    }
+
+    fn send_to_endpoint(&self, url: &str, mut request: RpcRequest, mut params: Value, once: bool) -> Result<Value> {
+        for middleware in &self.middleware {
+            middleware.on_request(&mut request, &mut params)?;
+        }
+        let no_retries = RetryPolicy::none();
+        let retry_policy = if once {
+            &no_retries
+        } else {
+            self.retry_policy_for(request)
+        };
+
+        let request_id = self.next_request_id();
+        let request_json = self.build_request_json(request_id, request, params).to_string();
+        let result = self.send_body(
+            &[normalize_url(url.to_string())],
+            request,
+            request_id,
+            request_json,
+            retry_policy,
+            Self::parse_response,
+        );
+        self.apply_response_middleware(request, result)
+    }
+
+    /// Passes the result through the middleware, in the reverse order of the requests.
+    fn apply_response_middleware(&self, request: RpcRequest, result: Result<Value>) -> Result<Value> {
+        self.middleware
+            .iter()
+            .rev()
+            .fold(result, |result, middleware| middleware.on_response(request, result))
+    }
+
+    /// Returns the endpoints a request is tried on, in order.
+    fn send_urls(&self) -> Vec<String> {
+        let mut urls = self.endpoint_urls();
+        urls.truncate(self.max_endpoints_per_send);
+        urls
+    }
+
+    /// Runs `f`, retrying it on error according to the sender's retry policy. The closure receives the attempt
+    /// number, starting from 0.
+    pub fn retry_with_policy<R>(&self, mut f: impl FnMut(u32) -> Result<R>) -> Result<R> {
+        let mut attempt = 0;
+        let mut waited = Duration::ZERO;
+        loop {
+            let delay = self.retry_policy.delay(attempt);
+            match f(attempt) {
+                Err(_) if attempt < self.retry_policy.max_retries && self.retry_policy.allows_wait(waited, delay) => {
+                    self.sleep(delay);
+                    waited += delay;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends the request and deserializes the result directly into `T`, without building an intermediate `Value`
+    /// for successful responses. This saves an allocation of the whole JSON tree per call, which matters for large
+    /// responses polled in a tight loop, e.g. a `getMultipleAccounts` response of 1000 accounts, which the ignored
+    /// `send_as_benchmark` test checks to be parsed faster than through a `Value`.
+    ///
+    /// The result is the same as the one of `send` deserialized into `T`. The middleware and the coalescing work on
+    /// `Value` results, so the requests they apply to are sent by `send`, as well as the responses whose context
+    /// slot is captured or checked for regressions.
+    pub fn send_as<T: DeserializeOwned>(&self, request: RpcRequest, params: Value) -> Result<T> {
+        if !self.middleware.is_empty() || self.is_coalesced(request) {
+            let result = self.send(request, params)?;
+            return serde_json::from_value(result)
+                .map_err(|err| ClientError::new_with_request(ClientErrorKind::SerdeJson(err), request));
+        }
+        self.send_with_retries(request, params, self.retry_policy_for(request), Self::parse_response_as)
+    }
+
+    fn is_coalesced(&self, request: RpcRequest) -> bool {
+        self.coalescer.is_some() && !self.non_coalesced_methods.contains(&request.to_string())
+    }
+
+    /// Sends the requests in a single JSON-RPC batch, returning the result of each request in the input order.
+    /// An empty batch is not sent at all, an empty response to a non-empty batch is an error.
+    pub fn send_batch(&self, requests: Vec<(RpcRequest, Value)>) -> Result<Vec<Result<Value>>> {
+        let first_request = match requests.first() {
+            Some((request, _)) => *request,
+            None => return Ok(Vec::new()),
+        };
+
+        let first_id = self.request_id.fetch_add(requests.len() as u64, Ordering::Relaxed);
+        let ids: Vec<u64> = (first_id..first_id + requests.len() as u64).collect();
+        let batch_json = Value::Array(
+            requests
+                .into_iter()
+                .zip(&ids)
+                .map(|((request, params), id)| self.build_request_json(*id, request, params))
+                .collect(),
+        )
+        .to_string();
+
+        self.send_body(
+            &self.send_urls(),
+            first_request,
+            first_id,
+            batch_json,
+            &self.retry_policy,
+            |sender, _, body| sender.parse_batch_response(&ids, body),
+        )
+    }
+
+    pub(crate) fn next_request_id(&self) -> u64 {
+        self.request_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn send_with_retries<T>(
+        &self,
+        request: RpcRequest,
+        params: Value,
+        retry_policy: &RetryPolicy,
+        parse: impl Fn(&Self, u64, &[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let request_id = self.next_request_id();
+        let request_json = self.build_request_json(request_id, request, params).to_string();
+        self.send_body(
+            &self.send_urls(),
+            request,
+            request_id,
+            request_json,
+            retry_policy,
+            parse,
+        )
+    }
+
+    fn send_body<T>(
+        &self,
+        urls: &[String],
+        request: RpcRequest,
+        request_id: u64,
+        request_json: String,
+        retry_policy: &RetryPolicy,
+        parse: impl Fn(&Self, u64, &[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let mut stats_updater = self.start_send(request, &request_json)?;
+        let mut result = Err(no_endpoint_error(request));
+        for url in urls {
+            self.observe(|| RpcEvent::RequestStarted {
+                method: request.to_string(),
+                url: sanitize_url(url).into_owned(),
+            });
+            result = self.exchange(
+                request,
+                request_id,
+                url,
+                &request_json,
+                retry_policy,
+                &mut stats_updater,
+                &parse,
+            );
+            if !matches!(&result, Err(err) if is_endpoint_failure(err)) {
+                break;
+            }
+        }
+        self.finish_send(result, stats_updater, &request_json)
+    }
+
+    /// Checks that the request can be sent and starts its statistics.
+    fn start_send(&self, request: RpcRequest, request_json: &str) -> Result<StatsUpdater<'_>> {
+        if let Some(is_online) = &self.is_online {
+            if !is_online() {
+                return Err(ClientError::new_with_request(
+                    ClientErrorKind::Custom("Offline: the host has no network connectivity".to_string()),
+                    request,
+                ));
+            }
+        }
+
+        if let Some(budget) = &self.request_budget {
+            if budget
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |budget| budget.checked_sub(1))
+                .is_err()
+            {
+                return Err(ClientError::new_with_request(
+                    ClientErrorKind::Custom("BudgetExhausted: the request budget is exhausted".to_string()),
+                    request,
+                ));
+            }
+        }
+
+        self.check_memory_ceiling(request)?;
+
+        let mut stats_updater = StatsUpdater::new(self, request);
+        if self.trace_ring.is_some() {
+            if let Ok(request_json) = serde_json::from_str::<Value>(request_json) {
+                stats_updater.set_params_summary(summarize_params(request, &request_json["params"]));
+            }
+        }
+        Ok(stats_updater)
+    }
+
+    /// Records the outcome of the request in its statistics and completes the error.
+    fn finish_send<T>(&self, result: Result<T>, mut stats_updater: StatsUpdater, request_json: &str) -> Result<T> {
+        if let Err(err) = &result {
+            stats_updater.set_error_kind(error_kind(err));
+        }
+        if self.include_request_in_errors {
+            result.map_err(|err| attach_request_body(err, &truncate_body(request_json, self.max_logged_body_len)))
+        } else {
+            result
+        }
+    }
+
+    /// Makes the HTTP exchange, retrying it according to the `retry_policy`.
+    fn exchange<T>(
+        &self,
+        request: RpcRequest,
+        request_id: u64,
+        url: &str,
+        request_json: &str,
+        retry_policy: &RetryPolicy,
+        stats_updater: &mut StatsUpdater,
+        parse: impl Fn(&Self, u64, &[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let mut exchange = self.start_exchange(request, request_id, url, request_json, retry_policy, stats_updater);
+        loop {
+            let delay = self.before_attempt(&exchange, stats_updater)?;
+            if !delay.is_zero() {
+                self.sleep(delay);
+            }
+
+            let http_request = self.attempt_request(&exchange, stats_updater);
+            let exchange_start_time = Instant::now();
+            let http_response = self.invoke(http_request);
+            match self.after_attempt(&mut exchange, stats_updater, http_response, exchange_start_time, &parse)? {
+                AttemptOutcome::Parsed(result) => {
+                    if let Some(delay) = self.artificial_delay {
+                        self.sleep(delay);
+                    }
+                    return Ok(result);
+                },
+                AttemptOutcome::Retry(delay) => self.sleep(delay),
+            }
+        }
+    }
+
+    fn start_exchange<'a>(
+        &'a self,
+        request: RpcRequest,
+        request_id: u64,
+        url: &'a str,
+        request_json: &'a str,
+        retry_policy: &'a RetryPolicy,
+        stats_updater: &mut StatsUpdater,
+    ) -> Exchange<'a> {
+        stats_updater.set_endpoint(url);
+        Exchange {
+            request,
+            request_id,
+            url,
+            request_json,
+            retry_policy,
+            trace_id: self
+                .trace_id
+                .as_ref()
+                .map(|(header_name, trace_id_gen)| (header_name.as_str(), trace_id_gen())),
+            timeout: self.timeout_policy.timeout(&request.to_string()),
+            attempt: 0,
+            waited: Duration::ZERO,
+        }
+    }
+
+    /// Checks the timeout of the request and returns the delay required by the client-side rate limiter before the
+    /// next attempt.
+    fn before_attempt(&self, exchange: &Exchange, stats_updater: &StatsUpdater) -> Result<Duration> {
+        let request = exchange.request;
+        if let Some(timeout) = exchange.timeout {
+            if stats_updater.request_start_time.elapsed() >= timeout {
+                return Err(ClientError::new_with_request(
+                    ClientErrorKind::Custom(format!("Timeout: the request exceeded its timeout of {:?}", timeout)),
+                    request,
+                ));
+            }
+        }
+
+        let delay = self.rate_limit_delay(request);
+        if !delay.is_zero() {
+            self.observe(|| RpcEvent::RateLimited {
+                method: request.to_string(),
+                delay,
+                client_side: true,
+            });
+        }
+        Ok(delay)
+    }
+
+    /// Returns the HTTP request of the next attempt.
+    fn attempt_request(&self, exchange: &Exchange, stats_updater: &StatsUpdater) -> HostHttpRequest {
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        headers.extend(self.request_headers());
+        if let Some((header_name, trace_id)) = &exchange.trace_id {
+            headers.push((header_name.to_string(), trace_id.clone()));
+        }
+        if self.capture_wire_bytes {
+            *self.last_request_bytes.write().unwrap() = Some(exchange.request_json.as_bytes().to_vec());
+        }
+
+        HostHttpRequest {
+            url: exchange.url.to_string(),
+            headers,
+            body: exchange.request_json.as_bytes().to_vec(),
+            timeout: exchange
+                .timeout
+                .map(|timeout| timeout.saturating_sub(stats_updater.request_start_time.elapsed())),
+        }
+    }
+
+    /// Handles the outcome of the attempt started at `exchange_start_time`, either parsing the response or deciding
+    /// to retry.
+    fn after_attempt<T>(
+        &self,
+        exchange: &mut Exchange,
+        stats_updater: &mut StatsUpdater,
+        http_response: std::result::Result<HostHttpResponse, String>,
+        exchange_start_time: Instant,
+        parse: impl Fn(&Self, u64, &[u8]) -> Result<T>,
+    ) -> Result<AttemptOutcome<T>> {
+        let Exchange {
+            request,
+            request_id,
+            url,
+            request_json,
+            retry_policy,
+            ..
+        } = *exchange;
+        let http_response = match http_response {
+            Ok(http_response) => http_response,
+            Err(err) => {
+                self.record_traffic(|| TrafficEntry::new(request, url, request_json, None, "", exchange_start_time));
+                let duration = retry_policy.delay(exchange.attempt);
+                if retry_policy.retry_transport_errors
+                    && exchange.attempt < retry_policy.max_retries
+                    && retry_policy.allows_wait(exchange.waited, duration)
+                {
+                    self.observe(|| RpcEvent::RetryScheduled {
+                        method: request.to_string(),
+                        attempt: exchange.attempt + 1,
+                        delay: duration,
+                        http_status: None,
+                    });
+                    exchange.attempt += 1;
+                    exchange.waited += duration;
+                    stats_updater.add_retry();
+                    return Ok(AttemptOutcome::Retry(duration));
+                }
+                return Err(self.transport_error(request, url, err));
+            },
+        };
+        let content_encoding = http_response.header("content-encoding");
+        let body = self.decode_body(request, content_encoding, &http_response.body)?;
+        let mut session_stats = self.session_stats.write().unwrap();
+        session_stats.wire_bytes_received += http_response.body.len() as u64;
+        session_stats.decoded_bytes_received += body.len() as u64;
+        drop(session_stats);
+
+        self.record_traffic(|| {
+            TrafficEntry::new(
+                request,
+                url,
+                request_json,
+                Some(http_response.status),
+                &String::from_utf8_lossy(&body),
+                exchange_start_time,
+            )
+        });
+        if self.capture_wire_bytes {
+            *self.last_response_bytes.write().unwrap() = Some(http_response.body.clone());
+        }
+        if let Some(on_attempt_complete) = &self.on_attempt_complete {
+            on_attempt_complete(exchange.attempt, exchange_start_time.elapsed(), http_response.status);
+        }
+        self.log_bodies(request, request_json, &body);
+        stats_updater.add_bytes(request_json.len(), http_response.body.len());
+        self.last_http_status.store(http_response.status, Ordering::Relaxed);
+        stats_updater.set_http_status(http_response.status);
+
+        if !http_response.is_success() {
+            let rate_limited = http_response.status == 429;
+            if retry_policy.is_retryable_status(http_response.status) && exchange.attempt < retry_policy.max_retries {
+                let duration = http_response
+                    .retry_after()
+                    .unwrap_or_else(|| retry_policy.delay(exchange.attempt));
+                if !retry_policy.allows_wait(exchange.waited, duration) {
+                    return Err(http_status_error(request, http_response.status));
+                }
+
+                self.observe(|| {
+                    if rate_limited {
+                        RpcEvent::RateLimited {
+                            method: request.to_string(),
+                            delay: duration,
+                            client_side: false,
+                        }
+                    } else {
+                        RpcEvent::RetryScheduled {
+                            method: request.to_string(),
+                            attempt: exchange.attempt + 1,
+                            delay: duration,
+                            http_status: Some(http_response.status),
+                        }
+                    }
+                });
+                exchange.attempt += 1;
+                exchange.waited += duration;
+                if rate_limited {
+                    stats_updater.add_rate_limited_time(duration);
+                } else {
+                    stats_updater.add_retry();
+                }
+                return Ok(AttemptOutcome::Retry(duration));
+            }
+            return Err(http_status_error(request, http_response.status));
+        }
+
+        let reservation = self.reserve_body_bytes(request, body.len())?;
+        let result = parse(self, request_id, &body);
+        drop(reservation);
+        let result = result?;
+        stats_updater.set_succeeded();
+        Ok(AttemptOutcome::Parsed(result))
+    }
+
+    pub(crate) fn parse_response(&self, request_id: u64, body: &[u8]) -> Result<Value> {
+        self.check_body(body)?;
+        self.handle_response_json(request_id, serde_json::from_slice(body)?)
+    }
+
+    fn parse_batch_response(&self, ids: &[u64], body: &[u8]) -> Result<Vec<Result<Value>>> {
+        self.check_body(body)?;
+
+        let json: Value = serde_json::from_slice(body)?;
+        let mut responses = match json {
+            Value::Array(responses) if responses.is_empty() => {
+                return Err(
+                    RpcError::RpcRequestError("RPC endpoint returned an empty batch response".to_string()).into(),
+                )
+            },
+            Value::Array(responses) => responses,
+            // An error for the whole batch, e.g. when the endpoint doesn't support batches
+            json => {
+                self.handle_response_json(ids[0], json)?;
+                return Err(RpcError::RpcRequestError("RPC endpoint returned a non-batch response".to_string()).into());
+            },
+        };
+
+        Ok(ids
+            .iter()
+            .map(|id| {
+                let position = responses.iter().position(|response| response["id"] == *id);
+                match position {
+                    Some(position) => self.handle_response_json(*id, responses.swap_remove(position)),
+                    None => Err(RpcError::RpcRequestError(format!(
+                        "Batch response has no result for request id {}",
+                        id
+                    ))
+                    .into()),
+                }
+            })
+            .collect())
+    }
+
+    fn handle_response_json(&self, request_id: u64, mut json: Value) -> Result<Value> {
+        if self.validate_response_id && json["id"] != request_id {
+            return Err(RpcError::RpcRequestError(format!(
+                "Response id {} doesn't match request id {}",
+                json["id"], request_id
+            ))
+            .into());
+        }
+        if let Some(error) = json["error"].as_str() {
+            // Load balancers may reply with a non JSON-RPC error, like `{"error":"upstream unavailable"}`
+            return Err(RpcError::RpcRequestError(format!("RPC endpoint returned an error: {}", error)).into());
+        }
+        if json["error"].is_object() {
+            return Err(self.response_error(&json["error"]));
+        }
+        if let Some(warning) = json["warning"].as_str() {
+            self.warn(warning);
+        }
+        if self.capture_context_slot {
+            if let Some(slot) = json["result"]["context"]["slot"].as_u64() {
+                *self.last_context_slot.write().unwrap() = Some(slot);
+            }
+        }
+        if let Some(tolerance) = self.slot_regression_tolerance {
+            if let Some(slot) = json["result"]["context"]["slot"].as_u64() {
+                self.check_slot_regression(slot, tolerance)?;
+            }
+        }
+
+        Ok(json["result"].take())
+    }
+
+    fn check_body(&self, body: &[u8]) -> Result<()> {
+        if let Some(max_json_depth) = self.max_json_depth {
+            check_json_depth(body, max_json_depth)?;
+        }
+        Ok(())
+    }
+
+    fn warn(&self, warning: &str) {
+        if let Some(on_warning) = &self.on_warning {
+            on_warning(warning);
+        }
+    }
+
+    fn check_slot_regression(&self, slot: Slot, tolerance: u64) -> Result<()> {
+        let max_slot = self.max_context_slot.fetch_max(slot, Ordering::Relaxed);
+        if slot.saturating_add(tolerance) >= max_slot {
+            return Ok(());
+        }
+
+        match &self.on_slot_regression {
+            Some(on_slot_regression) => {
+                on_slot_regression(max_slot, slot);
+                Ok(())
+            },
+            None => Err(RpcError::RpcRequestError(format!(
+                "Response context slot {} regressed from the already seen slot {}",
+                slot, max_slot
+            ))
+            .into()),
+        }
+    }
+
+    fn response_error(&self, error: &Value) -> ClientError {
+        let user_message = error["code"]
+            .as_i64()
+            .and_then(|code| self.user_error_messages.get(&code));
+        let err = match user_message {
+            Some(message) => RpcError::ForUser(message.clone()).into(),
+            None => rpc_error_from_json(error),
+        };
+
+        if self.retain_unknown_error_data && !error["data"].is_null() {
+            if let ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::Empty,
+                ..
+            }) = err.kind()
+            {
+                *self.last_unknown_error_data.write().unwrap() = Some(error["data"].clone());
+            }
+        }
+        err
+    }
+
+    fn parse_response_as<T: DeserializeOwned>(&self, request_id: u64, body: &[u8]) -> Result<T> {
+        // The context slot is only reachable through the generic path
+        if self.capture_context_slot || self.slot_regression_tolerance.is_some() {
+            return Ok(serde_json::from_value(self.parse_response(request_id, body)?)?);
+        }
+        self.check_body(body)?;
+
+        match serde_json::from_slice::<TypedResponse<T>>(body) {
+            Ok(TypedResponse {
+                id,
+                result: Some(result),
+                error: None,
+                warning,
+            }) if !self.validate_response_id || id == request_id => {
+                if let Some(warning) = warning {
+                    self.warn(&warning);
+                }
+                Ok(result)
+            },
+            // Error responses are rare, so they are simply handled by the generic path
+            _ => Ok(serde_json::from_value(self.parse_response(request_id, body)?)?),
+        }
+    }
+}
+
+impl RpcSender for HttpSender {
+    fn send(&self, mut request: RpcRequest, mut params: Value) -> Result<Value> {
+        for middleware in &self.middleware {
+            middleware.on_request(&mut request, &mut params)?;
+        }
+        let result = match &self.coalescer {
+            Some(coalescer) if self.is_coalesced(request) => coalescer.run(request.to_string(), &params, || {
+                self.send_with_retries(
+                    request,
+                    params.clone(),
+                    self.retry_policy_for(request),
+                    Self::parse_response,
+                )
+            }),
+            _ => self.send_with_retries(request, params, self.retry_policy_for(request), Self::parse_response),
+        };
+        self.apply_response_middleware(request, result)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.stats.read().unwrap().clone()
+    }
+}
+
+/// Serializable snapshot of the `HttpSender` configuration, see `HttpSender::config_snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientConfigSnapshot {
+    pub url: String,
+    pub fallback_urls: Vec<String>,
+    pub max_endpoints_per_send: usize,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub reset_id_on_url_change: bool,
+    pub validate_response_id: bool,
+    pub capture_context_slot: bool,
+    pub slot_regression_tolerance: Option<u64>,
+    pub max_logged_body_len: usize,
+    pub include_request_in_errors: bool,
+    pub user_error_codes: Vec<i64>,
+    pub retain_unknown_error_data: bool,
+    pub remaining_request_budget: Option<u64>,
+    pub trace_id_header: Option<String>,
+    pub trace_ring_capacity: Option<usize>,
+    pub artificial_delay_ms: Option<u64>,
+
+    /// Names of the custom headers, their values may be secrets.
+    pub header_names: Vec<String>,
+    pub has_bearer_token: bool,
+    pub accepts_compression: bool,
+    pub rate_limit_per_second: Option<u32>,
+}
+
+/// Builder of an `HttpSender`, see `HttpSender::builder`.
+pub struct HttpSenderBuilder {
+    sender: HttpSender,
+}
+
+impl HttpSenderBuilder {
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.sender = self.sender.with_header(name, value);
+        self
+    }
+
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.sender = self.sender.with_bearer_token(token);
+        self
+    }
+
+    pub fn bearer_token_provider(mut self, provider: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        self.sender = self.sender.with_bearer_token_provider(provider);
+        self
+    }
+
+    /// Applies the `with_*` setters of `HttpSender` not mirrored by the builder.
+    pub fn configure(mut self, configure: impl FnOnce(HttpSender) -> HttpSender) -> Self {
+        self.sender = configure(self.sender);
+        self
+    }
+
+    pub fn build(self) -> HttpSender {
+        self.sender
+    }
+}
+
+/// Statistics of a sender session that `RpcTransportStats` doesn't cover.
+#[derive(Debug, Default, Clone)]
+pub struct SessionStats {
+    pub error_count: u64,
+    pub max_latency: Duration,
+    pub method_counts: HashMap<String, u64>,
+
+    /// Response body bytes as received, compressed if the endpoint compressed them.
+    pub wire_bytes_received: u64,
+
+    /// Response body bytes after the decompression.
+    pub decoded_bytes_received: u64,
+}
+
+/// Sender sharing an `HttpSender` with other owners, so it stays accessible after being moved into an `RpcClient`.
+pub struct SharedHttpSender(pub Arc<HttpSender>);
+
+impl RpcSender for SharedHttpSender {
+    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        self.0.send(request, params)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.0.get_transport_stats()
+    }
+}
+
+/// Response body bytes accounted as in flight until dropped.
+struct BodyBytesReservation<'a> {
+    in_flight_body_bytes: &'a AtomicUsize,
+    len: usize,
+}
+
+impl Drop for BodyBytesReservation<'_> {
+    fn drop(&mut self) {
+        self.in_flight_body_bytes.fetch_sub(self.len, Ordering::AcqRel);
+    }
+}
+
+/// Summary of a request kept for debugging, see `HttpSender::recent_traces`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTrace {
+    pub method: String,
+    pub http_status: Option<u16>,
+    pub duration: Duration,
+    pub retries: u32,
+    pub succeeded: bool,
+    /// Request params, with large arrays of known methods summarized, see `summarize_params`.
+    pub params: String,
+}
+
+/// Recorded HTTP exchange, see `HttpSender::export_traffic`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficEntry {
+    pub method: String,
+    pub url: String,
+    pub request_body: String,
+    /// HTTP status of the response, `None` if the exchange failed at the transport level.
+    pub status: Option<u16>,
+    pub response_body: String,
+    pub duration_ms: u64,
+}
+
+impl TrafficEntry {
+    fn new(
+        request: RpcRequest,
+        url: &str,
+        request_body: &str,
+        status: Option<u16>,
+        response_body: &str,
+        start_time: Instant,
+    ) -> Self {
+        Self {
+            method: request.to_string(),
+            url: sanitize_url(url).into_owned(),
+            request_body: request_body.to_string(),
+            status,
+            response_body: response_body.to_string(),
+            duration_ms: start_time.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+struct TraceRing {
+    capacity: usize,
+    traces: RwLock<VecDeque<RequestTrace>>,
+}
+
+impl TraceRing {
+    fn push(&self, trace: RequestTrace) {
+        let mut traces = self.traces.write().unwrap();
+        while traces.len() >= self.capacity {
+            traces.pop_front();
+        }
+        traces.push_back(trace);
+    }
+}
+
+/// Attempts of a request on one endpoint, shared by the blocking and the non-blocking senders.
+struct Exchange<'a> {
+    request: RpcRequest,
+    request_id: u64,
+    url: &'a str,
+    request_json: &'a str,
+    retry_policy: &'a RetryPolicy,
+    trace_id: Option<(&'a str, String)>,
+    timeout: Option<Duration>,
+    attempt: u32,
+    waited: Duration,
+}
+
+enum AttemptOutcome<T> {
+    Parsed(T),
+
+    /// The attempt failed and is retried after the delay.
+    Retry(Duration),
+}
+
+struct StatsUpdater<'a> {
+    sender: &'a HttpSender,
+    request: RpcRequest,
+    endpoint: Option<String>,
+    request_start_time: Instant,
+    rate_limited_time: Duration,
+    http_status: Option<u16>,
+    retries: u32,
+    rate_limited: bool,
+    succeeded: bool,
+    params_summary: Option<String>,
+    error_kind: Option<String>,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl<'a> StatsUpdater<'a> {
+    fn new(sender: &'a HttpSender, request: RpcRequest) -> Self {
+        Self {
+            sender,
+            request,
+            endpoint: None,
+            request_start_time: Instant::now(),
+            rate_limited_time: Duration::default(),
+            http_status: None,
+            retries: 0,
+            rate_limited: false,
+            succeeded: false,
+            params_summary: None,
+            error_kind: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+        }
+    }
+
+    fn add_rate_limited_time(&mut self, duration: Duration) {
+        self.rate_limited_time += duration;
+        self.rate_limited = true;
+        self.add_retry();
+    }
+
+    fn add_retry(&mut self) {
+        self.retries += 1;
+    }
+
+    fn set_endpoint(&mut self, url: &str) {
+        self.endpoint = Some(url.to_string());
+    }
+
+    fn set_http_status(&mut self, http_status: u16) {
+        self.http_status = Some(http_status);
+    }
+
+    fn set_succeeded(&mut self) {
+        self.succeeded = true;
+    }
+
+    fn set_params_summary(&mut self, params_summary: String) {
+        self.params_summary = Some(params_summary);
+    }
+
+    fn set_error_kind(&mut self, error_kind: String) {
+        self.error_kind = Some(error_kind);
+    }
+
+    fn add_bytes(&mut self, sent: usize, received: usize) {
+        self.bytes_sent += sent as u64;
+        self.bytes_received += received as u64;
+    }
+}
+
+impl<'a> Drop for StatsUpdater<'a> {
+    fn drop(&mut self) {
+        let elapsed_time = Instant::now().duration_since(self.request_start_time);
+
+        let mut stats = self.sender.stats.write().unwrap();
+        stats.request_count += 1;
+        stats.elapsed_time += elapsed_time;
+        stats.rate_limited_time += self.rate_limited_time;
+        drop(stats);
+
+        if let Some(endpoint) = self.endpoint.take() {
+            let mut endpoint_stats = self.sender.endpoint_stats.write().unwrap();
+            let stats = endpoint_stats.entry(endpoint).or_default();
+            stats.request_count += 1;
+            stats.elapsed_time += elapsed_time;
+            stats.rate_limited_time += self.rate_limited_time;
+        }
+
+        let mut session_stats = self.sender.session_stats.write().unwrap();
+        if !self.succeeded {
+            session_stats.error_count += 1;
+        }
+        session_stats.max_latency = session_stats.max_latency.max(elapsed_time);
+        *session_stats.method_counts.entry(self.request.to_string()).or_default() += 1;
+        drop(session_stats);
+
+        let error_kind = match (&self.error_kind, self.succeeded) {
+            (Some(error_kind), _) => Some(error_kind.as_str()),
+            // E.g. the request was dropped before the sender produced a result
+            (None, false) => Some("Unknown"),
+            (None, true) => None,
+        };
+        self.sender
+            .method_metrics
+            .write()
+            .unwrap()
+            .entry(self.request.to_string())
+            .or_default()
+            .record(
+                elapsed_time,
+                self.retries,
+                error_kind,
+                self.bytes_sent,
+                self.bytes_received,
+            );
+        self.sender.observe(|| RpcEvent::RequestFinished {
+            method: self.request.to_string(),
+            latency: elapsed_time,
+            http_status: self.http_status,
+            error_kind: error_kind.map(str::to_string),
+        });
+
+        let mut rate_limit_window = self.sender.rate_limit_window.write().unwrap();
+        while rate_limit_window.len() >= self.sender.rate_limit_window_len {
+            rate_limit_window.pop_front();
+        }
+        rate_limit_window.push_back(self.rate_limited || self.http_status == Some(429));
+        drop(rate_limit_window);
+
+        if let Some(trace_ring) = &self.sender.trace_ring {
+            trace_ring.push(RequestTrace {
+                method: self.request.to_string(),
+                http_status: self.http_status,
+                duration: elapsed_time,
+                retries: self.retries,
+                succeeded: self.succeeded,
+                params: self.params_summary.take().unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// Formats the request params for logging, summarizing the key arrays of methods that take many of them,
+/// e.g. as `"[120 pubkeys]"` for `getMultipleAccounts`.
+pub fn summarize_params(request: RpcRequest, params: &Value) -> String {
+    let items_name = match request {
+        RpcRequest::GetMultipleAccounts => "pubkeys",
+        RpcRequest::GetSignatureStatuses => "signatures",
+        _ => return params.to_string(),
+    };
+
+    match params.as_array().and_then(|params| params.split_first()) {
+        Some((Value::Array(items), other_params)) if items.len() > SUMMARIZED_ARRAY_LEN => {
+            let summary = Value::String(format!("[{} {}]", items.len(), items_name));
+            Value::Array(iter::once(summary).chain(other_params.iter().cloned()).collect()).to_string()
+        },
+        _ => params.to_string(),
+    }
+}
+
+fn no_endpoint_error(request: RpcRequest) -> ClientError {
+    ClientError::new_with_request(
+        ClientErrorKind::Custom("no endpoint to send the request to".to_string()),
+        request,
+    )
+}
+
+fn memory_ceiling_error(request: RpcRequest, ceiling: usize) -> ClientError {
+    ClientError::new_with_request(
+        ClientErrorKind::Custom(format!(
+            "MemoryCeiling: the memory ceiling of {} bytes is reached",
+            ceiling
+        )),
+        request,
+    )
+}
+
+/// Returns the error of a response with the unsuccessful HTTP `status`, its message starting with the status code.
+pub(crate) fn http_status_error(request: RpcRequest, status: u16) -> ClientError {
+    let message = http::StatusCode::from_u16(status).map_or_else(|_| status.to_string(), |status| status.to_string());
+    ClientError::new_with_request(ClientErrorKind::RpcError(RpcError::ForUser(message)), request)
+}
+
+/// Prefixes of the custom errors raised by the client or the host rather than by the endpoint: deadlines, memory
+/// limits, missing connectivity, exhausted budgets, cancellations, open circuits and refused resends.
+const NON_ENDPOINT_ERROR_PREFIXES: &[&str] = &[
+    "Timeout:",
+    "MemoryCeiling:",
+    "Offline:",
+    "BudgetExhausted:",
+    "Cancelled:",
+    "CircuitOpen:",
+    "AlreadyPending:",
+];
+
+/// Checks whether the request failed because of the endpoint rather than the request itself, so it is worth trying
+/// on another one: a transport error or an HTTP server error or rate limiting status.
+pub(crate) fn is_endpoint_failure(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Custom(message) => !NON_ENDPOINT_ERROR_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix)),
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => message
+            .split(' ')
+            .next()
+            .and_then(|status| status.parse::<u16>().ok())
+            .map_or(false, |status| status >= 500 || status == 429),
+        _ => false,
+    }
+}
+
+/// Converts a JSON-RPC error object into a client error.
+pub(crate) fn rpc_error_from_json(error: &Value) -> ClientError {
+    match serde_json::from_value::<RpcErrorObject>(error.clone()) {
+        Ok(rpc_error_object) => {
+            let data = match rpc_error_object.code {
+                rpc_custom_error::JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE => {
+                    match serde_json::from_value::<RpcSimulateTransactionResult>(error["data"].clone()) {
+                        Ok(data) => RpcResponseErrorData::SendTransactionPreflightFailure(data),
+                        Err(_) => RpcResponseErrorData::Empty,
+                    }
+                },
+                rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY => {
+                    match serde_json::from_value::<rpc_custom_error::NodeUnhealthyErrorData>(error["data"].clone()) {
+                        Ok(rpc_custom_error::NodeUnhealthyErrorData { num_slots_behind }) => {
+                            RpcResponseErrorData::NodeUnhealthy { num_slots_behind }
+                        },
+                        Err(_err) => RpcResponseErrorData::Empty,
+                    }
+                },
+                _ => RpcResponseErrorData::Empty,
+            };
+
+            RpcError::RpcResponseError {
+                code: rpc_error_object.code,
+                message: rpc_error_object.message,
+                data,
+            }
+            .into()
+        },
+        Err(err) => RpcError::RpcRequestError(format!(
+            "Failed to deserialize RPC error response: {} [{}]",
+            serde_json::to_string(error).unwrap(),
+            err
+        ))
+        .into(),
+    }
+}
+
+/// Appends the request body to the error message, if the error kind has one.
+fn attach_request_body(err: ClientError, request_body: &str) -> ClientError {
+    let request = err.request().copied();
+    let kind = match err.kind {
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) => ClientErrorKind::RpcError(
+            RpcError::RpcRequestError(format!("{} [request: {}]", message, request_body)),
+        ),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, data }) => {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code,
+                message: format!("{} [request: {}]", message, request_body),
+                data,
+            })
+        },
+        ClientErrorKind::RpcError(RpcError::ParseError(message)) => {
+            ClientErrorKind::RpcError(RpcError::ParseError(format!("{} [request: {}]", message, request_body)))
+        },
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => {
+            ClientErrorKind::RpcError(RpcError::ForUser(format!("{} [request: {}]", message, request_body)))
+        },
+        ClientErrorKind::Custom(message) => ClientErrorKind::Custom(format!("{} [request: {}]", message, request_body)),
+        // Kept as is for the callers matching them, e.g. `TransactionError`
+        kind => kind,
+    };
+
+    match request {
+        Some(request) => ClientError::new_with_request(kind, request),
+        None => kind.into(),
+    }
+}
+
+/// Rejects JSON nested deeper than `max_depth` arrays and objects.
+fn check_json_depth(body: &[u8], max_depth: usize) -> Result<()> {
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(RpcError::ParseError(format!(
+                        "response JSON is nested deeper than the limit of {} levels",
+                        max_depth
+                    ))
+                    .into());
+                }
+            },
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct TypedResponse<T> {
+    #[serde(default)]
+    id: Value,
+    result: Option<T>,
+    #[serde(default)]
+    error: Option<Value>,
+    #[serde(default)]
+    warning: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Weak},
+        thread,
+    };
+
+    use serde_json::json;
+    use solana_account_decoder::UiAccount;
+    use solana_client_api::rpc_response::Response;
+    use solana_sdk::transaction::TransactionError;
+
+    use super::*;
+
+    const TEST_URL: &str = "http://localhost:8899";
+
+    fn http_response(status: u16, body: &str) -> HostHttpResponse {
+        HostHttpResponse {
+            status,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn result_response(result: Value) -> HostHttpResponse {
+        http_response(200, &json!({"jsonrpc": "2.0", "id": 0, "result": result}).to_string())
+    }
+
+    fn no_delay_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn record_request(sender: &HttpSender, request: RpcRequest, succeeded: bool) {
+        let mut stats_updater = StatsUpdater::new(sender, request);
+        stats_updater.set_endpoint(TEST_URL);
+        if succeeded {
+            stats_updater.set_succeeded();
+        } else {
+            stats_updater.set_error_kind("Timeout".to_string());
+        }
+    }
+
+    #[test]
+    fn client_side_failures_are_not_endpoint_failures() {
+        let custom = |message: &str| ClientError::from(ClientErrorKind::Custom(message.to_string()));
+        let for_user = |message: &str| ClientError::from(RpcError::ForUser(message.to_string()));
+
+        assert!(is_endpoint_failure(&custom("connection refused")));
+        assert!(is_endpoint_failure(&for_user("503 Service Unavailable")));
+        assert!(is_endpoint_failure(&for_user("429 Too Many Requests")));
+
+        for prefix in NON_ENDPOINT_ERROR_PREFIXES {
+            assert!(
+                !is_endpoint_failure(&custom(&format!("{} failure", prefix))),
+                "{}",
+                prefix
+            );
+        }
+        assert!(!is_endpoint_failure(&for_user("400 Bad Request")));
+        assert!(!is_endpoint_failure(&for_user("transaction fee payer is unknown")));
+        assert!(!is_endpoint_failure(
+            &RpcError::ParseError("invalid".to_string()).into()
+        ));
+    }
+
+    #[test]
+    fn memory_ceiling_bounds_concurrent_reservations() {
+        let sender = HttpSender::new(TEST_URL).with_global_memory_ceiling(100);
+
+        let first = sender.reserve_body_bytes(RpcRequest::GetSlot, 60).unwrap();
+        let err = sender.reserve_body_bytes(RpcRequest::GetSlot, 60).err().unwrap();
+        assert_eq!(error_kind(&err), "MemoryCeiling");
+        assert_eq!(sender.in_flight_body_bytes(), 60);
+
+        let second = sender.reserve_body_bytes(RpcRequest::GetSlot, 40).unwrap();
+        assert_eq!(sender.in_flight_body_bytes(), 100);
+        assert!(sender.check_memory_ceiling(RpcRequest::GetSlot).is_err());
+
+        drop(first);
+        drop(second);
+        assert_eq!(sender.in_flight_body_bytes(), 0);
+        assert!(sender.check_memory_ceiling(RpcRequest::GetSlot).is_ok());
+    }
+
+    #[test]
+    fn responses_wait_while_concurrent_ones_hold_the_in_flight_limit() {
+        let body = json!({"jsonrpc": "2.0", "id": 0, "result": 42}).to_string();
+        let waits = Arc::new(AtomicUsize::new(0));
+        let sender = HttpSender::new(TEST_URL)
+            .with_max_in_flight_body_bytes(body.len() + 10)
+            .with_sleeper({
+                let waits = waits.clone();
+                move |_| {
+                    waits.fetch_add(1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+            .with_transport(move |_| Ok(http_response(200, &body)));
+
+        // A concurrent response being parsed holds most of the limit
+        let concurrent = sender.reserve_body_bytes(RpcRequest::GetBalance, 20).unwrap();
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| sender.send(RpcRequest::GetSlot, Value::Null));
+            while waits.load(Ordering::SeqCst) == 0 {
+                thread::yield_now();
+            }
+            assert!(!waiting.is_finished());
+            assert_eq!(sender.in_flight_body_bytes(), 20);
+
+            drop(concurrent);
+            assert_eq!(waiting.join().unwrap().unwrap(), json!(42));
+        });
+        assert_eq!(sender.in_flight_body_bytes(), 0);
+
+        // A single body larger than the limit is parsed when no other one is in flight
+        let sender = HttpSender::new(TEST_URL)
+            .with_max_in_flight_body_bytes(1)
+            .with_transport(|_| Ok(result_response(json!(42))));
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(42));
+    }
+
+    #[test]
+    fn request_body_is_attached_to_message_kinds_only() {
+        let err = attach_request_body(
+            ClientError::new_with_request(
+                ClientErrorKind::Custom("Timeout: too long".to_string()),
+                RpcRequest::GetSlot,
+            ),
+            "{}",
+        );
+        assert_eq!(err.request(), Some(&RpcRequest::GetSlot));
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message == "Timeout: too long [request: {}]"));
+
+        let err = attach_request_body(RpcError::ForUser("503 Service Unavailable".to_string()).into(), "{}");
+        assert!(is_endpoint_failure(&err));
+
+        let err = attach_request_body(TransactionError::AccountNotFound.into(), "{}");
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::TransactionError(TransactionError::AccountNotFound)
+        ));
+    }
+
+    const ACCOUNT_RESPONSE: &str = r#"{
+        "jsonrpc": "2.0",
+        "id": 0,
+        "result": {
+            "context": { "slot": 150 },
+            "value": {
+                "lamports": 1000000,
+                "data": ["AQID", "base64"],
+                "owner": "11111111111111111111111111111111",
+                "executable": false,
+                "rentEpoch": 361
+            }
+        },
+        "warning": "deprecated"
+    }"#;
+
+    #[test]
+    fn typed_parsing_matches_value_parsing() {
+        let sender = HttpSender::new(TEST_URL).with_validate_response_id(true);
+
+        let typed: Response<Option<UiAccount>> = sender.parse_response_as(0, ACCOUNT_RESPONSE.as_bytes()).unwrap();
+        let generic: Response<Option<UiAccount>> =
+            serde_json::from_value(sender.parse_response(0, ACCOUNT_RESPONSE.as_bytes()).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&typed).unwrap(),
+            serde_json::to_value(&generic).unwrap()
+        );
+        assert_eq!(typed.context.slot, 150);
+        assert_eq!(typed.value.unwrap().lamports, 1000000);
+
+        let error_response = br#"{"jsonrpc":"2.0","id":0,"error":{"code":-32005,"message":"Node is behind"}}"#;
+        let typed = sender.parse_response_as::<u64>(0, error_response).unwrap_err();
+        let generic = sender.parse_response(0, error_response).unwrap_err();
+        assert_eq!(error_kind(&typed), error_kind(&generic));
+
+        assert!(sender.parse_response_as::<u64>(1, br#"{"id":0,"result":1}"#).is_err());
+    }
+
+    #[test]
+    fn typed_parsing_checks_the_context_slot() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_capture_context_slot(true)
+            .with_slot_regression_tolerance(10);
+        let response = |slot: u64| format!(r#"{{"id":0,"result":{{"context":{{"slot":{}}},"value":1}}}}"#, slot);
+
+        let result: Response<u64> = sender.parse_response_as(0, response(200).as_bytes()).unwrap();
+        assert_eq!(result.value, 1);
+        assert_eq!(sender.last_context_slot(), Some(200));
+
+        assert!(sender
+            .parse_response_as::<Response<u64>>(0, response(195).as_bytes())
+            .is_ok());
+        assert!(sender
+            .parse_response_as::<Response<u64>>(0, response(150).as_bytes())
+            .is_err());
+        assert!(sender.parse_response(0, response(150).as_bytes()).is_err());
+    }
+
+    /// Checks that the typed parsing of a large response is faster than the `Value` one, run with
+    /// `cargo test --release send_as_benchmark -- --ignored`.
+    #[test]
+    #[ignore]
+    fn send_as_benchmark() {
+        const ITERATIONS: u32 = 200;
+        let sender = HttpSender::new(TEST_URL);
+        let accounts: Vec<_> = (0..1000)
+            .map(|_| serde_json::from_str::<Value>(ACCOUNT_RESPONSE).unwrap()["result"]["value"].take())
+            .collect();
+        let body =
+            json!({"jsonrpc": "2.0", "id": 0, "result": {"context": {"slot": 1}, "value": accounts}}).to_string();
+
+        let started = Instant::now();
+        for _ in 0..ITERATIONS {
+            let result: Response<Vec<Option<UiAccount>>> = sender.parse_response_as(0, body.as_bytes()).unwrap();
+            assert_eq!(result.value.len(), 1000);
+        }
+        let typed = started.elapsed() / ITERATIONS;
+
+        let started = Instant::now();
+        for _ in 0..ITERATIONS {
+            let result: Response<Vec<Option<UiAccount>>> =
+                serde_json::from_value(sender.parse_response(0, body.as_bytes()).unwrap()).unwrap();
+            assert_eq!(result.value.len(), 1000);
+        }
+        let generic = started.elapsed() / ITERATIONS;
+
+        assert!(
+            typed < generic,
+            "typed: {:?} per response, value: {:?} per response",
+            typed,
+            generic
+        );
+    }
+
+    #[test]
+    fn presized_stats_maps_record_like_default_ones() {
+        let default_sender = HttpSender::new(TEST_URL);
+        let presized_sender = HttpSender::new_with_stats_capacity(TEST_URL, 64);
+        assert!(presized_sender.session_stats.read().unwrap().method_counts.capacity() >= 64);
+        assert!(presized_sender.method_metrics.read().unwrap().capacity() >= 64);
+        assert!(presized_sender.endpoint_stats.read().unwrap().capacity() >= 64);
+
+        for sender in [&default_sender, &presized_sender] {
+            record_request(sender, RpcRequest::GetSlot, true);
+            record_request(sender, RpcRequest::GetBalance, false);
+            record_request(sender, RpcRequest::GetSlot, true);
+        }
+
+        let default_stats = default_sender.session_stats();
+        let presized_stats = presized_sender.session_stats();
+        assert_eq!(default_stats.method_counts, presized_stats.method_counts);
+        assert_eq!(default_stats.error_count, presized_stats.error_count);
+        assert_eq!(default_stats.method_counts["getSlot"], 2);
+
+        let requests_per_method = |sender: &HttpSender| -> Vec<_> {
+            sender
+                .stats_snapshot()
+                .methods
+                .into_iter()
+                .map(|method| (method.method, method.requests, method.errors))
+                .collect()
+        };
+        assert_eq!(
+            requests_per_method(&default_sender),
+            requests_per_method(&presized_sender)
+        );
+        assert_eq!(
+            default_sender.stats_per_endpoint()[TEST_URL].request_count,
+            presized_sender.stats_per_endpoint()[TEST_URL].request_count
+        );
+    }
+
+    #[test]
+    fn request_timeout_bounds_the_attempts_and_is_passed_to_the_transport() {
+        let timeout = Duration::from_millis(50);
+        let transport_timeouts = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_request_timeout(timeout)
+            .with_retry_policy(no_delay_retry_policy().with_server_errors_retried())
+            .with_transport({
+                let transport_timeouts = transport_timeouts.clone();
+                move |request| {
+                    transport_timeouts.lock().unwrap().push(request.timeout);
+                    thread::sleep(Duration::from_millis(30));
+                    Ok(http_response(503, ""))
+                }
+            });
+
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert_eq!(error_kind(&err), "Timeout");
+
+        let transport_timeouts = transport_timeouts.lock().unwrap();
+        assert!(!transport_timeouts.is_empty());
+        assert!(transport_timeouts.len() <= 2);
+        assert!(transport_timeouts.iter().all(|left| left.unwrap() <= timeout));
+        assert!(transport_timeouts.windows(2).all(|left| left[1] < left[0]));
+    }
+
+    #[test]
+    fn requests_without_timeout_pass_none_to_the_transport() {
+        let transport_timeouts = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_method_timeout("getProgramAccounts", Duration::from_secs(30))
+            .with_transport({
+                let transport_timeouts = transport_timeouts.clone();
+                move |request| {
+                    transport_timeouts.lock().unwrap().push(request.timeout);
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(1));
+        sender.send(RpcRequest::GetProgramAccounts, Value::Null).unwrap();
+        let transport_timeouts = transport_timeouts.lock().unwrap();
+        assert_eq!(transport_timeouts[0], None);
+        assert!(transport_timeouts[1].unwrap() <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn hooked_bodies_are_truncated_to_the_configured_length() {
+        let hooked = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_max_logged_body_len(16)
+            .with_body_hook({
+                let hooked = hooked.clone();
+                move |_, request_body: &str, response_body: &str| {
+                    hooked
+                        .lock()
+                        .unwrap()
+                        .push((request_body.to_string(), response_body.to_string()))
+                }
+            })
+            .with_transport(|_| Ok(result_response(json!("a".repeat(100)))));
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        let hooked = hooked.lock().unwrap();
+        let (request_body, response_body) = &hooked[0];
+        for body in [request_body, response_body] {
+            assert_eq!(body.len(), 16 + "...".len());
+            assert!(body.ends_with("..."));
+        }
+
+        assert_eq!(truncate_body("short", 16), "short");
+        assert_eq!(truncate_body("h\u{e9}llo", 2), "h...");
+    }
+
+    #[test]
+    fn request_ids_restart_on_url_change_when_enabled() {
+        let sent_ids = |reset_id_on_url_change: bool| {
+            let ids = Arc::new(Mutex::new(Vec::new()));
+            let sender = HttpSender::new(TEST_URL)
+                .with_reset_id_on_url_change(reset_id_on_url_change)
+                .with_transport({
+                    let ids = ids.clone();
+                    move |request| {
+                        let body: Value = serde_json::from_slice(&request.body).unwrap();
+                        ids.lock().unwrap().push(body["id"].as_u64().unwrap());
+                        Ok(result_response(json!(1)))
+                    }
+                });
+
+            sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+            sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+            sender.set_url("http://localhost:8900");
+            sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+            let ids = ids.lock().unwrap().clone();
+            ids
+        };
+
+        assert_eq!(sent_ids(true), [0, 1, 0]);
+        assert_eq!(sent_ids(false), [0, 1, 2]);
+    }
+
+    #[test]
+    fn offline_requests_fail_without_a_network_call() {
+        let online = Arc::new(AtomicBool::new(false));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sender = HttpSender::new(TEST_URL)
+            .with_connectivity_check({
+                let online = online.clone();
+                move || online.load(Ordering::Relaxed)
+            })
+            .with_transport({
+                let calls = calls.clone();
+                move |_| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert_eq!(error_kind(&err), "Offline");
+        assert!(!is_endpoint_failure(&err));
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        online.store(true, Ordering::Relaxed);
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(1));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn each_request_gets_its_own_trace_id() {
+        let next_trace_id = Arc::new(AtomicUsize::new(0));
+        let trace_ids = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_trace_id("x-trace-id", move || {
+                format!("trace-{}", next_trace_id.fetch_add(1, Ordering::Relaxed))
+            })
+            .with_transport({
+                let trace_ids = trace_ids.clone();
+                move |request| {
+                    let trace_id = request
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name == "x-trace-id")
+                        .map(|(_, value)| value.clone());
+                    trace_ids.lock().unwrap().push(trace_id);
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(*trace_ids.lock().unwrap(), [
+            Some("trace-0".to_string()),
+            Some("trace-1".to_string())
+        ]);
+    }
+
+    #[test]
+    fn mismatched_response_id_is_an_error_when_validated() {
+        let transport = |_: HostHttpRequest| Ok(http_response(200, r#"{"jsonrpc":"2.0","id":7,"result":1}"#));
+
+        let sender = HttpSender::new(TEST_URL)
+            .with_validate_response_id(true)
+            .with_transport(transport);
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(err.to_string().contains("doesn't match request id 0"), "{}", err);
+        assert!(sender.send_as::<u64>(RpcRequest::GetSlot, Value::Null).is_err());
+
+        let sender = HttpSender::new(TEST_URL).with_transport(transport);
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(1));
+    }
+
+    #[test]
+    fn requests_beyond_the_budget_fail_without_a_network_call() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sender = HttpSender::new(TEST_URL).with_request_budget(2).with_transport({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                Ok(result_response(json!(1)))
+            }
+        });
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(sender.remaining_request_budget(), Some(0));
+
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert_eq!(error_kind(&err), "BudgetExhausted");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert_eq!(sender.remaining_request_budget(), Some(0));
+    }
+
+    #[test]
+    fn non_json_rpc_error_bodies_are_errors() {
+        let body = r#"{"error":"upstream unavailable"}"#;
+        let sender = HttpSender::new(TEST_URL).with_transport(move |_| Ok(http_response(200, body)));
+
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(
+            matches!(err.kind(), ClientErrorKind::RpcError(RpcError::RpcRequestError(message))
+                if message.contains("upstream unavailable")),
+            "{}",
+            err
+        );
+        assert!(sender.send_as::<u64>(RpcRequest::GetSlot, Value::Null).is_err());
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed_from_urls() {
+        let urls = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::try_new(format!("  {}\n", TEST_URL))
+            .unwrap()
+            .with_fallback_urls([" http://localhost:8900\t"])
+            .with_transport({
+                let urls = urls.clone();
+                move |request| {
+                    urls.lock().unwrap().push(request.url);
+                    Ok(http_response(503, ""))
+                }
+            })
+            .with_retry_policy(RetryPolicy::none());
+
+        assert_eq!(sender.url(), TEST_URL);
+        assert!(sender.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(*urls.lock().unwrap(), [TEST_URL, "http://localhost:8900"]);
+
+        sender.set_url(format!("\t{} ", TEST_URL));
+        assert_eq!(sender.url(), TEST_URL);
+        assert!(HttpSender::try_new("http://local host:8899").is_err());
+    }
+
+    #[test]
+    fn final_stats_are_passed_to_the_drop_callback() {
+        let flushed = Arc::new(Mutex::new(None));
+        let sender = HttpSender::new(TEST_URL)
+            .with_on_drop({
+                let flushed = flushed.clone();
+                move |stats| *flushed.lock().unwrap() = Some(stats)
+            })
+            .with_transport(|_| Ok(result_response(json!(1))));
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        assert!(flushed.lock().unwrap().is_none());
+
+        drop(sender);
+        assert_eq!(flushed.lock().unwrap().as_ref().unwrap().request_count, 2);
+    }
+
+    #[test]
+    fn context_slot_of_responses_is_captured() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_capture_context_slot(true)
+            .with_transport(|request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                Ok(match body["method"].as_str() {
+                    Some("getBalance") => result_response(json!({"context": {"slot": 77}, "value": 5})),
+                    _ => result_response(json!(1)),
+                })
+            });
+        assert_eq!(sender.last_context_slot(), None);
+
+        let balance = sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        assert_eq!(balance["value"], 5);
+        assert_eq!(sender.last_context_slot(), Some(77));
+
+        // Responses without a context keep the last captured slot
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(sender.last_context_slot(), Some(77));
+
+        let sender = HttpSender::new(TEST_URL)
+            .with_transport(|_| Ok(result_response(json!({"context": {"slot": 77}, "value": 5}))));
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        assert_eq!(sender.last_context_slot(), None);
+    }
+
+    #[test]
+    fn send_once_reports_rate_limiting_immediately() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let sleeps = Arc::new(AtomicUsize::new(0));
+        let sender = HttpSender::new(TEST_URL)
+            .with_sleeper({
+                let sleeps = sleeps.clone();
+                move |_| {
+                    sleeps.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .with_transport({
+                let calls = calls.clone();
+                move |_| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    Ok(http_response(429, ""))
+                }
+            });
+
+        let err = sender.send_once(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert_eq!(error_kind(&err), "Http429");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(sleeps.load(Ordering::Relaxed), 0);
+
+        assert!(sender.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(
+            calls.load(Ordering::Relaxed),
+            1 + 1 + RetryPolicy::default().max_retries as usize
+        );
+    }
+
+    #[test]
+    fn closures_are_retried_with_the_sender_policy() {
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_retry_policy(RetryPolicy {
+                max_retries: 3,
+                base_delay: Duration::from_millis(20),
+                ..RetryPolicy::default()
+            })
+            .with_sleeper({
+                let delays = delays.clone();
+                move |delay| delays.lock().unwrap().push(delay)
+            });
+
+        let mut attempts = Vec::new();
+        let result = sender.retry_with_policy(|attempt| {
+            attempts.push(attempt);
+            if attempt < 2 {
+                Err(ClientErrorKind::Custom("connection reset".to_string()).into())
+            } else {
+                Ok(attempt)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(attempts, [0, 1, 2]);
+        assert_eq!(*delays.lock().unwrap(), [Duration::from_millis(20); 2]);
+
+        let result: Result<()> =
+            sender.retry_with_policy(|_| Err(ClientErrorKind::Custom("connection reset".to_string()).into()));
+        assert!(result.is_err());
+        assert_eq!(delays.lock().unwrap().len(), 2 + 3);
+    }
+
+    #[test]
+    fn last_http_status_follows_the_retried_attempts() {
+        let statuses = Arc::new(Mutex::new(
+            vec![http_response(429, ""), result_response(json!(1))].into_iter(),
+        ));
+        let sender_cell = Arc::new(Mutex::new(Weak::<HttpSender>::new()));
+        let statuses_while_retrying = Arc::new(Mutex::new(Vec::new()));
+        let sender = Arc::new(
+            HttpSender::new(TEST_URL)
+                .with_sleeper({
+                    let sender_cell = sender_cell.clone();
+                    let statuses_while_retrying = statuses_while_retrying.clone();
+                    move |_| {
+                        if let Some(sender) = sender_cell.lock().unwrap().upgrade() {
+                            statuses_while_retrying.lock().unwrap().push(sender.last_http_status());
+                        }
+                    }
+                })
+                .with_transport(move |_| Ok(statuses.lock().unwrap().next().unwrap())),
+        );
+        *sender_cell.lock().unwrap() = Arc::downgrade(&sender);
+        assert_eq!(sender.last_http_status(), None);
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(*statuses_while_retrying.lock().unwrap(), [Some(429)]);
+        assert_eq!(sender.last_http_status(), Some(200));
+    }
+
+    #[test]
+    fn artificial_delay_is_applied_to_successful_responses() {
+        let slept = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(
+            vec![result_response(json!(1)), http_response(400, "")].into_iter(),
+        ));
+        let sender = HttpSender::new(TEST_URL)
+            .with_artificial_delay(Duration::from_millis(150))
+            .with_sleeper({
+                let slept = slept.clone();
+                move |delay| slept.lock().unwrap().push(delay)
+            })
+            .with_transport(move |_| Ok(responses.lock().unwrap().next().unwrap()));
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(*slept.lock().unwrap(), [Duration::from_millis(150)]);
+
+        assert!(sender.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(slept.lock().unwrap().len(), 1);
+        assert_eq!(sender.config_snapshot().artificial_delay_ms, Some(150));
+    }
+
+    #[test]
+    fn failed_send_errors_include_the_request_body_when_enabled() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_include_request_in_errors(true)
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport(|_| Ok(http_response(503, "")));
+
+        let err = sender.send(RpcRequest::GetBalance, json!(["vines"])).unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("503"), "{}", message);
+        assert!(message.contains("[request: {"), "{}", message);
+        assert!(message.contains(r#""method":"getBalance""#), "{}", message);
+        assert!(message.contains(r#""params":["vines"]"#), "{}", message);
+
+        let sender = HttpSender::new(TEST_URL)
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport(|_| Ok(http_response(503, "")));
+        let err = sender.send(RpcRequest::GetBalance, json!(["vines"])).unwrap_err();
+        assert!(!err.to_string().contains("[request:"), "{}", err);
+    }
+
+    #[test]
+    fn mapped_error_codes_produce_the_user_message() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_user_error_message(-32005, "The node is catching up, please retry")
+            .with_transport(|request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                let code = if body["method"] == "getSlot" { -32005 } else { -32602 };
+                let response = json!({"jsonrpc": "2.0", "id": 0, "error": {"code": code, "message": "failure"}});
+                Ok(http_response(200, &response.to_string()))
+            });
+
+        let err = sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::ForUser(message)) if message == "The node is catching up, please retry"
+        ));
+        assert!(!is_endpoint_failure(&err));
+
+        let err = sender.send(RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code: -32602, .. })
+        ));
+        assert_eq!(sender.config_snapshot().user_error_codes, [-32005]);
+    }
+
+    #[test]
+    fn unknown_error_data_is_retained_when_enabled() {
+        let respond = |request: HostHttpRequest| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let error = if body["method"] == "getSlot" {
+                json!({"code": -32005, "message": "Node is behind", "data": {"numSlotsBehind": 10}})
+            } else {
+                json!({"code": -32602, "message": "Invalid params", "data": {"expected": ["pubkey"], "index": 0}})
+            };
+            let response = json!({"jsonrpc": "2.0", "id": 0, "error": error});
+            Ok(http_response(200, &response.to_string()))
+        };
+
+        let sender = HttpSender::new(TEST_URL).with_transport(respond);
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert_eq!(sender.last_unknown_error_data(), None);
+
+        let sender = HttpSender::new(TEST_URL)
+            .with_retain_unknown_error_data(true)
+            .with_transport(respond);
+        let err = sender.send(RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32602,
+                data: RpcResponseErrorData::Empty,
+                ..
+            })
+        ));
+        assert_eq!(
+            sender.last_unknown_error_data(),
+            Some(json!({"expected": ["pubkey"], "index": 0}))
+        );
+
+        // Recognized data is not retained
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert_eq!(
+            sender.last_unknown_error_data(),
+            Some(json!({"expected": ["pubkey"], "index": 0}))
+        );
+    }
+
+    #[test]
+    fn trace_ring_keeps_the_most_recent_requests() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_trace_ring(2)
+            .with_transport(|_| Ok(result_response(json!(1))));
+
+        for request in [RpcRequest::GetSlot, RpcRequest::GetBalance, RpcRequest::GetEpochInfo] {
+            sender.send(request, Value::Null).unwrap();
+        }
+
+        let traces = sender.recent_traces();
+        assert_eq!(traces.iter().map(|trace| trace.method.as_str()).collect::<Vec<_>>(), [
+            "getBalance",
+            "getEpochInfo"
+        ]);
+        assert!(traces
+            .iter()
+            .all(|trace| trace.succeeded && trace.http_status == Some(200) && trace.retries == 0));
+
+        let sender = HttpSender::new(TEST_URL).with_transport(|_| Ok(result_response(json!(1))));
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert!(sender.recent_traces().is_empty());
+    }
+
+    #[test]
+    fn stats_are_kept_per_endpoint() {
+        let other_url = "http://localhost:8999";
+        let sender = HttpSender::new(TEST_URL).with_transport(|_| Ok(result_response(json!(1))));
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.set_url(other_url);
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+
+        let stats = sender.stats_per_endpoint();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[TEST_URL].request_count, 2);
+        assert_eq!(stats[other_url].request_count, 1);
+        assert_eq!(sender.get_transport_stats().request_count, 3);
+    }
+
+    #[test]
+    fn batch_edge_cases_are_explicit() {
+        let exchanges = Arc::new(AtomicUsize::new(0));
+        let sender = HttpSender::new(TEST_URL).with_transport({
+            let exchanges = exchanges.clone();
+            move |_| {
+                exchanges.fetch_add(1, Ordering::Relaxed);
+                Ok(http_response(200, "[]"))
+            }
+        });
+
+        assert!(sender.send_batch(Vec::new()).unwrap().is_empty());
+        assert_eq!(exchanges.load(Ordering::Relaxed), 0);
+
+        let err = sender
+            .send_batch(vec![
+                (RpcRequest::GetSlot, Value::Null),
+                (RpcRequest::GetBalance, Value::Null),
+            ])
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) if message.contains("empty batch response")
+        ));
+        assert_eq!(exchanges.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn config_snapshot_round_trips_without_secrets() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_header("x-api-key", "header-secret")
+            .with_bearer_token("token-secret")
+            .with_fallback_urls(["http://localhost:8999"])
+            .with_trace_ring(16);
+
+        let snapshot = sender.config_snapshot();
+        let snapshot_json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!snapshot_json.contains("header-secret"), "{}", snapshot_json);
+        assert!(!snapshot_json.contains("token-secret"), "{}", snapshot_json);
+        assert_eq!(snapshot.header_names, ["x-api-key"]);
+        assert!(snapshot.has_bearer_token);
+        assert_eq!(snapshot.trace_ring_capacity, Some(16));
+
+        let restored: ClientConfigSnapshot = serde_json::from_str(&snapshot_json).unwrap();
+        assert_eq!(restored, snapshot);
+    }
+
+    #[test]
+    fn custom_body_builder_shapes_the_sent_body() {
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_body_builder(|id, request, params| {
+                let mut body = request.build_request_json(id, params.clone());
+                body["tenant"] = json!("lapp");
+                body
+            })
+            .with_transport({
+                let bodies = bodies.clone();
+                move |request| {
+                    bodies
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::from_slice::<Value>(&request.body).unwrap());
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        sender.send(RpcRequest::GetBalance, json!(["pubkey"])).unwrap();
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies[0]["tenant"], "lapp");
+        assert_eq!(bodies[0]["method"], "getBalance");
+        assert_eq!(bodies[0]["params"], json!(["pubkey"]));
+    }
+
+    #[test]
+    fn url_secrets_are_kept_out_of_errors() {
+        let url = "https://rpc.example.com/?api-key=secret";
+        let failing_sender = || {
+            HttpSender::new(url)
+                .with_retry_policy(RetryPolicy::none())
+                .with_transport(|request| Err(format!("connection to {} refused", request.url)))
+        };
+
+        let err = failing_sender().send(RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(!err.to_string().contains("secret"), "{}", err);
+        assert!(err.to_string().contains("https://rpc.example.com/"), "{}", err);
+
+        let err = failing_sender()
+            .with_sanitize_urls_in_errors(false)
+            .send(RpcRequest::GetSlot, Value::Null)
+            .unwrap_err();
+        assert!(err.to_string().contains("?api-key=secret"), "{}", err);
+    }
+
+    #[test]
+    fn endpoints_tried_per_send_are_capped() {
+        let tried_urls = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://localhost:8001")
+            .with_fallback_urls(["http://localhost:8002", "http://localhost:8003"])
+            .with_max_endpoints_per_send(2)
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport({
+                let tried_urls = tried_urls.clone();
+                move |request| {
+                    tried_urls.lock().unwrap().push(request.url);
+                    Ok(http_response(503, "Service Unavailable"))
+                }
+            });
+
+        assert!(sender.send(RpcRequest::GetSlot, Value::Null).is_err());
+        assert_eq!(*tried_urls.lock().unwrap(), [
+            "http://localhost:8001",
+            "http://localhost:8002"
+        ]);
+    }
+
+    #[test]
+    fn traffic_of_each_exchange_is_recorded() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_traffic_recording(true)
+            .with_transport(|_| Ok(result_response(json!(7))));
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        sender.send(RpcRequest::GetBalance, json!(["pubkey"])).unwrap();
+
+        let traffic = sender.export_traffic();
+        assert_eq!(traffic.iter().map(|entry| entry.method.as_str()).collect::<Vec<_>>(), [
+            "getSlot",
+            "getBalance"
+        ]);
+        for entry in &traffic {
+            assert_eq!(entry.url, TEST_URL);
+            assert_eq!(entry.status, Some(200));
+            assert_eq!(
+                serde_json::from_str::<Value>(&entry.response_body).unwrap()["result"],
+                7
+            );
+        }
+        let request_body: Value = serde_json::from_str(&traffic[1].request_body).unwrap();
+        assert_eq!(request_body["params"], json!(["pubkey"]));
+        assert!(serde_json::to_string(&traffic).is_ok());
+    }
+
+    #[test]
+    fn large_key_arrays_are_summarized_in_traces() {
+        let sender = HttpSender::new(TEST_URL)
+            .with_trace_ring(4)
+            .with_transport(|_| Ok(result_response(json!({"context": {"slot": 1}, "value": []}))));
+        let pubkeys: Vec<_> = (0..120).map(|index| format!("pubkey{}", index)).collect();
+
+        sender
+            .send(
+                RpcRequest::GetMultipleAccounts,
+                json!([pubkeys, {"encoding": "base64"}]),
+            )
+            .unwrap();
+        sender
+            .send(RpcRequest::GetMultipleAccounts, json!([["a", "b"]]))
+            .unwrap();
+
+        let traces = sender.recent_traces();
+        assert_eq!(traces[0].params, r#"["[120 pubkeys]",{"encoding":"base64"}]"#);
+        assert_eq!(traces[1].params, r#"[["a","b"]]"#);
+        assert_eq!(
+            summarize_params(RpcRequest::GetBalance, &json!([pubkeys])),
+            json!([pubkeys]).to_string()
+        );
+    }
+
+    #[test]
+    fn slot_regressions_beyond_the_tolerance_are_reported() {
+        let context_slots = |slots: Vec<u64>| {
+            let slots = Mutex::new(VecDeque::from(slots));
+            move |_: HostHttpRequest| {
+                let slot = slots.lock().unwrap().pop_front().unwrap();
+                Ok(result_response(json!({"context": {"slot": slot}, "value": 1})))
+            }
+        };
+
+        let regressions = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_slot_regression_tolerance(10)
+            .with_on_slot_regression({
+                let regressions = regressions.clone();
+                move |max_slot, slot| regressions.lock().unwrap().push((max_slot, slot))
+            })
+            .with_transport(context_slots(vec![500, 495, 420, 510]));
+        for _ in 0..4 {
+            sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        }
+        assert_eq!(*regressions.lock().unwrap(), [(500, 420)]);
+
+        let sender = HttpSender::new(TEST_URL)
+            .with_slot_regression_tolerance(10)
+            .with_transport(context_slots(vec![500, 420]));
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        let err = sender.send(RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert!(err.to_string().contains("regressed"), "{}", err);
+    }
+
+    #[test]
+    fn heavy_reads_use_the_heavy_read_timeout() {
+        let transport_timeouts = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_timeout_policy(TimeoutPolicy {
+                read: Some(Duration::from_secs(5)),
+                heavy_read: Some(Duration::from_secs(30)),
+                write: Some(Duration::from_secs(10)),
+                ..TimeoutPolicy::default()
+            })
+            .with_transport({
+                let transport_timeouts = transport_timeouts.clone();
+                move |request| {
+                    transport_timeouts.lock().unwrap().push(request.timeout.unwrap());
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        sender.send(RpcRequest::GetProgramAccounts, Value::Null).unwrap();
+        sender.send(RpcRequest::GetBalance, Value::Null).unwrap();
+        sender.send(RpcRequest::SendTransaction, Value::Null).unwrap();
+        let transport_timeouts = transport_timeouts.lock().unwrap();
+        for (timeout, expected) in transport_timeouts.iter().zip([30, 5, 10]) {
+            let expected = Duration::from_secs(expected);
+            assert!(*timeout <= expected && *timeout > expected - Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn response_warnings_are_passed_to_the_callback() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_on_warning({
+                let warnings = warnings.clone();
+                move |warning| warnings.lock().unwrap().push(warning.to_string())
+            })
+            .with_transport(|_| {
+                Ok(http_response(
+                    200,
+                    r#"{"jsonrpc":"2.0","id":0,"result":7,"warning":"method is deprecated"}"#,
+                ))
+            });
+
+        assert_eq!(sender.send(RpcRequest::GetSlot, Value::Null).unwrap(), json!(7));
+        assert_eq!(sender.send_as::<u64>(RpcRequest::GetSlot, Value::Null).unwrap(), 7);
+        assert_eq!(*warnings.lock().unwrap(), [
+            "method is deprecated",
+            "method is deprecated"
+        ]);
+    }
+
+    #[test]
+    fn attempt_callback_fires_for_each_attempt() {
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let rate_limited = AtomicBool::new(true);
+        let sender = HttpSender::new(TEST_URL)
+            .with_retry_policy(no_delay_retry_policy())
+            .with_on_attempt_complete({
+                let attempts = attempts.clone();
+                move |attempt, duration, status| attempts.lock().unwrap().push((attempt, duration, status))
+            })
+            .with_transport(move |_| {
+                if rate_limited.swap(false, Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(20));
+                    Ok(http_response(429, "Too Many Requests"))
+                } else {
+                    Ok(result_response(json!(1)))
+                }
+            });
+
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        let attempts = attempts.lock().unwrap();
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|(attempt, _, status)| (*attempt, *status))
+                .collect::<Vec<_>>(),
+            [(0, 429), (1, 200)]
+        );
+        assert!(attempts[0].1 >= Duration::from_millis(20));
+        assert!(attempts[1].1 < attempts[0].1);
+    }
+
+    #[test]
+    fn rate_limit_ratio_is_computed_over_the_recent_requests() {
+        let statuses = Mutex::new(VecDeque::from([429, 200, 200, 429, 200, 200]));
+        let sender = HttpSender::new(TEST_URL)
+            .with_rate_limit_window(4)
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport(move |_| match statuses.lock().unwrap().pop_front().unwrap() {
+                200 => Ok(result_response(json!(1))),
+                status => Ok(http_response(status, "Too Many Requests")),
+            });
+        assert_eq!(sender.recent_rate_limit_ratio(), 0.0);
+
+        for _ in 0..2 {
+            let _ = sender.send(RpcRequest::GetSlot, Value::Null);
+        }
+        assert_eq!(sender.recent_rate_limit_ratio(), 0.5);
+
+        for _ in 0..4 {
+            let _ = sender.send(RpcRequest::GetSlot, Value::Null);
+        }
+        assert_eq!(sender.recent_rate_limit_ratio(), 0.25);
+    }
+
+    #[test]
+    fn responses_nested_beyond_the_limit_are_rejected() {
+        let nested = |depth: usize| format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        let sender = HttpSender::new(TEST_URL)
+            .with_max_json_depth(8)
+            .with_transport(move |request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                // The envelope adds a level to the result
+                let depth = if body["method"] == "getSlot" { 7 } else { 1_000 };
+                Ok(http_response(
+                    200,
+                    &format!(r#"{{"jsonrpc":"2.0","id":0,"result":{}}}"#, nested(depth)),
+                ))
+            });
+
+        assert!(sender.send(RpcRequest::GetSlot, Value::Null).is_ok());
+        let err = sender.send(RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::ParseError(message)) if message.contains("limit of 8 levels")
+        ));
+        // Brackets in strings don't count
+        assert!(check_json_depth(br#"{"result":"[[[[[[[[[["}"#, 2).is_ok());
+    }
+
+    #[test]
+    fn method_retry_policy_applies_only_to_its_method() {
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new(TEST_URL)
+            .with_retry_policy(RetryPolicy::none())
+            .with_method_retry_policy("getProgramAccounts", RetryPolicy {
+                max_retries: 3,
+                ..no_delay_retry_policy()
+            })
+            .with_transport({
+                let methods = methods.clone();
+                move |request| {
+                    let body: Value = serde_json::from_slice(&request.body).unwrap();
+                    methods
+                        .lock()
+                        .unwrap()
+                        .push(body["method"].as_str().unwrap().to_string());
+                    Ok(http_response(429, "Too Many Requests"))
+                }
+            });
+
+        assert!(sender.send(RpcRequest::GetProgramAccounts, Value::Null).is_err());
+        assert!(sender.send(RpcRequest::GetBalance, Value::Null).is_err());
+        let methods = methods.lock().unwrap();
+        assert_eq!(
+            methods.iter().filter(|method| *method == "getProgramAccounts").count(),
+            4
+        );
+        assert_eq!(methods.iter().filter(|method| *method == "getBalance").count(), 1);
+        assert_eq!(sender.retry_policy_for(RpcRequest::GetProgramAccounts).max_retries, 3);
+    }
+
+    #[test]
+    fn wire_bytes_of_the_last_exchange_are_captured() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let response_body = r#"{"jsonrpc":"2.0","id":0,"result":123}"#;
+        let capturing_sender = |capture_wire_bytes| {
+            HttpSender::new(TEST_URL)
+                .with_capture_wire_bytes(capture_wire_bytes)
+                .with_transport({
+                    let sent = sent.clone();
+                    move |request| {
+                        *sent.lock().unwrap() = request.body;
+                        Ok(http_response(200, response_body))
+                    }
+                })
+        };
+
+        let sender = capturing_sender(true);
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(sender.last_request_bytes().unwrap(), *sent.lock().unwrap());
+        assert_eq!(sender.last_response_bytes().unwrap(), response_body.as_bytes());
+
+        let sender = capturing_sender(false);
+        sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        assert_eq!(sender.last_request_bytes(), None);
+        assert_eq!(sender.last_response_bytes(), None);
+    }
 }
@@ -0,0 +1,136 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+
+/// Number of the most recent latencies per method the percentiles are computed over.
+pub const LATENCY_SAMPLES_PER_METHOD: usize = 1024;
+
+/// Metrics of the requests of a method, see `HttpSender::stats_snapshot`.
+#[derive(Debug, Default)]
+pub(crate) struct MethodMetrics {
+    requests: u64,
+    errors_by_kind: BTreeMap<String, u64>,
+    retries: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    latencies_ms: VecDeque<u64>,
+}
+
+impl MethodMetrics {
+    pub(crate) fn record(
+        &mut self,
+        latency: Duration,
+        retries: u32,
+        error_kind: Option<&str>,
+        bytes_sent: u64,
+        bytes_received: u64,
+    ) {
+        self.requests += 1;
+        if let Some(error_kind) = error_kind {
+            *self.errors_by_kind.entry(error_kind.to_string()).or_default() += 1;
+        }
+        self.retries += u64::from(retries);
+        self.bytes_sent += bytes_sent;
+        self.bytes_received += bytes_received;
+
+        if self.latencies_ms.len() >= LATENCY_SAMPLES_PER_METHOD {
+            self.latencies_ms.pop_front();
+        }
+        self.latencies_ms.push_back(latency.as_millis() as u64);
+    }
+
+    pub(crate) fn report(&self, method: &str) -> MethodReport {
+        let mut latencies_ms: Vec<_> = self.latencies_ms.iter().copied().collect();
+        latencies_ms.sort_unstable();
+        let percentile = |percentile: usize| {
+            let rank = (percentile * latencies_ms.len() + 99) / 100;
+            latencies_ms.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+        };
+
+        MethodReport {
+            method: method.to_string(),
+            requests: self.requests,
+            errors: self.errors_by_kind.values().sum(),
+            errors_by_kind: self.errors_by_kind.clone(),
+            retries: self.retries,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            latency_p50_ms: percentile(50),
+            latency_p95_ms: percentile(95),
+            latency_p99_ms: percentile(99),
+            latency_max_ms: latencies_ms.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Report of the requests of a method. Latency percentiles are computed over the most recent requests.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodReport {
+    pub method: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub errors_by_kind: BTreeMap<String, u64>,
+    pub retries: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub latency_p50_ms: u64,
+    pub latency_p95_ms: u64,
+    pub latency_p99_ms: u64,
+    pub latency_max_ms: u64,
+}
+
+/// Serializable report of the transport metrics of a sender, see `HttpSender::stats_snapshot`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub requests: u64,
+    pub errors: u64,
+    pub retries: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+
+    /// Reports of each method, sorted by name.
+    pub methods: Vec<MethodReport>,
+}
+
+impl StatsReport {
+    pub(crate) fn new(mut methods: Vec<MethodReport>) -> Self {
+        methods.sort_by(|a, b| a.method.cmp(&b.method));
+        Self {
+            requests: methods.iter().map(|method| method.requests).sum(),
+            errors: methods.iter().map(|method| method.errors).sum(),
+            retries: methods.iter().map(|method| method.retries).sum(),
+            bytes_sent: methods.iter().map(|method| method.bytes_sent).sum(),
+            bytes_received: methods.iter().map(|method| method.bytes_received).sum(),
+            methods,
+        }
+    }
+}
+
+/// Returns the kind of the error the metrics count it under, e.g. `Timeout`, `Http429` or `Rpc-32005`.
+pub fn error_kind(err: &ClientError) -> String {
+    match err.kind() {
+        ClientErrorKind::Custom(message) => match message.split_once(':') {
+            Some((kind, _)) if !kind.is_empty() && kind.chars().all(char::is_alphanumeric) => kind.to_string(),
+            _ => "Custom".to_string(),
+        },
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => {
+            match message.split(' ').next().and_then(|status| status.parse::<u16>().ok()) {
+                Some(status) => format!("Http{}", status),
+                None => "ForUser".to_string(),
+            }
+        },
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => format!("Rpc{}", code),
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => "Transport".to_string(),
+        ClientErrorKind::RpcError(RpcError::ParseError(_)) => "Parse".to_string(),
+        ClientErrorKind::SerdeJson(_) => "Json".to_string(),
+        ClientErrorKind::Io(_) => "Io".to_string(),
+        _ => "Other".to_string(),
+    }
+}
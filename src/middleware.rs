@@ -0,0 +1,39 @@
+use serde_json::Value;
+use solana_client_api::{client_error::Result, rpc_request::RpcRequest};
+
+/// Interceptor of the requests sent through `RpcSender::send` of an `HttpSender`, see `HttpSender::with_middleware`.
+///
+/// Middlewares see the outgoing requests in the order they were added and the results in the reverse order, so the
+/// first middleware is the outermost one.
+pub trait RpcMiddleware: Send + Sync {
+    /// Inspects or rewrites the request before it is sent, an error aborts the request without sending it.
+    fn on_request(&self, _request: &mut RpcRequest, _params: &mut Value) -> Result<()> {
+        Ok(())
+    }
+
+    /// Inspects or rewrites the result of the request, `request` being the request as actually sent.
+    fn on_response(&self, _request: RpcRequest, result: Result<Value>) -> Result<Value> {
+        result
+    }
+}
+
+/// Middleware rewriting a method into another one, e.g. into a method name expected by a proxy.
+pub struct RenameMethod {
+    from: String,
+    to: &'static str,
+}
+
+impl RenameMethod {
+    pub fn new(from: impl Into<String>, to: &'static str) -> Self {
+        Self { from: from.into(), to }
+    }
+}
+
+impl RpcMiddleware for RenameMethod {
+    fn on_request(&self, request: &mut RpcRequest, _params: &mut Value) -> Result<()> {
+        if request.to_string() == self.from {
+            *request = RpcRequest::Custom { method: self.to };
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind, Result},
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+use crate::{
+    async_sender::{AsyncRpcSender, LocalBoxFuture},
+    rpc_error_from_json, TrafficEntry,
+};
+
+/// Failure injected by a `MockSender`, reported as the `HttpSender` reports the real one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockError {
+    /// Failure at the transport level, e.g. a dropped connection.
+    Transport(String),
+
+    /// Non-success HTTP status of the response.
+    Status(u16),
+
+    /// JSON-RPC error object of the response, e.g. `NodeUnhealthy` or a preflight failure.
+    Rpc(Value),
+}
+
+impl MockError {
+    /// JSON-RPC error with the `code` and `message` and without data.
+    pub fn rpc(code: i64, message: impl Into<String>) -> Self {
+        Self::Rpc(json!({ "code": code, "message": message.into() }))
+    }
+
+    fn to_client_error(&self, request: RpcRequest) -> ClientError {
+        match self {
+            Self::Transport(message) => {
+                ClientError::new_with_request(ClientErrorKind::Custom(message.clone()), request)
+            },
+            Self::Status(status) => ClientError::new_with_request(
+                ClientErrorKind::RpcError(RpcError::ForUser(status.to_string())),
+                request,
+            ),
+            Self::Rpc(error) => rpc_error_from_json(error),
+        }
+    }
+}
+
+/// Response of a `MockSender`.
+pub type MockResponse = std::result::Result<Value, MockError>;
+
+/// Request received by a `MockSender`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    pub method: String,
+    pub params: Value,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Responses returned once each, before the default ones.
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    defaults: HashMap<String, MockResponse>,
+    /// Recorded responses keyed by the method and the params, replayed in order with the last one repeated.
+    fixtures: HashMap<(String, String), VecDeque<MockResponse>>,
+    calls: Vec<MockCall>,
+    stats: RpcTransportStats,
+}
+
+/// Sender answering the requests with canned responses instead of host HTTP calls, so the logic using
+/// `WasmRpcClient` or `AsyncWasmRpcClient` can be tested without a validator. The responses are looked up in turn
+/// among the queued ones of the method, the fixtures recorded for the method and params, and the default one of the
+/// method. Requests without a response fail with an `Unmocked:` error.
+///
+/// Clones share the responses and the recorded calls, so a clone can be passed to the client, e.g. with
+/// `RpcClient::new_sender`, and the original kept for assertions.
+#[derive(Clone, Default)]
+pub struct MockSender {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a sender replaying the HTTP exchanges recorded by `HttpSender::export_traffic`.
+    pub fn from_traffic(entries: impl IntoIterator<Item = TrafficEntry>) -> Self {
+        let sender = Self::new();
+        sender.add_traffic(entries);
+        sender
+    }
+
+    /// Creates a sender replaying the HTTP exchanges serialized as a JSON array of `TrafficEntry`.
+    pub fn from_fixture_json(json: &str) -> serde_json::Result<Self> {
+        let entries: Vec<TrafficEntry> = serde_json::from_str(json)?;
+        Ok(Self::from_traffic(entries))
+    }
+
+    /// Sets the result returned for every request of the `method`.
+    pub fn with_response(self, method: impl Into<String>, result: Value) -> Self {
+        self.set_response(method, Ok(result));
+        self
+    }
+
+    /// Sets the failure of every request of the `method`.
+    pub fn with_error(self, method: impl Into<String>, error: MockError) -> Self {
+        self.set_response(method, Err(error));
+        self
+    }
+
+    /// Sets the response of every request of the `method`, replacing the previous one.
+    pub fn set_response(&self, method: impl Into<String>, response: MockResponse) {
+        self.state.lock().unwrap().defaults.insert(method.into(), response);
+    }
+
+    /// Queues a response returned once, to the next request of the `method`, e.g. a failure before the default
+    /// success.
+    pub fn push_response(&self, method: impl Into<String>, response: MockResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .queued
+            .entry(method.into())
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Adds recorded HTTP exchanges to replay for the requests with the same method and params. The exchanges of the
+    /// same request are replayed in order, the last one is repeated.
+    pub fn add_traffic(&self, entries: impl IntoIterator<Item = TrafficEntry>) {
+        let mut state = self.state.lock().unwrap();
+        for entry in entries {
+            // Batches and unparsable bodies can't be matched to a single request
+            let params = match serde_json::from_str::<Value>(&entry.request_body) {
+                Ok(Value::Object(mut body)) => body.remove("params").unwrap_or(Value::Null),
+                _ => continue,
+            };
+            state
+                .fixtures
+                .entry((entry.method.clone(), params.to_string()))
+                .or_default()
+                .push_back(recorded_response(&entry));
+        }
+    }
+
+    /// Returns the received requests, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Returns the params of the received requests of the `method`, oldest first.
+    pub fn calls_to(&self, method: &str) -> Vec<Value> {
+        self.state
+            .lock()
+            .unwrap()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .map(|call| call.params.clone())
+            .collect()
+    }
+
+    pub fn call_count(&self, method: &str) -> usize {
+        self.calls_to(method).len()
+    }
+
+    pub fn clear_calls(&self) {
+        self.state.lock().unwrap().calls.clear();
+    }
+
+    /// Panics unless a request of the `method` was received.
+    pub fn assert_called(&self, method: &str) {
+        assert!(
+            self.call_count(method) > 0,
+            "expected a {} request, got: {:?}",
+            method,
+            self.calls()
+        );
+    }
+
+    /// Panics unless a request of the `method` with the `params` was received.
+    pub fn assert_called_with(&self, method: &str, params: &Value) {
+        let calls = self.calls_to(method);
+        assert!(
+            calls.contains(params),
+            "expected a {} request with params {}, got: {:?}",
+            method,
+            params,
+            calls
+        );
+    }
+
+    /// Panics if a request of the `method` was received.
+    pub fn assert_not_called(&self, method: &str) {
+        let calls = self.calls_to(method);
+        assert!(calls.is_empty(), "expected no {} request, got: {:?}", method, calls);
+    }
+
+    fn respond(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        let method = request.to_string();
+        let mut state = self.state.lock().unwrap();
+        state.stats.request_count += 1;
+        state.calls.push(MockCall {
+            method: method.clone(),
+            params: params.clone(),
+        });
+
+        let queued = state.queued.get_mut(&method).and_then(VecDeque::pop_front);
+        let response = queued
+            .or_else(|| {
+                let recorded = state.fixtures.get_mut(&(method.clone(), params.to_string()))?;
+                if recorded.len() > 1 {
+                    recorded.pop_front()
+                } else {
+                    recorded.front().cloned()
+                }
+            })
+            .or_else(|| state.defaults.get(&method).cloned());
+
+        match response {
+            Some(Ok(result)) => Ok(result),
+            Some(Err(error)) => Err(error.to_client_error(request)),
+            None => Err(ClientError::new_with_request(
+                ClientErrorKind::Custom(format!(
+                    "Unmocked: no response is set for {} with params {}",
+                    method, params
+                )),
+                request,
+            )),
+        }
+    }
+}
+
+impl RpcSender for MockSender {
+    fn send(&self, request: RpcRequest, params: Value) -> Result<Value> {
+        self.respond(request, params)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+}
+
+impl AsyncRpcSender for MockSender {
+    fn send(&self, request: RpcRequest, params: Value) -> LocalBoxFuture<'_, Result<Value>> {
+        let result = self.respond(request, params);
+        Box::pin(async move { result })
+    }
+
+    fn url(&self) -> String {
+        "mock".to_string()
+    }
+
+    /// Returns immediately, so the tests don't wait for the polling intervals.
+    fn sleep(&self, _duration: Duration) -> LocalBoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+}
+
+fn recorded_response(entry: &TrafficEntry) -> MockResponse {
+    let status = match entry.status {
+        Some(status) => status,
+        None => return Err(MockError::Transport("recorded transport failure".to_string())),
+    };
+    if !(200..300).contains(&status) {
+        return Err(MockError::Status(status));
+    }
+
+    let mut body: Value = serde_json::from_str(&entry.response_body)
+        .map_err(|err| MockError::Transport(format!("malformed recorded response: {}", err)))?;
+    match body.get_mut("error") {
+        Some(error) => Err(MockError::Rpc(error.take())),
+        None => Ok(body.get_mut("result").map(Value::take).unwrap_or(Value::Null)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client_api::rpc_request::RpcResponseErrorData;
+
+    use super::*;
+
+    fn traffic_entry(params: Value, status: Option<u16>, response_body: Value) -> TrafficEntry {
+        TrafficEntry {
+            method: "getBalance".to_string(),
+            url: "http://localhost:8899".to_string(),
+            request_body: json!({"jsonrpc": "2.0", "id": 0, "method": "getBalance", "params": params}).to_string(),
+            status,
+            response_body: response_body.to_string(),
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn queued_responses_come_before_the_default_one() {
+        let mock = MockSender::new().with_response("getSlot", json!(10));
+        mock.push_response("getSlot", Err(MockError::Status(503)));
+        mock.push_response("getSlot", Ok(json!(5)));
+
+        let err = RpcSender::send(&mock, RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::RpcError(RpcError::ForUser(message)) if message == "503"));
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetSlot, Value::Null).unwrap(),
+            json!(5)
+        );
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetSlot, Value::Null).unwrap(),
+            json!(10)
+        );
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetSlot, Value::Null).unwrap(),
+            json!(10)
+        );
+        assert_eq!(mock.call_count("getSlot"), 4);
+        assert_eq!(mock.get_transport_stats().request_count, 4);
+    }
+
+    #[test]
+    fn injected_errors_are_reported_like_the_http_sender_ones() {
+        let mock = MockSender::new()
+            .with_error("getSlot", MockError::rpc(-32005, "Node is behind"))
+            .with_error("getBalance", MockError::Transport("connection reset".to_string()));
+
+        let err = RpcSender::send(&mock, RpcRequest::GetSlot, Value::Null).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                data: RpcResponseErrorData::Empty,
+                ..
+            })
+        ));
+        let err = RpcSender::send(&mock, RpcRequest::GetBalance, Value::Null).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message == "connection reset"));
+
+        let err = RpcSender::send(&mock, RpcRequest::GetEpochInfo, Value::Null).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message.starts_with("Unmocked:")));
+    }
+
+    #[test]
+    fn recorded_traffic_is_replayed_per_params() {
+        let alice = json!(["alice"]);
+        let bob = json!(["bob"]);
+        let mock = MockSender::from_traffic([
+            traffic_entry(alice.clone(), Some(200), json!({"id": 0, "result": 1})),
+            traffic_entry(alice.clone(), Some(200), json!({"id": 1, "result": 2})),
+            traffic_entry(bob.clone(), Some(429), json!({})),
+            traffic_entry(bob.clone(), None, json!({})),
+        ]);
+
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetBalance, alice.clone()).unwrap(),
+            json!(1)
+        );
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetBalance, alice.clone()).unwrap(),
+            json!(2)
+        );
+        assert_eq!(
+            RpcSender::send(&mock, RpcRequest::GetBalance, alice.clone()).unwrap(),
+            json!(2)
+        );
+        assert!(RpcSender::send(&mock, RpcRequest::GetBalance, bob.clone()).is_err());
+        let err = RpcSender::send(&mock, RpcRequest::GetBalance, bob.clone()).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::Custom(message) if message == "recorded transport failure"));
+        mock.assert_called_with("getBalance", &bob);
+        mock.assert_not_called("getSlot");
+
+        let fixture = serde_json::to_string(&[traffic_entry(bob.clone(), Some(200), json!({"result": 3}))]).unwrap();
+        let mock = MockSender::from_fixture_json(&fixture).unwrap();
+        assert_eq!(RpcSender::send(&mock, RpcRequest::GetBalance, bob).unwrap(), json!(3));
+    }
+}
@@ -0,0 +1,199 @@
+use solana_client_api::{client_error::Result as ClientResult, rpc_request::RpcError};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    nonce::state::{State as NonceState, Versions as NonceVersions},
+    pubkey::Pubkey,
+    signature::Signature,
+    signers::Signers,
+    system_instruction,
+    transaction::Transaction,
+};
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// State of an initialized durable nonce account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceInfo {
+    pub authority: Pubkey,
+
+    /// Blockhash stored in the account, used as the recent blockhash of the nonce transactions.
+    pub blockhash: Hash,
+    pub lamports_per_signature: u64,
+}
+
+/// Builds an unsigned transaction using the durable nonce stored in `nonce_account`, with the nonce advance
+/// instruction prepended to the `instructions`.
+pub fn nonce_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    nonce_account: &Pubkey,
+    nonce: &NonceInfo,
+) -> Transaction {
+    let mut message = Message::new_with_nonce(instructions.to_vec(), Some(payer), nonce_account, &nonce.authority);
+    message.recent_blockhash = nonce.blockhash;
+    Transaction::new_unsigned(message)
+}
+
+impl WasmRpcClient {
+    /// Fetches and parses the durable nonce account.
+    pub fn get_nonce_info(&self, nonce_account: &Pubkey) -> ClientResult<NonceInfo> {
+        let account = self
+            .get_account_with_commitment(nonce_account, self.commitment())?
+            .value
+            .ok_or_else(|| RpcError::ForUser(format!("nonce account {} not found", nonce_account)))?;
+        let versions: NonceVersions = account
+            .deserialize_data()
+            .map_err(|err| RpcError::ForUser(format!("invalid nonce account {}: {}", nonce_account, err)))?;
+
+        match versions.convert_to_current() {
+            NonceState::Initialized(data) => Ok(NonceInfo {
+                authority: data.authority,
+                blockhash: data.blockhash,
+                lamports_per_signature: data.fee_calculator.lamports_per_signature,
+            }),
+            NonceState::Uninitialized => {
+                Err(RpcError::ForUser(format!("nonce account {} is not initialized", nonce_account)).into())
+            },
+        }
+    }
+
+    /// Creates and initializes the nonce account `nonce_account`, funded by `payer` with the rent-exempt minimum.
+    /// The `signers` must include `payer` and `nonce_account`.
+    pub fn create_nonce_account<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+        authority: &Pubkey,
+    ) -> ClientResult<Signature> {
+        let lamports = self.get_minimum_balance_for_rent_exemption(NonceState::size())?;
+        let instructions = system_instruction::create_nonce_account(payer, nonce_account, authority, lamports);
+        self.sign_and_confirm(signers, payer, &instructions)
+    }
+
+    /// Advances the nonce stored in `nonce_account`. The `signers` must include `payer` and the nonce authority.
+    pub fn advance_nonce_account<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+        authority: &Pubkey,
+    ) -> ClientResult<Signature> {
+        let instruction = system_instruction::advance_nonce_account(nonce_account, authority);
+        self.sign_and_confirm(signers, payer, &[instruction])
+    }
+
+    /// Withdraws `lamports` from `nonce_account` to `to`. The `signers` must include `payer` and the nonce
+    /// authority.
+    pub fn withdraw_nonce_account<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+        authority: &Pubkey,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> ClientResult<Signature> {
+        let instruction = system_instruction::withdraw_nonce_account(nonce_account, authority, to, lamports);
+        self.sign_and_confirm(signers, payer, &[instruction])
+    }
+
+    /// Builds an unsigned transaction of the `instructions` using the current durable nonce of `nonce_account`,
+    /// e.g. to be signed offline.
+    pub fn build_nonce_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        nonce_account: &Pubkey,
+    ) -> ClientResult<Transaction> {
+        let nonce = self.get_nonce_info(nonce_account)?;
+        Ok(nonce_transaction(instructions, payer, nonce_account, &nonce))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, fee_calculator::FeeCalculator, nonce::state::Data as NonceData,
+        system_instruction::SystemInstruction, system_program,
+    };
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    /// Client answering `getAccountInfo` with a system account holding the `state`.
+    fn nonce_client(state: NonceState) -> WasmRpcClient {
+        let data = bincode::serialize(&NonceVersions::new_current(state)).unwrap();
+        let mock = MockSender::new().with_response(
+            "getAccountInfo",
+            json!({"context": {"slot": 1}, "value": {
+                "lamports": 1_000_000,
+                "data": [base64::encode(data), "base64"],
+                "owner": system_program::id().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+            }}),
+        );
+        WasmRpcClient::new(RpcClient::new_sender(
+            mock,
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ))
+    }
+
+    #[test]
+    fn initialized_nonce_account_is_parsed() {
+        let authority = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let client = nonce_client(NonceState::Initialized(NonceData {
+            authority,
+            blockhash,
+            fee_calculator: FeeCalculator::new(5000),
+        }));
+
+        assert_eq!(client.get_nonce_info(&Pubkey::new_unique()).unwrap(), NonceInfo {
+            authority,
+            blockhash,
+            lamports_per_signature: 5000,
+        });
+    }
+
+    #[test]
+    fn uninitialized_nonce_account_is_an_error() {
+        let client = nonce_client(NonceState::Uninitialized);
+
+        let err = client.get_nonce_info(&Pubkey::new_unique()).unwrap_err();
+        assert!(err.to_string().contains("is not initialized"), "{}", err);
+    }
+
+    #[test]
+    fn nonce_transaction_starts_with_the_advance() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = NonceInfo {
+            authority: Pubkey::new_unique(),
+            blockhash: Hash::new_unique(),
+            lamports_per_signature: 5000,
+        };
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let transaction = nonce_transaction(&[transfer], &payer, &nonce_account, &nonce);
+        let message = &transaction.message;
+        assert_eq!(message.recent_blockhash, nonce.blockhash);
+        assert_eq!(message.account_keys[0], payer);
+        assert_eq!(message.instructions.len(), 2);
+        let advance = &message.instructions[0];
+        assert_eq!(
+            message.account_keys[advance.program_id_index as usize],
+            system_program::id()
+        );
+        assert_eq!(message.account_keys[advance.accounts[0] as usize], nonce_account);
+        assert_eq!(
+            bincode::deserialize::<SystemInstruction>(&advance.data).unwrap(),
+            SystemInstruction::AdvanceNonceAccount
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use solana_sdk::signature::Signature;
+
+/// Structured event of the transport or the confirmation flow, see `RpcEventObserver`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcEvent {
+    /// The request is about to be sent to the `url`, before the retries and the fallback endpoints.
+    RequestStarted { method: String, url: String },
+
+    /// The request completed, successfully unless `error_kind` is set. The `latency` includes the retries.
+    RequestFinished {
+        method: String,
+        latency: Duration,
+        http_status: Option<u16>,
+        error_kind: Option<String>,
+    },
+
+    /// The failed attempt is retried after the `delay`. The HTTP status is `None` for transport failures.
+    RetryScheduled {
+        method: String,
+        attempt: u32,
+        delay: Duration,
+        http_status: Option<u16>,
+    },
+
+    /// The request waits for the `delay`, either paced by the client-side rate limiter or rejected by the endpoint
+    /// with `429 Too Many Requests`.
+    RateLimited {
+        method: String,
+        delay: Duration,
+        client_side: bool,
+    },
+
+    /// The status of the sent transaction was polled, `processed` once the node knows of it.
+    ConfirmationPoll {
+        signature: Signature,
+        poll: usize,
+        processed: bool,
+    },
+
+    /// The transaction was rejected by the preflight simulation.
+    PreflightFailure {
+        message: String,
+        error: Option<String>,
+        logs: Vec<String>,
+    },
+}
+
+/// Observer of the structured events of an `HttpSender` or a `WasmRpcClient`, e.g. to forward them to `log` or
+/// `tracing`, or to show them in the lapp's console. Events are delivered synchronously, so the observer should be
+/// cheap.
+pub trait RpcEventObserver: Send + Sync {
+    fn on_event(&self, event: &RpcEvent);
+}
+
+impl<F: Fn(&RpcEvent) + Send + Sync> RpcEventObserver for F {
+    fn on_event(&self, event: &RpcEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,237 @@
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client_api::{
+    client_error::Result as ClientResult,
+    rpc_request::{RpcError, RpcRequest, MAX_MULTIPLE_ACCOUNTS},
+    rpc_response::Response,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// Builder of a `getProgramAccounts` query, see `WasmRpcClient::program_accounts`.
+pub struct ProgramAccountsQuery<'a> {
+    client: &'a WasmRpcClient,
+    program_id: Pubkey,
+    filters: Vec<Value>,
+    data_slice: Option<(usize, usize)>,
+    page_size: usize,
+}
+
+impl WasmRpcClient {
+    /// Starts building a query of the accounts owned by `program_id`.
+    pub fn program_accounts(&self, program_id: &Pubkey) -> ProgramAccountsQuery<'_> {
+        ProgramAccountsQuery {
+            client: self,
+            program_id: *program_id,
+            filters: Vec::new(),
+            data_slice: None,
+            page_size: MAX_MULTIPLE_ACCOUNTS,
+        }
+    }
+}
+
+impl<'a> ProgramAccountsQuery<'a> {
+    /// Keeps the accounts whose data at `offset` matches `bytes`.
+    pub fn memcmp(mut self, offset: usize, bytes: &[u8]) -> Self {
+        self.filters.push(json!({
+            "memcmp": {
+                "offset": offset,
+                "bytes": bs58::encode(bytes).into_string(),
+            }
+        }));
+        self
+    }
+
+    /// Keeps the accounts whose data is `data_size` bytes long.
+    pub fn data_size(mut self, data_size: u64) -> Self {
+        self.filters.push(json!({ "dataSize": data_size }));
+        self
+    }
+
+    /// Returns only `length` bytes of the account data starting at `offset`.
+    pub fn with_data_slice(mut self, offset: usize, length: usize) -> Self {
+        self.data_slice = Some((offset, length));
+        self
+    }
+
+    /// Sets the number of accounts fetched per request by the paginated methods, capped by the
+    /// `getMultipleAccounts` limit.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, MAX_MULTIPLE_ACCOUNTS);
+        self
+    }
+
+    /// Fetches the matching accounts in a single request.
+    pub fn fetch(&self) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.get_program_accounts(self.data_slice)
+    }
+
+    /// Fetches the addresses of the matching accounts only, without their data.
+    pub fn fetch_pubkeys(&self) -> ClientResult<Vec<Pubkey>> {
+        Ok(self
+            .get_program_accounts(Some((0, 0)))?
+            .into_iter()
+            .map(|(pubkey, _)| pubkey)
+            .collect())
+    }
+
+    /// Fetches the addresses of the matching accounts first, then their data page by page, passing each page to
+    /// `on_page`. This keeps each response small even if the program owns a huge number of accounts. Accounts
+    /// closed in between are skipped.
+    pub fn for_each_page(
+        &self,
+        mut on_page: impl FnMut(Vec<(Pubkey, Account)>) -> ClientResult<()>,
+    ) -> ClientResult<()> {
+        for pubkeys in self.fetch_pubkeys()?.chunks(self.page_size) {
+            let mut config = json!({
+                "encoding": UiAccountEncoding::Base64,
+                "commitment": self.client.commitment().commitment,
+            });
+            if let Some((offset, length)) = self.data_slice {
+                config["dataSlice"] = json!({ "offset": offset, "length": length });
+            }
+
+            let response: Response<Vec<Option<UiAccount>>> = self.client.send(
+                RpcRequest::GetMultipleAccounts,
+                json!([pubkeys.iter().map(Pubkey::to_string).collect::<Vec<_>>(), config]),
+            )?;
+            let mut page = Vec::with_capacity(pubkeys.len());
+            for (pubkey, account) in pubkeys.iter().zip(response.value) {
+                if let Some(account) = account {
+                    page.push((*pubkey, decode_ui_account(pubkey, &account)?));
+                }
+            }
+            on_page(page)?;
+        }
+        Ok(())
+    }
+
+    /// Fetches the matching accounts page by page and decodes their data with `decode`.
+    pub fn fetch_decoded<T>(
+        &self,
+        decode: impl Fn(&Pubkey, &[u8]) -> ClientResult<T>,
+    ) -> ClientResult<Vec<(Pubkey, T)>> {
+        let mut decoded = Vec::new();
+        self.for_each_page(|page| {
+            for (pubkey, account) in page {
+                let value = decode(&pubkey, &account.data)?;
+                decoded.push((pubkey, value));
+            }
+            Ok(())
+        })?;
+        Ok(decoded)
+    }
+
+    fn get_program_accounts(&self, data_slice: Option<(usize, usize)>) -> ClientResult<Vec<(Pubkey, Account)>> {
+        let mut config = json!({
+            "encoding": UiAccountEncoding::Base64,
+            "commitment": self.client.commitment().commitment,
+        });
+        if !self.filters.is_empty() {
+            config["filters"] = Value::Array(self.filters.clone());
+        }
+        if let Some((offset, length)) = data_slice {
+            config["dataSlice"] = json!({ "offset": offset, "length": length });
+        }
+
+        let keyed_accounts: Vec<Value> = self.client.send(
+            RpcRequest::GetProgramAccounts,
+            json!([self.program_id.to_string(), config]),
+        )?;
+        keyed_accounts
+            .into_iter()
+            .map(|mut keyed_account| {
+                let pubkey: Pubkey = keyed_account["pubkey"]
+                    .as_str()
+                    .and_then(|pubkey| pubkey.parse().ok())
+                    .ok_or_else(|| RpcError::ParseError("program account has invalid pubkey".to_string()))?;
+                let account: UiAccount = serde_json::from_value(keyed_account["account"].take())
+                    .map_err(|err| RpcError::ParseError(format!("program account {} is invalid: {}", pubkey, err)))?;
+                Ok((pubkey, decode_ui_account(&pubkey, &account)?))
+            })
+            .collect()
+    }
+}
+
+fn decode_ui_account(pubkey: &Pubkey, account: &UiAccount) -> ClientResult<Account> {
+    account
+        .decode()
+        .ok_or_else(|| RpcError::ParseError(format!("failed to decode account {}", pubkey)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    fn ui_account(owner: &Pubkey, data: &[u8]) -> Value {
+        json!({
+            "lamports": 1,
+            "data": [base64::encode(data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        })
+    }
+
+    #[test]
+    fn pages_of_accounts_follow_the_fetched_pubkeys() {
+        let program_id = Pubkey::new_unique();
+        let pubkeys: Vec<_> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let mock = MockSender::new().with_response(
+            "getProgramAccounts",
+            Value::Array(
+                pubkeys
+                    .iter()
+                    .map(|pubkey| json!({"pubkey": pubkey.to_string(), "account": ui_account(&program_id, &[])}))
+                    .collect(),
+            ),
+        );
+        for page in [
+            json!([ui_account(&program_id, &[0]), ui_account(&program_id, &[1])]),
+            // The fourth account was closed after its pubkey was fetched
+            json!([ui_account(&program_id, &[2]), null]),
+            json!([ui_account(&program_id, &[4])]),
+        ] {
+            mock.push_response(
+                "getMultipleAccounts",
+                Ok(json!({"context": {"slot": 1}, "value": page})),
+            );
+        }
+        let client = WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ));
+
+        let decoded = client
+            .program_accounts(&program_id)
+            .data_size(1)
+            .page_size(2)
+            .fetch_decoded(|_, data| Ok(data[0]))
+            .unwrap();
+        assert_eq!(decoded, [
+            (pubkeys[0], 0),
+            (pubkeys[1], 1),
+            (pubkeys[2], 2),
+            (pubkeys[4], 4)
+        ]);
+
+        let query = &mock.calls_to("getProgramAccounts")[0][1];
+        assert_eq!(query["dataSlice"], json!({"offset": 0, "length": 0}));
+        assert_eq!(query["filters"], json!([{"dataSize": 1}]));
+        let pages: Vec<_> = mock
+            .calls_to("getMultipleAccounts")
+            .iter()
+            .map(|params| params[0].as_array().unwrap().len())
+            .collect();
+        assert_eq!(pages, [2, 2, 1]);
+        assert_eq!(
+            mock.calls_to("getMultipleAccounts")[2][0],
+            json!([pubkeys[4].to_string()])
+        );
+    }
+}
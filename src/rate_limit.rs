@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Client-side token bucket pacing the requests, so that the limits of public endpoints are not hit in the first
+/// place. Each HTTP attempt takes the weight of its method from the bucket, waiting for the bucket to refill when it
+/// is short of tokens. The methods without a set weight take one token.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: u32,
+    burst: u32,
+    method_weights: HashMap<String, u32>,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Negative when the waiting requests have reserved the tokens not refilled yet.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Limiter of `requests_per_second` sustained requests, allowing a burst of as many.
+    pub fn new(requests_per_second: u32) -> Self {
+        let requests_per_second = requests_per_second.max(1);
+        Self {
+            requests_per_second,
+            burst: requests_per_second,
+            method_weights: HashMap::new(),
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(requests_per_second),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Sets the number of requests that can be made at once after a period of inactivity.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self.bucket.get_mut().unwrap().tokens = f64::from(self.burst);
+        self
+    }
+
+    /// Sets the number of tokens a request of the `method` takes, e.g. more for `getProgramAccounts`.
+    pub fn with_method_weight(mut self, method: impl Into<String>, weight: u32) -> Self {
+        self.method_weights.insert(method.into(), weight);
+        self
+    }
+
+    pub fn requests_per_second(&self) -> u32 {
+        self.requests_per_second
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Returns the number of tokens a request of the `method` takes.
+    pub fn method_weight(&self, method: &str) -> u32 {
+        self.method_weights.get(method).copied().unwrap_or(1)
+    }
+
+    /// Takes the tokens of a request of the `method` and returns the time to wait before making it. The tokens are
+    /// reserved even if they are not available yet, so the concurrent requests are paced in the order they come.
+    pub(crate) fn reserve(&self, method: &str) -> Duration {
+        let weight = f64::from(self.method_weight(method));
+        if weight == 0.0 {
+            return Duration::ZERO;
+        }
+
+        let rate = f64::from(self.requests_per_second);
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.refilled_at).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refilled).min(f64::from(self.burst));
+        bucket.refilled_at = now;
+        bucket.tokens -= weight;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::{json, Value};
+    use solana_client_api::{rpc_request::RpcRequest, rpc_sender::RpcSender};
+
+    use super::*;
+    use crate::{transport::HostHttpResponse, HttpSender};
+
+    fn assert_about(delay: Duration, expected: Duration) {
+        assert!(
+            delay <= expected && delay > expected - Duration::from_millis(20),
+            "expected about {:?}, got {:?}",
+            expected,
+            delay
+        );
+    }
+
+    #[test]
+    fn requests_beyond_the_burst_wait_for_the_refill() {
+        let limiter = RateLimiter::new(10)
+            .with_burst(2)
+            .with_method_weight("getProgramAccounts", 3);
+
+        assert_eq!(limiter.reserve("getSlot"), Duration::ZERO);
+        assert_eq!(limiter.reserve("getSlot"), Duration::ZERO);
+        assert_about(limiter.reserve("getSlot"), Duration::from_millis(100));
+        // Reserved behind the waiting request
+        assert_about(limiter.reserve("getProgramAccounts"), Duration::from_millis(400));
+        assert_eq!(limiter.method_weight("unweighted"), 1);
+    }
+
+    #[test]
+    fn attempts_are_paced_by_the_sender() {
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://localhost:8899")
+            .with_rate_limiter(RateLimiter::new(20).with_burst(1))
+            .with_sleeper({
+                let delays = delays.clone();
+                move |delay| delays.lock().unwrap().push(delay)
+            })
+            .with_transport(|_| {
+                Ok(HostHttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: json!({"jsonrpc": "2.0", "id": 0, "result": 1}).to_string().into_bytes(),
+                })
+            });
+
+        for _ in 0..3 {
+            sender.send(RpcRequest::GetSlot, Value::Null).unwrap();
+        }
+        let delays = delays.lock().unwrap();
+        assert_eq!(delays.len(), 2);
+        assert_about(delays[0], Duration::from_millis(50));
+        assert_about(delays[1], Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// State of the pseudo-random jitter sequence, shared by all policies.
+static JITTER_STATE: AtomicU64 = AtomicU64::new(0x9E37_79B9_7F4A_7C15);
+
+/// Statuses of gateway and availability errors, retried only if added to the `retryable_statuses` of a policy, e.g.
+/// with `RetryPolicy::with_server_errors_retried`, since the request may have been processed before the error.
+pub const SERVER_ERROR_STATUSES: [u16; 3] = [502, 503, 504];
+
+/// Growth of the delay between consecutive retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// The same base delay before every retry.
+    Constant,
+
+    /// The base delay multiplied by `factor` after every retry, up to `max_delay`.
+    Exponential { factor: u32, max_delay: Duration },
+}
+
+/// Policy of retrying failed requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+
+    /// Delay before a retry, unless the endpoint requests another one.
+    pub base_delay: Duration,
+
+    pub backoff: Backoff,
+
+    /// Upper bound of a random delay added to each computed one, so that clients don't retry in lockstep.
+    pub jitter: Duration,
+
+    /// Maximum total time waited between the attempts of a request, no more retries are made once it would be
+    /// exceeded.
+    pub max_total_wait: Option<Duration>,
+
+    /// HTTP statuses of the responses that are retried, by default only `429 Too Many Requests`.
+    pub retryable_statuses: Vec<u16>,
+
+    /// Whether requests timed out by the endpoint, with `408 Request Timeout` or `504 Gateway Timeout`, are retried
+    /// in addition to the statuses listed as retryable.
+    pub retry_on_timeout: bool,
+
+    /// Whether requests failed at the transport level, e.g. on a dropped connection, are retried.
+    pub retry_transport_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            backoff: Backoff::Constant,
+            jitter: Duration::ZERO,
+            max_total_wait: None,
+            retryable_statuses: vec![429],
+            retry_on_timeout: false,
+            retry_transport_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy without any retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Policy doubling the delay after every retry, up to `max_delay`, with up to a quarter of the base delay of
+    /// jitter.
+    pub fn exponential(max_retries: u32, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            backoff: Backoff::Exponential { factor: 2, max_delay },
+            jitter: base_delay / 4,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the `SERVER_ERROR_STATUSES` to the retryable ones.
+    pub fn with_server_errors_retried(mut self) -> Self {
+        for status in SERVER_ERROR_STATUSES {
+            if !self.retryable_statuses.contains(&status) {
+                self.retryable_statuses.push(status);
+            }
+        }
+        self
+    }
+
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status) || (self.retry_on_timeout && matches!(status, 408 | 504))
+    }
+
+    /// Returns the delay before the retry following the failed `attempt` (starting from 0).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = match self.backoff {
+            Backoff::Constant => self.base_delay,
+            Backoff::Exponential { factor, max_delay } => factor
+                .checked_pow(attempt)
+                .and_then(|multiplier| self.base_delay.checked_mul(multiplier))
+                .map_or(max_delay, |delay| delay.min(max_delay)),
+        };
+        delay + self.jitter.mul_f64(next_jitter_fraction())
+    }
+
+    /// Checks whether waiting `delay` more after already waiting `waited` stays within the maximum total wait.
+    pub fn allows_wait(&self, waited: Duration, delay: Duration) -> bool {
+        self.max_total_wait
+            .map_or(true, |max_total_wait| waited + delay <= max_total_wait)
+    }
+}
+
+/// Returns a pseudo-random fraction in `[0, 1)`, the randomness is only needed to spread retries.
+fn next_jitter_fraction() -> f64 {
+    // SplitMix64 step
+    let mut z = JITTER_STATE.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+    use solana_client_api::{rpc_request::RpcRequest, rpc_sender::RpcSender};
+
+    use super::*;
+    use crate::{
+        mock_sender::{MockError, MockSender},
+        HttpSender,
+    };
+
+    #[test]
+    fn exponential_delays_grow_up_to_the_maximum() {
+        let mock = MockSender::new().with_response("getSlot", json!(10));
+        for _ in 0..4 {
+            mock.push_response("getSlot", Err(MockError::Transport("connection reset".to_string())));
+        }
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://localhost:8899")
+            .with_retry_policy(RetryPolicy {
+                jitter: Duration::ZERO,
+                ..RetryPolicy::exponential(5, Duration::from_millis(10), Duration::from_millis(50))
+            })
+            .with_sleeper({
+                let delays = delays.clone();
+                move |delay| delays.lock().unwrap().push(delay)
+            });
+
+        let result = sender.retry_with_policy(|_| mock.send(RpcRequest::GetSlot, Value::Null));
+        assert_eq!(result.unwrap(), json!(10));
+        assert_eq!(mock.call_count("getSlot"), 5);
+        assert_eq!(*delays.lock().unwrap(), [10, 20, 40, 50].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn retries_stop_at_the_maximum_total_wait() {
+        let mock = MockSender::new().with_error("getSlot", MockError::Transport("connection reset".to_string()));
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://localhost:8899")
+            .with_retry_policy(RetryPolicy {
+                max_total_wait: Some(Duration::from_millis(35)),
+                ..RetryPolicy::exponential(5, Duration::from_millis(10), Duration::from_secs(1))
+            })
+            .with_sleeper({
+                let delays = delays.clone();
+                move |delay| delays.lock().unwrap().push(delay)
+            });
+
+        assert!(sender
+            .retry_with_policy(|_| mock.send(RpcRequest::GetSlot, Value::Null))
+            .is_err());
+        // The 10ms and 20ms delays, each with up to 2.5ms of jitter, fit in the maximum, the 40ms one doesn't
+        assert_eq!(mock.call_count("getSlot"), 3);
+        assert_eq!(delays.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn server_errors_are_retried_only_when_opted_in() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable_status(429));
+        assert!(!policy.is_retryable_status(503));
+
+        let policy = policy.with_server_errors_retried();
+        assert!(SERVER_ERROR_STATUSES
+            .iter()
+            .all(|&status| policy.is_retryable_status(status)));
+        assert_eq!(policy.with_server_errors_retried().retryable_statuses.len(), 4);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash::Hash, signature::Signature};
+
+use crate::wasm_rpc_client::TrackedSignature;
+
+/// Transaction sent but not confirmed yet, as remembered by the `SignatureLedger`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    pub signature: Signature,
+
+    /// Blockhash whose validity bounds the lifetime of the transaction.
+    pub recent_blockhash: Hash,
+}
+
+impl From<TrackedSignature> for PendingTransaction {
+    fn from(tracked: TrackedSignature) -> Self {
+        Self {
+            signature: tracked.signature,
+            recent_blockhash: tracked.recent_blockhash,
+        }
+    }
+}
+
+/// Local record of the transactions sent by a `WasmRpcClient` whose confirmation is not settled yet. A transaction
+/// stays pending until it is confirmed, fails or its blockhash expires, so an interrupted confirmation can be
+/// resumed with `WasmRpcClient::resume_confirmation` instead of risking a duplicate execution by a resend.
+///
+/// The ledger lives in memory, to survive a crash of the lapp its pending transactions can be persisted from the
+/// change callback and restored with `from_pending`.
+#[derive(Default)]
+pub struct SignatureLedger {
+    pending: Mutex<HashMap<Signature, PendingTransaction>>,
+    on_change: Option<Box<dyn Fn(&[PendingTransaction]) + Send + Sync>>,
+}
+
+impl SignatureLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a ledger with the pending transactions restored from a previous session.
+    pub fn from_pending(pending: impl IntoIterator<Item = PendingTransaction>) -> Self {
+        Self {
+            pending: Mutex::new(
+                pending
+                    .into_iter()
+                    .map(|transaction| (transaction.signature, transaction))
+                    .collect(),
+            ),
+            on_change: None,
+        }
+    }
+
+    /// Sets a callback receiving all the pending transactions after each change, e.g. to persist them.
+    pub fn with_on_change(mut self, on_change: impl Fn(&[PendingTransaction]) + Send + Sync + 'static) -> Self {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    pub fn pending(&self) -> Vec<PendingTransaction> {
+        self.pending.lock().unwrap().values().copied().collect()
+    }
+
+    pub fn get(&self, signature: &Signature) -> Option<PendingTransaction> {
+        self.pending.lock().unwrap().get(signature).copied()
+    }
+
+    pub fn is_pending(&self, signature: &Signature) -> bool {
+        self.pending.lock().unwrap().contains_key(signature)
+    }
+
+    pub fn insert(&self, transaction: PendingTransaction) {
+        self.pending.lock().unwrap().insert(transaction.signature, transaction);
+        self.notify();
+    }
+
+    /// Forgets the transaction, e.g. to allow sending it once more.
+    pub fn remove(&self, signature: &Signature) -> Option<PendingTransaction> {
+        let removed = self.pending.lock().unwrap().remove(signature);
+        if removed.is_some() {
+            self.notify();
+        }
+        removed
+    }
+
+    fn notify(&self) {
+        if let Some(on_change) = &self.on_change {
+            on_change(&self.pending());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Category of RPC methods sharing a latency profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodCategory {
+    Read,
+    HeavyRead,
+    Write,
+}
+
+/// Policy of request timeouts per method category. Methods are classified by the first matching name prefix,
+/// the unmatched ones are light reads. A timeout set for a specific method overrides the one of its category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    pub read: Option<Duration>,
+    pub heavy_read: Option<Duration>,
+    pub write: Option<Duration>,
+    pub prefixes: Vec<(String, MethodCategory)>,
+    pub method_timeouts: HashMap<String, Duration>,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        let prefixes = [
+            ("send", MethodCategory::Write),
+            ("requestAirdrop", MethodCategory::Write),
+            ("getProgramAccounts", MethodCategory::HeavyRead),
+            ("getMultipleAccounts", MethodCategory::HeavyRead),
+            ("getTokenAccountsBy", MethodCategory::HeavyRead),
+            ("getSignaturesForAddress", MethodCategory::HeavyRead),
+            ("getLargestAccounts", MethodCategory::HeavyRead),
+            ("getBlocks", MethodCategory::HeavyRead),
+        ];
+
+        Self {
+            read: None,
+            heavy_read: None,
+            write: None,
+            prefixes: prefixes
+                .into_iter()
+                .map(|(prefix, category)| (prefix.to_string(), category))
+                .collect(),
+            method_timeouts: HashMap::new(),
+        }
+    }
+}
+
+impl TimeoutPolicy {
+    /// Policy with the same `timeout` for all the methods, unless overridden per method.
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            read: Some(timeout),
+            heavy_read: Some(timeout),
+            write: Some(timeout),
+            ..Self::default()
+        }
+    }
+
+    /// Overrides the timeout of the `method`, e.g. a longer one for `getProgramAccounts`.
+    pub fn with_method_timeout(mut self, method: impl Into<String>, timeout: Duration) -> Self {
+        self.method_timeouts.insert(method.into(), timeout);
+        self
+    }
+
+    pub fn category(&self, method: &str) -> MethodCategory {
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| method.starts_with(prefix.as_str()))
+            .map_or(MethodCategory::Read, |(_, category)| *category)
+    }
+
+    /// Returns the timeout of the `method`, `None` if it is not limited.
+    pub fn timeout(&self, method: &str) -> Option<Duration> {
+        if let Some(timeout) = self.method_timeouts.get(method) {
+            return Some(*timeout);
+        }
+        match self.category(method) {
+            MethodCategory::Read => self.read,
+            MethodCategory::HeavyRead => self.heavy_read,
+            MethodCategory::Write => self.write,
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use serde_json::{json, Value};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client_api::{
+    client_error::Result as ClientResult,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_response::Response,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signature,
+    signers::Signers,
+    system_program,
+};
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// Id of the SPL Token program.
+pub mod token_program {
+    solana_sdk::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+/// Id of the SPL Token-2022 program.
+pub mod token_2022_program {
+    solana_sdk::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+/// Id of the SPL Associated Token Account program.
+pub mod associated_token_program {
+    solana_sdk::declare_id!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+}
+
+/// Size of the base mint state, shared by both token programs.
+const MINT_LEN: usize = 82;
+
+/// Discriminators of the token program instructions.
+const TRANSFER_CHECKED: u8 = 12;
+
+/// Discriminator of the associated token account program instruction creating the account unless it exists.
+const CREATE_IDEMPOTENT: u8 = 1;
+
+/// Token account, as parsed by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccount {
+    pub pubkey: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+
+    /// Raw amount, in the smallest units of the mint.
+    pub amount: u64,
+    pub decimals: u8,
+
+    /// Token program owning the account, Token or Token-2022.
+    pub program_id: Pubkey,
+}
+
+/// Mint state, see `WasmRpcClient::get_mint_info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MintInfo {
+    pub mint_authority: Option<Pubkey>,
+    pub supply: u64,
+    pub decimals: u8,
+    pub is_initialized: bool,
+    pub freeze_authority: Option<Pubkey>,
+
+    /// Token program owning the mint, Token or Token-2022.
+    pub program_id: Pubkey,
+}
+
+/// Returns whether `program_id` is one of the token programs.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == token_program::id() || *program_id == token_2022_program::id()
+}
+
+/// Derives the associated token account of `owner` for `mint` of the token program `token_program_id`.
+pub fn get_associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program_id.as_ref(), mint.as_ref()],
+        &associated_token_program::id(),
+    )
+    .0
+}
+
+/// Instruction creating the associated token account of `owner` for `mint` paid by `payer`, doing nothing if it
+/// already exists.
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(associated_token_program::id(), &[CREATE_IDEMPOTENT], vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(get_associated_token_address(owner, mint, token_program_id), false),
+        AccountMeta::new_readonly(*owner, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ])
+}
+
+/// Instruction transferring `amount` of `mint` from the `source` to the `destination` token account, checking the
+/// mint `decimals`.
+pub fn transfer_checked(
+    token_program_id: &Pubkey,
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction::new_with_bytes(*token_program_id, &data, vec![
+        AccountMeta::new(*source, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*authority, true),
+    ])
+}
+
+/// Parses the base mint state, `None` if the data is too short.
+fn parse_mint(data: &[u8], program_id: Pubkey) -> Option<MintInfo> {
+    if data.len() < MINT_LEN {
+        return None;
+    }
+
+    let optional_pubkey =
+        |offset: usize| (data[offset..offset + 4] == [1, 0, 0, 0]).then(|| Pubkey::new(&data[offset + 4..offset + 36]));
+    Some(MintInfo {
+        mint_authority: optional_pubkey(0),
+        supply: u64::from_le_bytes(data[36..44].try_into().ok()?),
+        decimals: data[44],
+        is_initialized: data[45] != 0,
+        freeze_authority: optional_pubkey(46),
+        program_id,
+    })
+}
+
+/// Parses a keyed token account fetched in the `jsonParsed` encoding.
+fn parse_keyed_token_account(keyed_account: &Value, program_id: Pubkey) -> ClientResult<TokenAccount> {
+    let pubkey = keyed_account["pubkey"].as_str().unwrap_or_default();
+    let info = &keyed_account["account"]["data"]["parsed"]["info"];
+    let invalid_field = |field| RpcError::ParseError(format!("token account {} has invalid {}", pubkey, field));
+    let parse_pubkey = |value: &Value, field| {
+        value
+            .as_str()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| invalid_field(field))
+    };
+
+    Ok(TokenAccount {
+        pubkey: parse_pubkey(&keyed_account["pubkey"], "pubkey")?,
+        mint: parse_pubkey(&info["mint"], "mint")?,
+        owner: parse_pubkey(&info["owner"], "owner")?,
+        amount: info["tokenAmount"]["amount"]
+            .as_str()
+            .and_then(|amount| amount.parse().ok())
+            .ok_or_else(|| invalid_field("amount"))?,
+        decimals: info["tokenAmount"]["decimals"]
+            .as_u64()
+            .and_then(|decimals| u8::try_from(decimals).ok())
+            .ok_or_else(|| invalid_field("decimals"))?,
+        program_id,
+    })
+}
+
+impl WasmRpcClient {
+    /// Lists the token accounts of `owner` under both the Token and the Token-2022 programs.
+    pub fn token_accounts_by_owner(&self, owner: &Pubkey) -> ClientResult<Vec<TokenAccount>> {
+        let mut token_accounts = Vec::new();
+        for program_id in [token_program::id(), token_2022_program::id()] {
+            let response: Response<Vec<Value>> = self.send(
+                RpcRequest::GetTokenAccountsByOwner,
+                json!([owner.to_string(), { "programId": program_id.to_string() }, {
+                    "encoding": UiAccountEncoding::JsonParsed,
+                    "commitment": self.commitment().commitment,
+                }]),
+            )?;
+            for keyed_account in &response.value {
+                token_accounts.push(parse_keyed_token_account(keyed_account, program_id)?);
+            }
+        }
+        Ok(token_accounts)
+    }
+
+    /// Fetches the mint, under either token program.
+    pub fn get_mint_info(&self, mint: &Pubkey) -> ClientResult<MintInfo> {
+        let account = self
+            .get_account_with_commitment(mint, self.commitment())?
+            .value
+            .ok_or_else(|| RpcError::ForUser(format!("mint {} not found", mint)))?;
+        if !is_token_program(&account.owner) {
+            return Err(RpcError::ForUser(format!("account {} is not owned by a token program", mint)).into());
+        }
+
+        parse_mint(&account.data, account.owner)
+            .ok_or_else(|| RpcError::ParseError(format!("mint {} has invalid data", mint)).into())
+    }
+
+    /// Derives the associated token account of `owner` for `mint`, fetching the mint to find its token program.
+    pub fn associated_token_account(&self, owner: &Pubkey, mint: &Pubkey) -> ClientResult<Pubkey> {
+        let mint_info = self.get_mint_info(mint)?;
+        Ok(get_associated_token_address(owner, mint, &mint_info.program_id))
+    }
+
+    /// Transfers `amount` of `mint` from the associated token account of `owner` to the one of
+    /// `destination_owner`, creating the latter if it is missing. The transaction is paid by `payer`, signed by the
+    /// `signers`, which must include `payer` and `owner`, and confirmed.
+    pub fn transfer_tokens<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        destination_owner: &Pubkey,
+        amount: u64,
+    ) -> ClientResult<Signature> {
+        let mint_info = self.get_mint_info(mint)?;
+        let program_id = mint_info.program_id;
+        let source = get_associated_token_address(owner, mint, &program_id);
+        let destination = get_associated_token_address(destination_owner, mint, &program_id);
+
+        let mut instructions = Vec::with_capacity(2);
+        if self
+            .get_account_with_commitment(&destination, self.commitment())?
+            .value
+            .is_none()
+        {
+            instructions.push(create_associated_token_account_idempotent(
+                payer,
+                destination_owner,
+                mint,
+                &program_id,
+            ));
+        }
+        instructions.push(transfer_checked(
+            &program_id,
+            &source,
+            mint,
+            &destination,
+            owner,
+            amount,
+            mint_info.decimals,
+        ));
+
+        self.sign_and_confirm(signers, payer, &instructions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    #[test]
+    fn transfer_checked_encodes_the_amount_and_the_decimals() {
+        let [source, mint, destination, authority] = [(); 4].map(|_| Pubkey::new_unique());
+        let instruction = transfer_checked(
+            &token_2022_program::id(),
+            &source,
+            &mint,
+            &destination,
+            &authority,
+            0x0102_0304_0506_0708,
+            6,
+        );
+
+        assert_eq!(instruction.program_id, token_2022_program::id());
+        assert_eq!(instruction.data, [12, 8, 7, 6, 5, 4, 3, 2, 1, 6]);
+        let accounts: Vec<_> = instruction
+            .accounts
+            .iter()
+            .map(|meta| (meta.pubkey, meta.is_writable, meta.is_signer))
+            .collect();
+        assert_eq!(accounts, [
+            (source, true, false),
+            (mint, false, false),
+            (destination, true, false),
+            (authority, false, true),
+        ]);
+    }
+
+    #[test]
+    fn mint_state_is_parsed_at_its_offsets() {
+        let mint_authority = Pubkey::new_unique();
+        let mut data = vec![0; MINT_LEN];
+        data[..4].copy_from_slice(&[1, 0, 0, 0]);
+        data[4..36].copy_from_slice(mint_authority.as_ref());
+        data[36..44].copy_from_slice(&1_000_000_u64.to_le_bytes());
+        data[44] = 9;
+        data[45] = 1;
+
+        assert_eq!(
+            parse_mint(&data, token_program::id()),
+            Some(MintInfo {
+                mint_authority: Some(mint_authority),
+                supply: 1_000_000,
+                decimals: 9,
+                is_initialized: true,
+                freeze_authority: None,
+                program_id: token_program::id(),
+            })
+        );
+        assert_eq!(parse_mint(&data[..MINT_LEN - 1], token_program::id()), None);
+    }
+
+    #[test]
+    fn token_accounts_of_both_programs_are_listed() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let keyed_account = json!({
+            "pubkey": token_account.to_string(),
+            "account": {"data": {"parsed": {"info": {
+                "mint": mint.to_string(),
+                "owner": owner.to_string(),
+                "tokenAmount": {"amount": "42", "decimals": 2},
+            }}}},
+        });
+        let mock = MockSender::new();
+        mock.push_response(
+            "getTokenAccountsByOwner",
+            Ok(json!({"context": {"slot": 1}, "value": []})),
+        );
+        mock.push_response(
+            "getTokenAccountsByOwner",
+            Ok(json!({"context": {"slot": 1}, "value": [keyed_account]})),
+        );
+        let client = WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ));
+
+        assert_eq!(client.token_accounts_by_owner(&owner).unwrap(), [TokenAccount {
+            pubkey: token_account,
+            mint,
+            owner,
+            amount: 42,
+            decimals: 2,
+            program_id: token_2022_program::id(),
+        }]);
+        let calls = mock.calls_to("getTokenAccountsByOwner");
+        assert_eq!(calls[0][1]["programId"], token_program::id().to_string());
+        assert_eq!(calls[1][1]["programId"], token_2022_program::id().to_string());
+        assert_eq!(calls[1][2]["encoding"], "jsonParsed");
+    }
+}
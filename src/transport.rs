@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use laplace_wasm::http;
+
+/// Blocking HTTP call making the exchanges of an `HttpSender`, see `HttpSender::with_transport`.
+pub type HttpTransport = Box<dyn Fn(HostHttpRequest) -> Result<HostHttpResponse, String> + Send + Sync>;
+
+/// HTTP POST request passed to the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostHttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+
+    /// Time left until the request timeout, `None` if the request has no timeout. The transport should abort the
+    /// exchange once it expires, since the sender can't interrupt a call in progress.
+    pub timeout: Option<Duration>,
+}
+
+/// HTTP response returned by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostHttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HostHttpResponse {
+    /// Returns the value of the header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the delay requested by the `Retry-After` header, if it is given in seconds and is reasonable.
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        self.header("retry-after")
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|retry_after| *retry_after < 120)
+            .map(Duration::from_secs)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Makes the exchange with the blocking `laplace_wasm::http::invoke`, the default transport.
+///
+/// The host call has no timeout, so the `timeout` of the request is not applied: a hung endpoint blocks the call
+/// until the host gives up on it. Lapps needing a hard bound should provide a transport that applies it.
+pub fn invoke_host(request: HostHttpRequest) -> Result<HostHttpResponse, String> {
+    let mut http_request_builder = http::RequestBuilder::new()
+        .method(http::Method::POST)
+        .uri(request.url.as_str());
+    for (name, value) in &request.headers {
+        http_request_builder = http_request_builder.header(name.as_str(), value.as_str());
+    }
+    let http_request = http_request_builder
+        .body(request.body)
+        .map_err(|err| format!("{:?}", err))?
+        .into();
+
+    let http_response = http::invoke(http_request).map_err(|err| format!("{:?}", err))?;
+    Ok(HostHttpResponse {
+        status: http_response.status.as_u16(),
+        headers: http_response
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body: http_response.body,
+    })
+}
@@ -0,0 +1,415 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
+
+use solana_client_api::{client_error::Result as ClientResult, rpc_client::GetConfirmedSignaturesForAddress2Config};
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+    transaction::Result as TransactionResult,
+};
+
+use crate::wasm_rpc_client::WasmRpcClient;
+
+/// Default interval between the polls of a subscription.
+pub const DEFAULT_PUBSUB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identifier of a subscription, used to unsubscribe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Changed state of a subscribed account, `None` if it was closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountUpdate {
+    pub slot: Slot,
+    pub account: Option<Account>,
+}
+
+/// Logs of a transaction mentioning a subscribed address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogsUpdate {
+    pub signature: Signature,
+    pub slot: Slot,
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+/// Status of a subscribed transaction that reached the subscription commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureUpdate {
+    pub signature: Signature,
+    pub status: TransactionResult<()>,
+}
+
+enum SubscriptionKind<'a> {
+    Account {
+        pubkey: Pubkey,
+        last_account: Option<Option<Account>>,
+        on_update: Box<dyn FnMut(AccountUpdate) + 'a>,
+    },
+    Logs {
+        address: Pubkey,
+        last_signature: Option<Signature>,
+        started: bool,
+        on_update: Box<dyn FnMut(LogsUpdate) + 'a>,
+    },
+    Signature {
+        signature: Signature,
+        commitment: CommitmentConfig,
+        on_update: Box<dyn FnMut(SignatureUpdate) + 'a>,
+    },
+}
+
+struct Subscription<'a> {
+    id: SubscriptionId,
+    interval: Duration,
+    next_poll: Instant,
+    kind: SubscriptionKind<'a>,
+}
+
+/// Emulation of the `accountSubscribe`, `logsSubscribe` and `signatureSubscribe` WebSocket subscriptions, which are
+/// not available through the host HTTP transport, by polling the HTTP endpoint of the `client`. Updates are
+/// delivered to callbacks or channels by `poll` or `run_for`, called from the lapp's event loop.
+pub struct WasmPubsubClient<'a> {
+    client: &'a WasmRpcClient,
+    poll_interval: Duration,
+    next_id: u64,
+    subscriptions: Vec<Subscription<'a>>,
+}
+
+impl<'a> WasmPubsubClient<'a> {
+    pub fn new(client: &'a WasmRpcClient) -> Self {
+        Self {
+            client,
+            poll_interval: DEFAULT_PUBSUB_POLL_INTERVAL,
+            next_id: 0,
+            subscriptions: Vec::new(),
+        }
+    }
+
+    /// Sets the poll interval of the subscriptions made afterwards.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Subscribes to the changes of the account at `pubkey`, the current state is delivered by the first poll.
+    pub fn account_subscribe(&mut self, pubkey: Pubkey, on_update: impl FnMut(AccountUpdate) + 'a) -> SubscriptionId {
+        self.subscribe(SubscriptionKind::Account {
+            pubkey,
+            last_account: None,
+            on_update: Box::new(on_update),
+        })
+    }
+
+    pub fn account_subscribe_channel(&mut self, pubkey: Pubkey) -> (SubscriptionId, Receiver<AccountUpdate>) {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.account_subscribe(pubkey, move |update| {
+            let _ = sender.send(update);
+        });
+        (id, receiver)
+    }
+
+    /// Subscribes to the logs of the confirmed transactions mentioning the `address`, starting from the
+    /// subscription.
+    pub fn logs_subscribe(&mut self, address: Pubkey, on_update: impl FnMut(LogsUpdate) + 'a) -> SubscriptionId {
+        self.subscribe(SubscriptionKind::Logs {
+            address,
+            last_signature: None,
+            started: false,
+            on_update: Box::new(on_update),
+        })
+    }
+
+    pub fn logs_subscribe_channel(&mut self, address: Pubkey) -> (SubscriptionId, Receiver<LogsUpdate>) {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.logs_subscribe(address, move |update| {
+            let _ = sender.send(update);
+        });
+        (id, receiver)
+    }
+
+    /// Subscribes to the transaction with `signature` reaching the `commitment`. The subscription ends after its
+    /// only update.
+    pub fn signature_subscribe(
+        &mut self,
+        signature: Signature,
+        commitment: CommitmentConfig,
+        on_update: impl FnMut(SignatureUpdate) + 'a,
+    ) -> SubscriptionId {
+        self.subscribe(SubscriptionKind::Signature {
+            signature,
+            commitment,
+            on_update: Box::new(on_update),
+        })
+    }
+
+    pub fn signature_subscribe_channel(
+        &mut self,
+        signature: Signature,
+        commitment: CommitmentConfig,
+    ) -> (SubscriptionId, Receiver<SignatureUpdate>) {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.signature_subscribe(signature, commitment, move |update| {
+            let _ = sender.send(update);
+        });
+        (id, receiver)
+    }
+
+    /// Returns `true` if the subscription was active.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscriptions.len();
+        self.subscriptions.retain(|subscription| subscription.id != id);
+        self.subscriptions.len() != len
+    }
+
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Polls the subscriptions that are due, delivering their updates. All of them are polled even if some fail,
+    /// the first error is returned.
+    pub fn poll(&mut self) -> ClientResult<()> {
+        let client = self.client;
+        let mut first_error = None;
+        let mut finished = Vec::new();
+
+        for subscription in &mut self.subscriptions {
+            let now = Instant::now();
+            if now < subscription.next_poll {
+                continue;
+            }
+            subscription.next_poll = now + subscription.interval;
+
+            match poll_subscription(client, &mut subscription.kind) {
+                Ok(true) => finished.push(subscription.id),
+                Ok(false) => (),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                },
+            }
+        }
+        self.subscriptions
+            .retain(|subscription| !finished.contains(&subscription.id));
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Keeps polling the subscriptions for the `duration` or until none is left.
+    pub fn run_for(&mut self, duration: Duration) -> ClientResult<()> {
+        let started = Instant::now();
+        while !self.subscriptions.is_empty() && started.elapsed() < duration {
+            self.poll()?;
+
+            let now = Instant::now();
+            if let Some(next_poll) = self
+                .subscriptions
+                .iter()
+                .map(|subscription| subscription.next_poll)
+                .min()
+            {
+                self.client.sleep(
+                    next_poll
+                        .saturating_duration_since(now)
+                        .min(duration.saturating_sub(started.elapsed())),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe(&mut self, kind: SubscriptionKind<'a>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscriptions.push(Subscription {
+            id,
+            interval: self.poll_interval,
+            next_poll: Instant::now(),
+            kind,
+        });
+        id
+    }
+}
+
+/// Polls the subscription once, returning `true` if it has finished.
+fn poll_subscription(client: &WasmRpcClient, kind: &mut SubscriptionKind) -> ClientResult<bool> {
+    match kind {
+        SubscriptionKind::Account {
+            pubkey,
+            last_account,
+            on_update,
+        } => {
+            let response = client.get_account_with_commitment(pubkey, client.commitment())?;
+            if last_account.as_ref() != Some(&response.value) {
+                *last_account = Some(response.value.clone());
+                on_update(AccountUpdate {
+                    slot: response.context.slot,
+                    account: response.value,
+                });
+            }
+            Ok(false)
+        },
+        SubscriptionKind::Logs {
+            address,
+            last_signature,
+            started,
+            on_update,
+        } => {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                until: *last_signature,
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            };
+            let statuses = client.get_signatures_for_address_with_config(address, config)?;
+
+            // Transactions before the subscription are skipped like the WebSocket subscription does
+            if !*started {
+                if let Some(newest) = statuses.first().and_then(|status| status.signature.parse().ok()) {
+                    *last_signature = Some(newest);
+                }
+                *started = true;
+                return Ok(false);
+            }
+
+            for status in statuses.iter().rev() {
+                let signature = match status.signature.parse() {
+                    Ok(signature) => signature,
+                    Err(_) => continue,
+                };
+                let meta = client.get_transaction_raw_meta(&signature)?;
+                let logs = meta["logMessages"]
+                    .as_array()
+                    .map(|logs| logs.iter().filter_map(|log| log.as_str().map(String::from)).collect())
+                    .unwrap_or_default();
+                on_update(LogsUpdate {
+                    signature,
+                    slot: status.slot,
+                    failed: status.err.is_some(),
+                    logs,
+                });
+                // Advanced only once delivered, so the next poll resumes from here if a later fetch fails
+                *last_signature = Some(signature);
+            }
+            Ok(false)
+        },
+        SubscriptionKind::Signature {
+            signature,
+            commitment,
+            on_update,
+        } => match client.get_signature_status_with_commitment(signature, *commitment)? {
+            Some(status) => {
+                on_update(SignatureUpdate {
+                    signature: *signature,
+                    status,
+                });
+                Ok(true)
+            },
+            None => Ok(false),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use solana_client_api::rpc_client::{RpcClient, RpcClientConfig};
+
+    use super::*;
+    use crate::mock_sender::MockSender;
+
+    fn mock_client(mock: &MockSender) -> WasmRpcClient {
+        WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ))
+        .with_sleeper(|_| {})
+    }
+
+    /// Mock of a node answering the version checks of the `RpcClient` methods.
+    fn rpc_mock() -> MockSender {
+        MockSender::new().with_response("getVersion", json!({ "solana-core": "1.9.0" }))
+    }
+
+    fn context_response(slot: Slot, value: Value) -> Value {
+        json!({"context": {"slot": slot}, "value": value})
+    }
+
+    #[test]
+    fn account_updates_are_delivered_only_on_change() {
+        let owner = Pubkey::new_unique();
+        let ui_account = json!({
+            "lamports": 5,
+            "data": ["", "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        });
+        let mock = rpc_mock();
+        for response in [
+            context_response(1, Value::Null),
+            context_response(2, Value::Null),
+            context_response(3, ui_account),
+        ] {
+            mock.push_response("getAccountInfo", Ok(response));
+        }
+        let client = mock_client(&mock);
+        let mut pubsub = WasmPubsubClient::new(&client).with_poll_interval(Duration::ZERO);
+        let (id, updates) = pubsub.account_subscribe_channel(Pubkey::new_unique());
+
+        for _ in 0..3 {
+            pubsub.poll().unwrap();
+        }
+        assert_eq!(updates.try_iter().collect::<Vec<_>>(), [
+            AccountUpdate { slot: 1, account: None },
+            AccountUpdate {
+                slot: 3,
+                account: Some(Account {
+                    lamports: 5,
+                    data: Vec::new(),
+                    owner,
+                    executable: false,
+                    rent_epoch: 0,
+                }),
+            },
+        ]);
+
+        assert!(pubsub.unsubscribe(id));
+        assert!(!pubsub.unsubscribe(id));
+        pubsub.poll().unwrap();
+        assert_eq!(mock.call_count("getAccountInfo"), 3);
+    }
+
+    #[test]
+    fn signature_subscription_ends_after_its_update() {
+        let signature = Signature::new_unique();
+        let mock = rpc_mock().with_response(
+            "getSignatureStatuses",
+            context_response(
+                5,
+                json!([{
+                    "slot": 5,
+                    "confirmations": null,
+                    "status": { "Ok": null },
+                    "err": null,
+                    "confirmationStatus": "finalized",
+                }]),
+            ),
+        );
+        mock.push_response("getSignatureStatuses", Ok(context_response(4, json!([null]))));
+        let client = mock_client(&mock);
+        let mut pubsub = WasmPubsubClient::new(&client).with_poll_interval(Duration::ZERO);
+        let (_, updates) = pubsub.signature_subscribe_channel(signature, CommitmentConfig::finalized());
+
+        pubsub.poll().unwrap();
+        assert!(updates.try_recv().is_err());
+        assert_eq!(pubsub.subscription_count(), 1);
+
+        pubsub.run_for(Duration::from_secs(1)).unwrap();
+        assert_eq!(updates.try_recv().unwrap(), SignatureUpdate {
+            signature,
+            status: Ok(()),
+        });
+        assert_eq!(pubsub.subscription_count(), 0);
+        assert_eq!(mock.call_count("getSignatureStatuses"), 2);
+    }
+}
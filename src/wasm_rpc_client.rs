@@ -1,81 +1,4875 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::{HashMap, VecDeque},
+    iter,
+    ops::{Deref, DerefMut},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
 
-use solana_client_api::{client_error::Result as ClientResult, rpc_client::RpcClient, rpc_request::RpcError};
+#[cfg(feature = "borsh")]
+use borsh::BorshDeserialize;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountData, UiAccountEncoding};
+use solana_client_api::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_config::{RpcSendTransactionConfig, RpcTransactionConfig},
+    rpc_custom_error,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS},
+    rpc_response::{
+        Response, RpcBlockCommitment, RpcBlockhash, RpcKeyedAccount, RpcPerfSample, RpcSimulateTransactionResult,
+        RpcTokenAccountBalance,
+    },
+    rpc_sender::RpcSender,
+};
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
+    account::Account,
+    clock::{Epoch, Slot, DEFAULT_MS_PER_SLOT},
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    epoch_schedule::EpochSchedule,
+    hash::Hash,
+    instruction::{CompiledInstruction, Instruction, InstructionError},
+    message::{Message, VersionedMessage},
+    nonce::state::{State as NonceState, Versions as NonceVersions},
+    pubkey::Pubkey,
     signature::Signature,
-    transaction::{uses_durable_nonce, Transaction},
+    signers::Signers,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::{uses_durable_nonce, Transaction, TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{
+    InnerInstructions, TransactionConfirmationStatus, TransactionDetails, TransactionStatus, TransactionStatusMeta,
+    TransactionTokenBalance, UiConfirmedBlock, UiInstruction, UiTransactionEncoding, UiTransactionStatusMeta,
+    UiTransactionTokenBalance,
+};
+
+use crate::{
+    confirmation_hub::ConfirmationHub,
+    custom_request,
+    decode::{
+        compact_account_encoding, decode_account, decode_account_data, parse_program_account, DataDecoder,
+        DefaultDataDecoder, ParsedProgramAccount,
+    },
+    fees::{self, with_compute_budget, MAX_COMPUTE_UNIT_LIMIT},
+    observer::{RpcEvent, RpcEventObserver},
+    signature_ledger::SignatureLedger,
+    sleep, HttpSender, SharedHttpSender,
 };
 
-pub struct WasmRpcClient(RpcClient);
+/// Interval between signature status polls.
+pub(crate) const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Maximum number of signature status polls made when confirming a bare signature.
+const CONFIRM_STATUS_RETRIES: usize = 120;
+
+/// Interval between epoch info polls, epochs last for days on mainnet, so there is no point to poll them often.
+const EPOCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of retries of reads rejected because the node is behind the cluster.
+const NODE_UNHEALTHY_RETRIES: usize = 5;
+
+/// Maximum number of signature status polls made when waiting for finalization.
+const FINALIZE_STATUS_RETRIES: usize = 240;
+
+/// Percentage of the stake voting on the latest confirmed block above which `recommend_commitment` considers the
+/// confirmed commitment safe.
+const SAFE_CONFIRMED_STAKE_PERCENT: u128 = 80;
+
+/// Number of recent performance samples the confirmation time is estimated from.
+const ESTIMATE_PERFORMANCE_SAMPLES: usize = 5;
+
+/// Typical number of slots until a processed transaction is optimistically confirmed, counting its own slot.
+const CONFIRMED_DEPTH_SLOTS: u32 = 2;
+
+/// Typical number of slots until a processed transaction is finalized, counting its own slot.
+const FINALIZED_DEPTH_SLOTS: u32 = 32;
+
+/// Time the historical query floor is cached for.
+const HISTORICAL_FLOOR_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Margin added to the compute units consumed in simulation when deriving the compute unit limit.
+const COMPUTE_UNIT_LIMIT_MARGIN_PERCENT: u64 = 10;
+
+/// Number of the most recent failed transactions whose program logs are kept, see `failure_logs`.
+const KEPT_FAILURE_LOGS: usize = 32;
+
+/// Number of the most recently fetched blockhashes whose slot is kept, see `fetched_blockhash_age`.
+const KEPT_BLOCKHASH_SLOTS: usize = 32;
+
+/// Default number of times an expired transaction is re-signed with a fresh blockhash and resent.
+pub const DEFAULT_RESIGN_ATTEMPTS: usize = 3;
+
+/// Default name of the provider extension method resolving names to addresses.
+pub const DEFAULT_NAME_RESOLUTION_METHOD: &str = "resolveName";
+
+/// JSON-RPC error code of an unsupported method.
+pub const JSON_RPC_METHOD_NOT_FOUND: i64 = -32601;
+
+/// Commitment array of a block: stake voted at each lockout depth, indexed by confirmation count.
+pub type BlockCommitmentArray = [u64; 32];
+
+pub struct WasmRpcClient {
+    client: RpcClient,
+    http_sender: Option<Arc<HttpSender>>,
+    nonce_blockhash_commitment: CommitmentConfig,
+    data_decoder: Box<dyn DataDecoder>,
+    on_commitment_mismatch: Option<Box<dyn Fn(&Pubkey, Option<&Account>, Option<&Account>) + Send + Sync>>,
+    fallback_account_encoding: Option<UiAccountEncoding>,
+    tx_wire_encoding: Option<UiTransactionEncoding>,
+    priority_fee_percentile: Option<u8>,
+    blockhash_not_found_retries: usize,
+    resign_attempts: usize,
+    blockhash_ttl: Option<Duration>,
+    cached_blockhash: RwLock<Option<(Instant, Hash)>>,
+    blockhash_slots: RwLock<VecDeque<(Hash, Slot)>>,
+    attach_logs_on_failure: bool,
+    failure_logs: RwLock<VecDeque<(Signature, Vec<String>)>>,
+    slot_poll_interval: Duration,
+    overall_deadline: Option<Duration>,
+    downgrade_on_timeout: bool,
+    send_retries: usize,
+    resend_delay: Duration,
+    confirmation_hub: Option<Arc<ConfirmationHub>>,
+    endpoints: Vec<String>,
+    endpoint_latencies: RwLock<Vec<(String, Duration)>>,
+    node_version: RwLock<Option<NodeVersion>>,
+    method_availability: RwLock<HashMap<String, bool>>,
+    rent_exempt_minimums: RwLock<HashMap<usize, u64>>,
+    historical_floor: RwLock<Option<(Instant, Slot)>>,
+    name_resolution_method: String,
+    cancellation_token: Option<CancellationToken>,
+    signature_ledger: Option<Arc<SignatureLedger>>,
+    observer: Option<Arc<dyn RpcEventObserver>>,
+    sleeper: Option<Box<dyn Fn(Duration) + Send + Sync>>,
+}
 
 impl WasmRpcClient {
     pub fn new(client: RpcClient) -> Self {
-        Self(client)
+        Self {
+            client,
+            http_sender: None,
+            nonce_blockhash_commitment: CommitmentConfig::processed(),
+            data_decoder: Box::new(DefaultDataDecoder),
+            on_commitment_mismatch: None,
+            fallback_account_encoding: Some(UiAccountEncoding::Base64),
+            tx_wire_encoding: None,
+            priority_fee_percentile: None,
+            blockhash_not_found_retries: 3,
+            resign_attempts: DEFAULT_RESIGN_ATTEMPTS,
+            blockhash_ttl: None,
+            cached_blockhash: RwLock::new(None),
+            blockhash_slots: RwLock::new(VecDeque::new()),
+            attach_logs_on_failure: false,
+            failure_logs: RwLock::new(VecDeque::new()),
+            slot_poll_interval: Duration::from_millis(DEFAULT_MS_PER_SLOT),
+            overall_deadline: None,
+            downgrade_on_timeout: false,
+            send_retries: 1,
+            resend_delay: STATUS_POLL_INTERVAL,
+            confirmation_hub: None,
+            endpoints: Vec::new(),
+            endpoint_latencies: RwLock::new(Vec::new()),
+            node_version: RwLock::new(None),
+            method_availability: RwLock::new(HashMap::new()),
+            rent_exempt_minimums: RwLock::new(HashMap::new()),
+            historical_floor: RwLock::new(None),
+            name_resolution_method: DEFAULT_NAME_RESOLUTION_METHOD.to_string(),
+            cancellation_token: None,
+            signature_ledger: None,
+            observer: None,
+            sleeper: None,
+        }
+    }
+
+    /// Creates a client over the `sender`, which stays accessible through `http_sender`.
+    pub fn with_http_sender(sender: HttpSender, config: RpcClientConfig) -> Self {
+        let sender = Arc::new(sender);
+        let mut client = Self::new(RpcClient::new_sender(SharedHttpSender(Arc::clone(&sender)), config));
+        client.observer = sender.observer.clone();
+        client.http_sender = Some(sender);
+        client
+    }
+
+    pub fn http_sender(&self) -> Option<&HttpSender> {
+        self.http_sender.as_deref()
+    }
+
+    /// Sets how many times `send_and_confirm_with_rebuild` rebuilds the transaction when the preflight doesn't find
+    /// its freshly fetched blockhash yet.
+    pub fn with_blockhash_not_found_retries(mut self, retries: usize) -> Self {
+        self.blockhash_not_found_retries = retries;
+        self
+    }
+
+    /// Sets how many times `send_and_confirm_with_signers` re-signs the transaction with a fresh blockhash after
+    /// its blockhash expired before confirmation.
+    pub fn with_resign_attempts(mut self, resign_attempts: usize) -> Self {
+        self.resign_attempts = resign_attempts;
+        self
+    }
+
+    /// Enables caching of the latest blockhash for `ttl`, which saves a request per transaction built by
+    /// `send_and_confirm_with_rebuild` or re-signed by `send_and_confirm_with_signers`. A blockhash older than half
+    /// of the `ttl` is refreshed on access, keeping the cached one if the refresh fails.
+    pub fn with_blockhash_cache(mut self, ttl: Duration) -> Self {
+        self.blockhash_ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the latest blockhash at the client's commitment, from the cache if it is enabled and fresh.
+    pub fn get_cached_blockhash(&self) -> ClientResult<Hash> {
+        let ttl = match self.blockhash_ttl {
+            Some(ttl) => ttl,
+            None => return Ok(self.fetch_latest_blockhash(self.commitment())?.0),
+        };
+
+        let cached = *self.cached_blockhash.read().unwrap();
+        match cached {
+            Some((fetched_at, blockhash)) if fetched_at.elapsed() < ttl / 2 => Ok(blockhash),
+            Some((fetched_at, blockhash)) if fetched_at.elapsed() < ttl => {
+                Ok(self.refresh_cached_blockhash().unwrap_or(blockhash))
+            },
+            _ => self.refresh_cached_blockhash(),
+        }
+    }
+
+    /// Drops the cached blockhash, e.g. after a transaction using it expired.
+    pub fn invalidate_cached_blockhash(&self) {
+        *self.cached_blockhash.write().unwrap() = None;
+    }
+
+    fn refresh_cached_blockhash(&self) -> ClientResult<Hash> {
+        let (blockhash, _) = self.fetch_latest_blockhash(self.commitment())?;
+        *self.cached_blockhash.write().unwrap() = Some((Instant::now(), blockhash));
+        Ok(blockhash)
+    }
+
+    /// Sets whether the program logs of a transaction that failed during confirmation are fetched. The returned
+    /// error keeps its `TransactionError` kind, the logs are available from `failure_logs`.
+    pub fn with_attach_logs_on_failure(mut self, attach_logs_on_failure: bool) -> Self {
+        self.attach_logs_on_failure = attach_logs_on_failure;
+        self
+    }
+
+    /// Returns the program logs of the transaction with the `signature` if it failed during a recent confirmation
+    /// and its logs were fetched, see `with_attach_logs_on_failure`.
+    pub fn failure_logs(&self, signature: &Signature) -> Option<Vec<String>> {
+        self.failure_logs
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(failed_signature, _)| failed_signature == signature)
+            .map(|(_, logs)| logs.clone())
+    }
+
+    /// Sets the function waiting between the polls of the client, e.g. one returning immediately in tests using a
+    /// `MockSender`. By default the client waits with the sleeper of its `HttpSender`, if it has one.
+    pub fn with_sleeper(mut self, sleeper: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.sleeper = Some(Box::new(sleeper));
+        self
+    }
+
+    /// Sleeps with the client's sleeper, or the sender's one if the client has a known sender.
+    pub(crate) fn sleep(&self, duration: Duration) {
+        match (&self.sleeper, self.http_sender()) {
+            (Some(sleeper), _) => sleeper(duration),
+            (None, Some(http_sender)) => http_sender.sleep(duration),
+            (None, None) => sleep(duration),
+        }
+    }
+
+    /// Sets the deadline bounding the whole `send_and_confirm_transaction` flow, sending and confirmation included.
+    /// It is checked between the requests, which can't be interrupted.
+    pub fn with_overall_deadline(mut self, overall_deadline: Option<Duration>) -> Self {
+        self.overall_deadline = overall_deadline;
+        self
+    }
+
+    /// Sets whether `confirm_finalized` returns the highest commitment reached on timeout instead of an error.
+    pub fn with_downgrade_on_timeout(mut self, downgrade_on_timeout: bool) -> Self {
+        self.downgrade_on_timeout = downgrade_on_timeout;
+        self
+    }
+
+    /// Sets how many times `send_and_confirm_transaction` sends the transaction when sending fails or its blockhash
+    /// expires unconfirmed, waiting `resend_delay` between the attempts.
+    pub fn with_send_retries(mut self, send_retries: usize, resend_delay: Duration) -> Self {
+        self.send_retries = send_retries;
+        self.resend_delay = resend_delay;
+        self
+    }
+
+    /// Sets the provider's extension method used by `resolve_name`.
+    pub fn with_name_resolution_method(mut self, method: impl Into<String>) -> Self {
+        self.name_resolution_method = method.into();
+        self
+    }
+
+    /// Sets the interval between slot polls made by `poll_slots`.
+    pub fn with_slot_poll_interval(mut self, interval: Duration) -> Self {
+        self.slot_poll_interval = interval;
+        self
+    }
+
+    /// Sets the hub polling the confirmations of `send_and_confirm_transaction` together with the other clients and
+    /// concurrent calls sharing it.
+    pub fn with_confirmation_hub(mut self, hub: Arc<ConfirmationHub>) -> Self {
+        self.confirmation_hub = Some(hub);
+        self
+    }
+
+    pub fn confirmation_hub(&self) -> Option<&Arc<ConfirmationHub>> {
+        self.confirmation_hub.as_ref()
+    }
+
+    /// Sets additional endpoints that are warmed up by `prewarm` along with the sender's one.
+    pub fn with_endpoints(mut self, endpoints: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.endpoints = endpoints.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Warms up the endpoints with a `getHealth` request, recording their latencies ordered from the fastest.
+    /// Unresponsive endpoints are left out, an error is returned only if none of them responded.
+    pub fn prewarm(&self) -> ClientResult<Vec<(String, Duration)>> {
+        let mut latencies = Vec::new();
+        let mut last_error = None;
+
+        for url in self
+            .http_sender()
+            .map(HttpSender::url)
+            .into_iter()
+            .chain(self.endpoints.iter().cloned())
+        {
+            let started = Instant::now();
+            match self.send_once_to(&url, RpcRequest::GetHealth, Value::Null) {
+                Ok(_) => latencies.push((url, started.elapsed())),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        if let (true, Some(err)) = (latencies.is_empty(), last_error) {
+            return Err(err);
+        }
+
+        latencies.sort_by_key(|(_, latency)| *latency);
+        *self.endpoint_latencies.write().unwrap() = latencies.clone();
+        Ok(latencies)
+    }
+
+    /// Sends the request to the endpoint at `url` with the configuration of the client's sender, see
+    /// `HttpSender::send_to`. A client without a known sender has no configuration to reuse, the request is sent
+    /// with the default one.
+    fn send_to(&self, url: &str, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        match self.http_sender() {
+            Some(http_sender) => http_sender.send_to(url, request, params),
+            None => HttpSender::new(url).send(request, params),
+        }
+    }
+
+    /// Sends the request once to the endpoint at `url`, see `send_to`.
+    fn send_once_to(&self, url: &str, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        match self.http_sender() {
+            Some(http_sender) => http_sender.send_once_to(url, request, params),
+            None => HttpSender::new(url).send_once(request, params),
+        }
+    }
+
+    /// Returns the endpoint latencies recorded by the last `prewarm`, ordered from the fastest.
+    pub fn endpoint_latencies(&self) -> Vec<(String, Duration)> {
+        self.endpoint_latencies.read().unwrap().clone()
+    }
+
+    /// Sets the decoder used by the account data helpers.
+    pub fn with_data_decoder(mut self, data_decoder: impl DataDecoder + 'static) -> Self {
+        self.data_decoder = Box::new(data_decoder);
+        self
+    }
+
+    /// Sets the encoding the account data is requested with once more when it fails to decode in the requested one,
+    /// `base64` by default.
+    pub fn with_fallback_account_encoding(mut self, encoding: Option<UiAccountEncoding>) -> Self {
+        self.fallback_account_encoding = encoding;
+        self
+    }
+
+    /// Sets the encoding of transactions sent over the wire, `base58` or `base64`, instead of the inner client's
+    /// default.
+    pub fn with_tx_wire_encoding(mut self, encoding: UiTransactionEncoding) -> Self {
+        self.tx_wire_encoding = Some(encoding);
+        self
+    }
+
+    /// Sets the percentile of the recent prioritization fees paid by the transactions the client builds, e.g. the
+    /// nonce, token and lookup table ones. Their compute budget is then set by `add_compute_budget` before
+    /// signing, `None` (the default) leaving the instructions as they are.
+    pub fn with_priority_fee_percentile(mut self, percentile: Option<u8>) -> Self {
+        self.priority_fee_percentile = percentile;
+        self
+    }
+
+    /// Starts building a JSON-RPC batch, sent in a single HTTP request through the client's sender.
+    pub fn batch(&self) -> RpcBatch<'_> {
+        RpcBatch {
+            client: self,
+            requests: Vec::new(),
+        }
+    }
+
+    /// Sets the callback receiving the confirmed and finalized states of an account read by
+    /// `get_account_finalized_safe` when they differ.
+    pub fn with_on_commitment_mismatch(
+        mut self,
+        on_commitment_mismatch: impl Fn(&Pubkey, Option<&Account>, Option<&Account>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_commitment_mismatch = Some(Box::new(on_commitment_mismatch));
+        self
+    }
+
+    /// Sets the commitment used to fetch the blockhash confirming durable-nonce transactions.
+    pub fn with_nonce_blockhash_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.nonce_blockhash_commitment = commitment;
+        self
     }
 
     pub fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
-        const SEND_RETRIES: usize = 1;
-        const GET_STATUS_RETRIES: usize = usize::MAX;
+        self.send_and_confirm_with(|| self.send_tracked(transaction))
+    }
 
-        'sending: for _ in 0..SEND_RETRIES {
-            let signature = self.send_transaction(transaction)?;
+    /// Sends the transaction with the `options` and confirms it, following the same logic as
+    /// `send_and_confirm_transaction`.
+    pub fn send_and_confirm_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        options: SendTransactionOptions,
+    ) -> ClientResult<Signature> {
+        self.send_and_confirm_with(|| self.send_tracked_with_options(transaction, options))
+    }
 
-            let recent_blockhash = if uses_durable_nonce(transaction).is_some() {
-                let (recent_blockhash, ..) = self.get_latest_blockhash_with_commitment(CommitmentConfig::processed())?;
-                recent_blockhash
-            } else {
-                transaction.message.recent_blockhash
-            };
+    /// Sends and confirms the versioned transaction, e.g. a v0 one using address lookup tables, following the same
+    /// logic as `send_and_confirm_transaction`.
+    pub fn send_and_confirm_versioned_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> ClientResult<Signature> {
+        self.send_and_confirm_with(|| self.send_versioned_tracked(transaction))
+    }
 
-            for status_retry in 0..GET_STATUS_RETRIES {
-                match self.get_signature_status(&signature)? {
-                    Some(Ok(_)) => return Ok(signature),
-                    Some(Err(e)) => return Err(e.into()),
-                    None => {
-                        if !self.is_blockhash_valid(&recent_blockhash, CommitmentConfig::processed())? {
-                            // Block hash is not found by some reason
-                            break 'sending;
-                        } else if cfg!(not(test))
-                            // Ignore sleep at last step.
-                            && status_retry < GET_STATUS_RETRIES
-                        {
-                            // Retry twice a second
-                            let delay_millis = 500;
-
-                            #[cfg(feature = "laplace_sleep")]
-                            laplace_wasm::sleep::invoke(delay_millis);
-
-                            #[cfg(not(feature = "laplace_sleep"))]
-                            std::thread::sleep(std::time::Duration::from_millis(delay_millis));
-
-                            continue;
+    /// Same as `send_and_confirm_versioned_transaction`.
+    pub fn send_and_confirm_versioned(&self, transaction: &VersionedTransaction) -> ClientResult<Signature> {
+        self.send_and_confirm_versioned_transaction(transaction)
+    }
+
+    /// Simulates the versioned transaction without verifying its signatures, returning the post-simulation state of
+    /// all the accounts it uses, including those loaded from address lookup tables.
+    pub fn simulate_versioned_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> ClientResult<Response<RpcSimulateTransactionResult>> {
+        let mut addresses: Vec<String> = match &transaction.message {
+            VersionedMessage::Legacy(message) => &message.account_keys,
+            VersionedMessage::V0(message) => &message.account_keys,
+        }
+        .iter()
+        .map(Pubkey::to_string)
+        .collect();
+        addresses.extend(self.loaded_addresses(transaction)?.iter().map(Pubkey::to_string));
+
+        self.send(
+            RpcRequest::SimulateTransaction,
+            json!([encode_transaction(transaction, UiTransactionEncoding::Base64)?, {
+                "encoding": UiTransactionEncoding::Base64,
+                "commitment": self.commitment().commitment,
+                "sigVerify": false,
+                "accounts": {
+                    "encoding": UiAccountEncoding::Base64,
+                    "addresses": addresses,
+                },
+            }]),
+        )
+    }
+
+    /// Simulates the transaction with its blockhash replaced by the latest one and without verifying its
+    /// signatures, and returns a report of the simulation with the lamport balance changes of the `accounts`.
+    pub fn simulate_with_report(
+        &self,
+        transaction: &Transaction,
+        accounts: &[Pubkey],
+    ) -> ClientResult<SimulationReport> {
+        let pre_lamports: Vec<_> = if accounts.is_empty() {
+            Vec::new()
+        } else {
+            self.get_multiple_accounts(accounts)?
+                .iter()
+                .map(|account| account.as_ref().map_or(0, |account| account.lamports))
+                .collect()
+        };
+
+        let addresses: Vec<_> = accounts.iter().map(Pubkey::to_string).collect();
+        let response: Response<Value> = self.send(
+            RpcRequest::SimulateTransaction,
+            json!([encode_transaction(transaction, UiTransactionEncoding::Base64)?, {
+                "encoding": UiTransactionEncoding::Base64,
+                "commitment": self.commitment().commitment,
+                "sigVerify": false,
+                "replaceRecentBlockhash": true,
+                "accounts": {
+                    "encoding": UiAccountEncoding::Base64,
+                    "addresses": addresses,
+                },
+            }]),
+        )?;
+        let result = response.value;
+
+        let error: Option<TransactionError> = serde_json::from_value(result["err"].clone())
+            .map_err(|err| RpcError::ParseError(format!("invalid simulation error: {}", err)))?;
+        let instruction_error = match &error {
+            Some(TransactionError::InstructionError(index, err)) => Some((*index, err.clone())),
+            _ => None,
+        };
+        let logs = result["logs"]
+            .as_array()
+            .map(|logs| logs.iter().filter_map(|log| log.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let balance_changes = accounts
+            .iter()
+            .zip(pre_lamports)
+            .enumerate()
+            .map(|(index, (pubkey, pre_lamports))| BalanceChange {
+                pubkey: *pubkey,
+                pre_lamports,
+                // Accounts are not returned when the simulation fails
+                post_lamports: match &result["accounts"][index] {
+                    Value::Null => {
+                        if error.is_some() {
+                            pre_lamports
+                        } else {
+                            0
                         }
                     },
-                }
+                    account => account["lamports"].as_u64().unwrap_or(0),
+                },
+            })
+            .collect();
+
+        Ok(SimulationReport {
+            error,
+            instruction_error,
+            units_consumed: result["unitsConsumed"].as_u64(),
+            logs,
+            balance_changes,
+        })
+    }
+
+    /// Returns the `percentile` (0 to 100) of the prioritization fees paid in the recent slots for locking the
+    /// `writable_accounts`, in micro-lamports per compute unit.
+    pub fn estimate_priority_fee(&self, writable_accounts: &[Pubkey], percentile: u8) -> ClientResult<u64> {
+        let fees = fees::get_recent_prioritization_fees_for_accounts(&self.client, writable_accounts)?;
+        Ok(fees::fee_percentile(&fees, percentile))
+    }
+
+    /// Simulates a transaction of the `instructions` paid by `payer` with the maximum compute unit limit, and
+    /// returns the compute units it consumed.
+    pub fn simulate_compute_units(&self, instructions: &[Instruction], payer: &Pubkey) -> ClientResult<u64> {
+        let instructions = with_compute_budget(instructions, MAX_COMPUTE_UNIT_LIMIT, 0);
+        let transaction = Transaction::new_unsigned(Message::new(&instructions, Some(payer)));
+        let response: Response<Value> = self.send(
+            RpcRequest::SimulateTransaction,
+            json!([encode_transaction(&transaction, UiTransactionEncoding::Base64)?, {
+                "encoding": UiTransactionEncoding::Base64,
+                "commitment": self.commitment().commitment,
+                "sigVerify": false,
+                "replaceRecentBlockhash": true,
+            }]),
+        )?;
+
+        if !response.value["err"].is_null() {
+            return Err(RpcError::ForUser(format!("transaction simulation failed: {}", response.value["err"])).into());
+        }
+        response.value["unitsConsumed"]
+            .as_u64()
+            .ok_or_else(|| RpcError::ParseError("simulation result has no consumed compute units".to_string()).into())
+    }
+
+    /// Returns the `instructions` with compute budget instructions, see `fees::with_compute_budget`. The compute unit
+    /// limit is the units consumed in simulation with a margin, the price is the `percentile` of the recent
+    /// prioritization fees paid for the accounts the instructions write.
+    pub fn add_compute_budget(
+        &self,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        percentile: u8,
+    ) -> ClientResult<Vec<Instruction>> {
+        let units = self.simulate_compute_units(instructions, payer)?;
+        let units = (units + units * COMPUTE_UNIT_LIMIT_MARGIN_PERCENT / 100).min(u64::from(MAX_COMPUTE_UNIT_LIMIT));
+
+        let mut writable_accounts = vec![*payer];
+        for account in instructions.iter().flat_map(|instruction| &instruction.accounts) {
+            if account.is_writable && !writable_accounts.contains(&account.pubkey) {
+                writable_accounts.push(account.pubkey);
+            }
+        }
+        let micro_lamports = self.estimate_priority_fee(&writable_accounts, percentile)?;
+
+        Ok(with_compute_budget(instructions, units as u32, micro_lamports))
+    }
+
+    /// Resolves the addresses the v0 transaction loads from address lookup tables, the writable ones first, in the
+    /// order the runtime appends them to the static account keys. Legacy transactions load none.
+    pub fn loaded_addresses(&self, transaction: &VersionedTransaction) -> ClientResult<Vec<Pubkey>> {
+        let lookups = match &transaction.message {
+            VersionedMessage::Legacy(_) => return Ok(Vec::new()),
+            VersionedMessage::V0(message) => &message.address_table_lookups,
+        };
+        if lookups.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let table_keys: Vec<_> = lookups.iter().map(|lookup| lookup.account_key).collect();
+        let tables_addresses: Vec<_> = self
+            .get_lookup_tables(&table_keys)?
+            .into_iter()
+            .map(|table| table.addresses)
+            .collect();
+
+        let lookup_address = |table_index: usize, address_index: u8| {
+            tables_addresses[table_index]
+                .get(address_index as usize)
+                .copied()
+                .ok_or_else(|| {
+                    ClientError::from(ClientErrorKind::Custom(format!(
+                        "address lookup table {} has no address at index {}",
+                        table_keys[table_index], address_index
+                    )))
+                })
+        };
+        let mut writable = Vec::new();
+        let mut readonly = Vec::new();
+        for (table_index, lookup) in lookups.iter().enumerate() {
+            for &address_index in &lookup.writable_indexes {
+                writable.push(lookup_address(table_index, address_index)?);
+            }
+            for &address_index in &lookup.readonly_indexes {
+                readonly.push(lookup_address(table_index, address_index)?);
             }
         }
+        writable.extend(readonly);
+        Ok(writable)
+    }
 
-        Err(RpcError::ForUser(
-            "unable to confirm transaction. \
-             This can happen in situations such as transaction expiration \
-             and insufficient fee-payer funds"
-                .to_string(),
+    /// Sends the transaction and waits for it to reach the `commitment`, reporting the confirmation progress to
+    /// `on_progress` after each status poll.
+    pub fn send_and_confirm_transaction_with_commitment(
+        &self,
+        transaction: &Transaction,
+        commitment: CommitmentConfig,
+        mut on_progress: Option<&mut dyn FnMut(ConfirmationProgress)>,
+    ) -> ClientResult<Signature> {
+        self.send_and_confirm_until(
+            || self.send_tracked(transaction),
+            |tracked, deadline| self.wait_for_commitment(tracked, commitment, deadline, &mut on_progress),
         )
+    }
+
+    fn send_and_confirm_with(&self, send: impl Fn() -> ClientResult<TrackedSignature>) -> ClientResult<Signature> {
+        self.send_and_confirm_until(send, |tracked, deadline| {
+            self.wait_for_confirmation(&tracked.signature, &tracked.recent_blockhash, deadline)
+        })
+    }
+
+    /// Sends the transaction until `confirm` returns `true`, resending it after the blockhash expires if send
+    /// retries are configured.
+    fn send_and_confirm_until(
+        &self,
+        send: impl Fn() -> ClientResult<TrackedSignature>,
+        mut confirm: impl FnMut(&TrackedSignature, Option<Instant>) -> ClientResult<bool>,
+    ) -> ClientResult<Signature> {
+        let deadline = self
+            .overall_deadline
+            .map(|overall_deadline| Instant::now() + overall_deadline);
+        for send_retry in 0..self.send_retries.max(1) {
+            if send_retry > 0 {
+                self.sleep(self.resend_delay);
+            }
+            self.check_overall_deadline(deadline)?;
+
+            let tracked = match send() {
+                Ok(tracked) => tracked,
+                Err(_) if send_retry + 1 < self.send_retries => continue,
+                Err(err) => return Err(err),
+            };
+            let confirmed = confirm(&tracked, deadline);
+            self.settle_pending(&tracked.signature, &confirmed);
+            if confirmed? {
+                return Ok(tracked.signature);
+            }
+        }
+
+        Err(unable_to_confirm_error())
+    }
+
+    /// Polls the status of the tracked transaction until it reaches the `commitment`. Returns `false` if the
+    /// blockhash expired before the transaction was processed.
+    fn wait_for_commitment(
+        &self,
+        tracked: &TrackedSignature,
+        commitment: CommitmentConfig,
+        deadline: Option<Instant>,
+        on_progress: &mut Option<&mut dyn FnMut(ConfirmationProgress)>,
+    ) -> ClientResult<bool> {
+        let started = Instant::now();
+        for poll in 0..FINALIZE_STATUS_RETRIES {
+            self.check_overall_deadline(deadline)?;
+            let status = self.get_transaction_status(&tracked.signature)?;
+            self.observe(|| RpcEvent::ConfirmationPoll {
+                signature: tracked.signature,
+                poll,
+                processed: status.is_some(),
+            });
+            if let Some(on_progress) = on_progress {
+                on_progress(ConfirmationProgress {
+                    signature: tracked.signature,
+                    confirmation_status: status.as_ref().and_then(|status| status.confirmation_status.clone()),
+                    confirmations: status.as_ref().and_then(|status| status.confirmations),
+                    slot: status.as_ref().map(|status| status.slot),
+                    elapsed: started.elapsed(),
+                });
+            }
+
+            match status {
+                Some(TransactionStatus { err: Some(err), .. }) if self.attach_logs_on_failure => {
+                    return Err(self.record_failure_logs(&tracked.signature, err))
+                },
+                Some(TransactionStatus { err: Some(err), .. }) => return Err(err.into()),
+                Some(status) if status.satisfies_commitment(commitment) => return Ok(true),
+                // Once processed, the transaction no longer depends on the blockhash validity
+                Some(_) => {},
+                None => {
+                    if !self.is_blockhash_valid(&tracked.recent_blockhash, CommitmentConfig::processed())? {
+                        return Ok(false);
+                    }
+                },
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+
+        Err(RpcError::ForUser(format!(
+            "transaction {} did not reach the {:?} commitment",
+            tracked.signature, commitment.commitment
+        ))
         .into())
     }
-}
 
-impl Deref for WasmRpcClient {
-    type Target = RpcClient;
+    /// Sends and confirms the transaction like `send_and_confirm_transaction`, but treats the send being rejected as
+    /// already processed as success and reports it in the outcome, e.g. when resending a broadcast transaction.
+    pub fn send_and_confirm_detecting_duplicate(&self, transaction: &Transaction) -> ClientResult<ConfirmOutcome> {
+        let (tracked, was_already_processed) = match (self.send_tracked(transaction), transaction.signatures.first()) {
+            (Ok(tracked), _) => (tracked, false),
+            (Err(err), Some(signature)) if is_already_processed(&err) => {
+                let tracked = TrackedSignature {
+                    signature: *signature,
+                    recent_blockhash: self.lifetime_blockhash(transaction)?,
+                };
+                (tracked, true)
+            },
+            (Err(err), _) => return Err(err),
+        };
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        tracked.confirm(self).map(|signature| ConfirmOutcome {
+            signature,
+            was_already_processed,
+        })
     }
-}
 
-impl DerefMut for WasmRpcClient {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+    /// Sets the token cancelling the confirmation loops of the client. Cancellation is checked between the
+    /// requests, like the overall deadline.
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Sets the ledger remembering the sent transactions until their confirmation is settled. While a transaction is
+    /// pending in the ledger, sending it once more fails with an `AlreadyPending:` error, its confirmation should be
+    /// resumed with `resume_confirmation` instead.
+    pub fn with_signature_ledger(mut self, signature_ledger: Arc<SignatureLedger>) -> Self {
+        self.signature_ledger = Some(signature_ledger);
+        self
+    }
+
+    pub fn signature_ledger(&self) -> Option<&SignatureLedger> {
+        self.signature_ledger.as_deref()
+    }
+
+    /// Resumes the interrupted confirmation of the transaction pending in the signature ledger, e.g. after a restart
+    /// of the lapp.
+    pub fn resume_confirmation(&self, signature: &Signature) -> ClientResult<Signature> {
+        let pending = self
+            .signature_ledger
+            .as_ref()
+            .and_then(|signature_ledger| signature_ledger.get(signature))
+            .ok_or_else(|| RpcError::ForUser(format!("transaction {} is not pending confirmation", signature)))?;
+
+        let deadline = self
+            .overall_deadline
+            .map(|overall_deadline| Instant::now() + overall_deadline);
+        let confirmed = self.wait_for_confirmation(&pending.signature, &pending.recent_blockhash, deadline);
+        self.settle_pending(signature, &confirmed);
+        if confirmed? {
+            Ok(pending.signature)
+        } else {
+            Err(unable_to_confirm_error())
+        }
+    }
+
+    /// Sends and confirms the transaction like `send_and_confirm_transaction`, even if it is pending in the
+    /// signature ledger. The transaction may be executed twice if the pending one is still to land.
+    pub fn send_and_confirm_transaction_forced(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        if let (Some(signature_ledger), Some(signature)) = (&self.signature_ledger, transaction.signatures.first()) {
+            signature_ledger.remove(signature);
+        }
+        self.send_and_confirm_transaction(transaction)
+    }
+
+    /// Sets the observer receiving the structured events of the confirmation flow. A client created with
+    /// `with_http_sender` shares the observer of the sender by default.
+    pub fn with_observer(mut self, observer: Arc<dyn RpcEventObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    fn observe(&self, event: impl FnOnce() -> RpcEvent) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event());
+        }
+    }
+
+    /// Reports the send error to the observer if it is a preflight failure.
+    fn observe_send_error(&self, err: ClientError) -> ClientError {
+        if let ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            message,
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) = err.kind()
+        {
+            self.observe(|| RpcEvent::PreflightFailure {
+                message: message.clone(),
+                error: result.err.as_ref().map(ToString::to_string),
+                logs: result.logs.clone().unwrap_or_default(),
+            });
+        }
+        err
+    }
+
+    /// Fails if the transaction with the `signature` is pending in the signature ledger.
+    fn check_not_pending(&self, signature: Option<&Signature>) -> ClientResult<()> {
+        match (&self.signature_ledger, signature) {
+            (Some(signature_ledger), Some(signature)) if signature_ledger.is_pending(signature) => {
+                Err(ClientErrorKind::Custom(format!(
+                    "AlreadyPending: transaction {} was sent and is not confirmed yet",
+                    signature
+                ))
+                .into())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn record_pending(&self, tracked: TrackedSignature) -> TrackedSignature {
+        if let Some(signature_ledger) = &self.signature_ledger {
+            signature_ledger.insert(tracked.into());
+        }
+        tracked
+    }
+
+    /// Forgets the pending transaction once it is confirmed, failed, or can no longer land after its blockhash
+    /// expired. It stays pending if the confirmation was interrupted by another error.
+    pub(crate) fn settle_pending(&self, signature: &Signature, confirmed: &ClientResult<bool>) {
+        if let Some(signature_ledger) = &self.signature_ledger {
+            let settled = match confirmed {
+                Ok(_) => true,
+                Err(err) => matches!(err.kind(), ClientErrorKind::TransactionError(_)),
+            };
+            if settled {
+                signature_ledger.remove(signature);
+            }
+        }
+    }
+
+    /// Fails if the confirmation was cancelled or the overall `deadline` passed.
+    pub(crate) fn check_overall_deadline(&self, deadline: Option<Instant>) -> ClientResult<()> {
+        if let Some(cancellation_token) = &self.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(ClientErrorKind::Custom("Cancelled: the confirmation was cancelled".to_string()).into());
+            }
+        }
+
+        match (deadline, self.overall_deadline) {
+            (Some(deadline), Some(overall_deadline)) if Instant::now() >= deadline => Err(RpcError::ForUser(format!(
+                "unable to confirm transaction within the overall deadline of {:?}",
+                overall_deadline
+            ))
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the transaction with the latest blockhash by `build`, then sends and confirms it. If the preflight
+    /// reports the blockhash as not found, which happens while it is not yet propagated, the transaction is rebuilt
+    /// with a refreshed blockhash and resent.
+    pub fn send_and_confirm_with_rebuild(
+        &self,
+        mut build: impl FnMut(Hash) -> ClientResult<Transaction>,
+    ) -> ClientResult<Signature> {
+        let mut retry = 0;
+        loop {
+            let transaction = build(self.get_cached_blockhash()?)?;
+
+            match self.send_and_confirm_transaction(&transaction) {
+                Err(err) if is_blockhash_not_found(&err) && retry < self.blockhash_not_found_retries => {
+                    self.invalidate_cached_blockhash();
+                    retry += 1;
+                    self.sleep(STATUS_POLL_INTERVAL);
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends and confirms the signed transaction. If its blockhash expires before the transaction is processed, the
+    /// transaction is re-signed by the `signers` with a fresh blockhash and resent, up to the configured number of
+    /// attempts, see `with_resign_attempts`.
+    pub fn send_and_confirm_with_signers<T: Signers>(
+        &self,
+        transaction: &mut Transaction,
+        signers: &T,
+    ) -> ClientResult<Signature> {
+        self.send_and_confirm_with_resign(transaction, |transaction, blockhash| {
+            transaction
+                .try_sign(signers, blockhash)
+                .map_err(|err| ClientErrorKind::SigningError(err).into())
+        })
+    }
+
+    /// Same as `send_and_confirm_with_signers`, re-signing the transaction with a fresh blockhash by `resign`.
+    /// Durable-nonce transactions don't expire with the blockhash, so they are resent without re-signing.
+    pub fn send_and_confirm_with_resign(
+        &self,
+        transaction: &mut Transaction,
+        mut resign: impl FnMut(&mut Transaction, Hash) -> ClientResult<()>,
+    ) -> ClientResult<Signature> {
+        let deadline = self
+            .overall_deadline
+            .map(|overall_deadline| Instant::now() + overall_deadline);
+        let is_durable_nonce = uses_durable_nonce(transaction).is_some();
+        for attempt in 0..=self.resign_attempts {
+            self.check_overall_deadline(deadline)?;
+            let tracked = if attempt == 0 {
+                self.send_tracked(transaction)?
+            } else if is_durable_nonce {
+                // Resent with the same signature, which is still pending since the transaction may yet land
+                self.send_tracked_unchecked(transaction)?
+            } else {
+                self.invalidate_cached_blockhash();
+                resign(transaction, self.get_cached_blockhash()?)?;
+                self.send_tracked(transaction)?
+            };
+
+            let confirmed = self.wait_for_confirmation(&tracked.signature, &tracked.recent_blockhash, deadline);
+            if is_durable_nonce && matches!(confirmed, Ok(false)) {
+                continue;
+            }
+            self.settle_pending(&tracked.signature, &confirmed);
+            if confirmed? {
+                return Ok(tracked.signature);
+            }
+        }
+
+        Err(unable_to_confirm_error())
+    }
+
+    /// Builds a transaction of the `instructions` paid by `payer`, with the compute budget if a priority fee
+    /// percentile is set, signs it by the `signers` with the latest blockhash, then sends and confirms it.
+    pub(crate) fn sign_and_confirm<T: Signers>(
+        &self,
+        signers: &T,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> ClientResult<Signature> {
+        let instructions = match self.priority_fee_percentile {
+            Some(percentile) => self.add_compute_budget(instructions, payer, percentile)?,
+            None => instructions.to_vec(),
+        };
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(payer));
+        transaction
+            .try_sign(signers, self.get_cached_blockhash()?)
+            .map_err(ClientErrorKind::SigningError)?;
+        self.send_and_confirm_transaction(&transaction)
+    }
+
+    /// Sends and confirms the transaction, then fetches the listed `accounts` in a single request.
+    pub fn send_confirm_and_fetch(
+        &self,
+        transaction: &Transaction,
+        accounts: &[Pubkey],
+    ) -> ClientResult<(Signature, Vec<Option<Account>>)> {
+        let signature = self.send_and_confirm_transaction(transaction)?;
+        let accounts = self.get_multiple_accounts(accounts)?;
+        Ok((signature, accounts))
+    }
+
+    /// Sends and confirms the transaction, then returns the compute units it consumed.
+    pub fn send_confirm_and_units(&self, transaction: &Transaction) -> ClientResult<(Signature, u64)> {
+        let signature = self.send_and_confirm_for_history(transaction)?;
+        let meta = self.get_transaction_raw_meta(&signature)?;
+        let units = meta["computeUnitsConsumed"].as_u64().ok_or_else(|| {
+            RpcError::ForUser(format!("transaction {} meta has no consumed compute units", signature))
+        })?;
+        Ok((signature, units))
+    }
+
+    /// Sends and confirms the transaction, then returns the fee it was charged, in lamports.
+    pub fn send_confirm_and_fee(&self, transaction: &Transaction) -> ClientResult<(Signature, u64)> {
+        let signature = self.send_and_confirm_for_history(transaction)?;
+        let meta = self.get_transaction_raw_meta(&signature)?;
+        let fee = meta["fee"]
+            .as_u64()
+            .ok_or_else(|| RpcError::ForUser(format!("transaction {} meta has no fee", signature)))?;
+        Ok((signature, fee))
+    }
+
+    /// Sends and confirms the transaction, then computes the changes of the token balances it touched from
+    /// the pre and post token balances of its meta.
+    pub fn send_confirm_and_token_deltas(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<(Signature, Vec<TokenBalanceDelta>)> {
+        let signature = self.send_and_confirm_for_history(transaction)?;
+        let meta = self.get_transaction_raw_meta(&signature)?;
+
+        let mut deltas: Vec<TokenBalanceDelta> = Vec::new();
+        for (balances, sign) in [(&meta["preTokenBalances"], -1), (&meta["postTokenBalances"], 1)] {
+            for balance in balances.as_array().into_iter().flatten() {
+                let parse_error = || {
+                    RpcError::ParseError(format!(
+                        "transaction {} has an invalid token balance {}",
+                        signature, balance
+                    ))
+                };
+                let account_index = balance["accountIndex"].as_u64().ok_or_else(parse_error)? as usize;
+                let mint = balance["mint"]
+                    .as_str()
+                    .and_then(|mint| mint.parse().ok())
+                    .ok_or_else(parse_error)?;
+                let amount: i128 = balance["uiTokenAmount"]["amount"]
+                    .as_str()
+                    .and_then(|amount| amount.parse().ok())
+                    .ok_or_else(parse_error)?;
+
+                let position = deltas
+                    .iter()
+                    .position(|delta| delta.account_index == account_index && delta.mint == mint);
+                let delta = match position {
+                    Some(position) => &mut deltas[position],
+                    None => {
+                        deltas.push(TokenBalanceDelta {
+                            account_index,
+                            account: transaction.message.account_keys.get(account_index).copied(),
+                            mint,
+                            owner: balance["owner"].as_str().and_then(|owner| owner.parse().ok()),
+                            decimals: balance["uiTokenAmount"]["decimals"].as_u64().unwrap_or_default() as u8,
+                            delta: 0,
+                        });
+                        deltas.last_mut().unwrap()
+                    },
+                };
+                delta.delta += sign * amount;
+            }
+        }
+        Ok((signature, deltas))
+    }
+
+    /// Sends and confirms the transaction, then waits until it is available for history queries, which don't serve
+    /// transactions at the processed commitment.
+    fn send_and_confirm_for_history(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let signature = self.send_and_confirm_transaction(transaction)?;
+        self.confirm_signature_with_commitment(&signature, CommitmentConfig::confirmed())?;
+        Ok(signature)
+    }
+
+    /// Checks that the `transaction` is paid by `expected_fee_payer` and carries valid signatures of all its required
+    /// signers.
+    pub fn validate_transaction(&self, transaction: &Transaction, expected_fee_payer: &Pubkey) -> ClientResult<()> {
+        let message = &transaction.message;
+        match message.account_keys.first() {
+            Some(fee_payer) if fee_payer == expected_fee_payer => (),
+            Some(fee_payer) => {
+                return Err(RpcError::ForUser(format!(
+                    "transaction fee payer is {}, expected {}",
+                    fee_payer, expected_fee_payer
+                ))
+                .into())
+            },
+            None => return Err(RpcError::ForUser("transaction has no fee payer".to_string()).into()),
+        }
+
+        let required_signatures = message.header.num_required_signatures as usize;
+        if transaction.signatures.len() != required_signatures {
+            return Err(RpcError::ForUser(format!(
+                "transaction has {} signatures, {} are required",
+                transaction.signatures.len(),
+                required_signatures
+            ))
+            .into());
+        }
+        if let Some(index) = transaction
+            .signatures
+            .iter()
+            .position(|signature| *signature == Signature::default())
+        {
+            return Err(match message.account_keys.get(index) {
+                Some(signer) => RpcError::ForUser(format!("transaction is not signed by {}", signer)),
+                None => RpcError::ForUser(format!(
+                    "transaction requires {} signatures but has only {} account keys",
+                    required_signatures,
+                    message.account_keys.len()
+                )),
+            }
+            .into());
+        }
+        transaction.verify()?;
+
+        Ok(())
+    }
+
+    /// Returns the required signers of the `transaction` that haven't signed it with a valid signature yet.
+    pub fn missing_signers(&self, transaction: &Transaction) -> Vec<Pubkey> {
+        let message_data = transaction.message_data();
+        let required_signatures = transaction.message.header.num_required_signatures as usize;
+
+        transaction
+            .message
+            .account_keys
+            .iter()
+            .take(required_signatures)
+            .enumerate()
+            .filter(|(index, signer)| {
+                !transaction
+                    .signatures
+                    .get(*index)
+                    .map_or(false, |signature| signature.verify(signer.as_ref(), &message_data))
+            })
+            .map(|(_, signer)| *signer)
+            .collect()
+    }
+
+    /// Sends the transaction to the sender's endpoint and all the additional ones, returning its signature if any
+    /// of them accepted it. The requests are made in turn, since the transport is blocking, and the endpoints
+    /// reporting the transaction as already processed are counted as accepting it.
+    pub fn broadcast_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let mut accepted = None;
+        let mut first_error = None;
+
+        let primary = self.send_transaction_with_config(transaction, self.send_transaction_config());
+        let others = self.endpoints.iter().map(|url| {
+            let config = self.send_transaction_config();
+            let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Base64);
+            let params = json!([encode_transaction(transaction, encoding)?, RpcSendTransactionConfig {
+                encoding: Some(encoding),
+                ..config
+            }]);
+            let signature = self.send_to(url, RpcRequest::SendTransaction, params)?;
+            serde_json::from_value::<String>(signature)
+                .map_err(|err| {
+                    ClientError::new_with_request(ClientErrorKind::SerdeJson(err), RpcRequest::SendTransaction)
+                })
+                .and_then(|signature| parse_signature(&signature))
+        });
+        for result in iter::once(primary).chain(others) {
+            match (result, transaction.signatures.first()) {
+                (Ok(signature), _) => {
+                    accepted.get_or_insert(signature);
+                },
+                (Err(err), Some(signature)) if is_already_processed(&err) => {
+                    accepted.get_or_insert(*signature);
+                },
+                (Err(err), _) => {
+                    first_error.get_or_insert(err);
+                },
+            }
+        }
+
+        match (accepted, first_error) {
+            (Some(signature), _) => Ok(signature),
+            (None, Some(err)) => Err(err),
+            (None, None) => Err(RpcError::ForUser("no endpoint to broadcast the transaction to".to_string()).into()),
+        }
+    }
+
+    /// Sends the transaction and returns immediately with a handle that can be confirmed later.
+    pub fn send_tracked(&self, transaction: &Transaction) -> ClientResult<TrackedSignature> {
+        self.check_not_pending(transaction.signatures.first())?;
+        self.send_tracked_unchecked(transaction)
+    }
+
+    /// Sends the transaction like `send_tracked`, even if it is pending in the signature ledger.
+    fn send_tracked_unchecked(&self, transaction: &Transaction) -> ClientResult<TrackedSignature> {
+        let signature = self
+            .send_transaction_with_config(transaction, self.send_transaction_config())
+            .map_err(|err| self.observe_send_error(err))?;
+        Ok(self.record_pending(TrackedSignature {
+            signature,
+            recent_blockhash: self.lifetime_blockhash(transaction)?,
+        }))
+    }
+
+    /// Sends the transaction with the `options` and returns a handle that can be confirmed later.
+    pub fn send_tracked_with_options(
+        &self,
+        transaction: &Transaction,
+        options: SendTransactionOptions,
+    ) -> ClientResult<TrackedSignature> {
+        self.check_not_pending(transaction.signatures.first())?;
+        let signature = self.send_encoded_transaction(transaction, options)?;
+        Ok(self.record_pending(TrackedSignature {
+            signature,
+            recent_blockhash: self.lifetime_blockhash(transaction)?,
+        }))
+    }
+
+    /// Sends the transaction serialized in the wire encoding of the client.
+    fn send_encoded_transaction(
+        &self,
+        transaction: &impl Serialize,
+        options: SendTransactionOptions,
+    ) -> ClientResult<Signature> {
+        let signature: String = self
+            .send(
+                RpcRequest::SendTransaction,
+                self.send_transaction_params(transaction, options)?,
+            )
+            .map_err(|err| self.observe_send_error(err))?;
+        parse_signature(&signature)
+    }
+
+    /// Returns the `sendTransaction` params of the transaction serialized in the wire encoding of the client.
+    fn send_transaction_params(
+        &self,
+        transaction: &impl Serialize,
+        options: SendTransactionOptions,
+    ) -> ClientResult<Value> {
+        let encoding = self.tx_wire_encoding.unwrap_or(UiTransactionEncoding::Base64);
+        let mut config = json!({
+            "encoding": encoding,
+            "skipPreflight": options.skip_preflight,
+            "preflightCommitment": options.preflight_commitment.unwrap_or(self.commitment().commitment),
+        });
+        if let Some(max_retries) = options.max_retries {
+            config["maxRetries"] = json!(max_retries);
+        }
+        if let Some(min_context_slot) = options.min_context_slot {
+            config["minContextSlot"] = json!(min_context_slot);
+        }
+        Ok(json!([encode_transaction(transaction, encoding)?, config]))
+    }
+
+    /// Sends the versioned transaction and returns a handle that can be confirmed later.
+    pub fn send_versioned_tracked(&self, transaction: &VersionedTransaction) -> ClientResult<TrackedSignature> {
+        self.check_not_pending(transaction.signatures.first())?;
+        let signature = self.send_encoded_transaction(transaction, SendTransactionOptions::default())?;
+
+        let (recent_blockhash, account_keys, instructions) = match &transaction.message {
+            VersionedMessage::Legacy(message) => {
+                (message.recent_blockhash, &message.account_keys, &message.instructions)
+            },
+            VersionedMessage::V0(message) => (message.recent_blockhash, &message.account_keys, &message.instructions),
+        };
+        let recent_blockhash = if uses_durable_nonce_instruction(account_keys, instructions.first()) {
+            let (recent_blockhash, ..) = self.get_latest_blockhash_with_commitment(self.nonce_blockhash_commitment)?;
+            recent_blockhash
+        } else {
+            recent_blockhash
+        };
+
+        Ok(self.record_pending(TrackedSignature {
+            signature,
+            recent_blockhash,
+        }))
+    }
+
+    fn send_transaction_config(&self) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            preflight_commitment: Some(self.commitment().commitment),
+            encoding: self.tx_wire_encoding,
+            ..RpcSendTransactionConfig::default()
+        }
+    }
+
+    /// Returns the blockhash whose validity bounds the lifetime of the `transaction`.
+    fn lifetime_blockhash(&self, transaction: &Transaction) -> ClientResult<Hash> {
+        match nonce_lifetime_commitment(transaction, self.nonce_blockhash_commitment) {
+            Some(commitment) => {
+                let (recent_blockhash, ..) = self.get_latest_blockhash_with_commitment(commitment)?;
+                Ok(recent_blockhash)
+            },
+            None => Ok(transaction.message.recent_blockhash),
+        }
+    }
+
+    /// Polls the signature status until the transaction is processed or the `recent_blockhash` becomes invalid.
+    /// Returns `false` if the blockhash expired before the transaction was processed, and an error if the `deadline`
+    /// passed.
+    fn wait_for_confirmation(
+        &self,
+        signature: &Signature,
+        recent_blockhash: &Hash,
+        deadline: Option<Instant>,
+    ) -> ClientResult<bool> {
+        let confirmed = self.poll_confirmation(signature, recent_blockhash, deadline);
+        if let Some(hub) = &self.confirmation_hub {
+            hub.unregister(signature);
+        }
+        confirmed
+    }
+
+    fn poll_confirmation(
+        &self,
+        signature: &Signature,
+        recent_blockhash: &Hash,
+        deadline: Option<Instant>,
+    ) -> ClientResult<bool> {
+        const GET_STATUS_RETRIES: usize = usize::MAX;
+
+        for status_retry in 0..GET_STATUS_RETRIES {
+            self.check_overall_deadline(deadline)?;
+            let status = match &self.confirmation_hub {
+                Some(hub) => hub.signature_status(&self.client, signature)?,
+                None => self.get_signature_status(signature)?,
+            };
+            self.observe(|| RpcEvent::ConfirmationPoll {
+                signature: *signature,
+                poll: status_retry,
+                processed: status.is_some(),
+            });
+            match status {
+                Some(Ok(_)) => return Ok(true),
+                Some(Err(e)) if self.attach_logs_on_failure => return Err(self.record_failure_logs(signature, e)),
+                Some(Err(e)) => return Err(e.into()),
+                None => {
+                    if !self.is_blockhash_valid(recent_blockhash, CommitmentConfig::processed())? {
+                        // Block hash is not found by some reason
+                        return Ok(false);
+                    } else if cfg!(not(test))
+                        // Ignore sleep at last step.
+                        && status_retry < GET_STATUS_RETRIES
+                    {
+                        // Retry twice a second
+                        self.sleep(STATUS_POLL_INTERVAL);
+                        continue;
+                    }
+                },
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Waits until the transaction with `signature` reaches the `commitment` level, returning the transaction
+    /// error if it failed.
+    pub fn confirm_signature_with_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<()> {
+        for _ in 0..CONFIRM_STATUS_RETRIES {
+            match self.get_signature_status_with_commitment(signature, commitment)? {
+                Some(Ok(_)) => return Ok(()),
+                Some(Err(e)) => return Err(e.into()),
+                None => self.sleep(STATUS_POLL_INTERVAL),
+            }
+        }
+
+        Err(RpcError::ForUser(format!("unable to confirm transaction {}", signature)).into())
+    }
+
+    /// Returns the status of the transaction with `signature`, searching the history only for recent transactions.
+    pub fn get_transaction_status(&self, signature: &Signature) -> ClientResult<Option<TransactionStatus>> {
+        Ok(self
+            .get_signature_statuses(&[*signature])?
+            .value
+            .into_iter()
+            .next()
+            .flatten())
+    }
+
+    /// Queries the status of the transaction with `signature` for each of the processed, confirmed and finalized
+    /// levels in turn, returning the confirmation status reported by each query that satisfied its level.
+    pub fn status_across_commitments(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<[Option<TransactionConfirmationStatus>; 3]> {
+        let mut statuses = [None, None, None];
+        let commitments = [
+            CommitmentConfig::processed(),
+            CommitmentConfig::confirmed(),
+            CommitmentConfig::finalized(),
+        ];
+        for (status, commitment) in statuses.iter_mut().zip(commitments) {
+            *status = self
+                .get_transaction_status(signature)?
+                .filter(|status| status.satisfies_commitment(commitment))
+                .and_then(|status| status.confirmation_status);
+        }
+        Ok(statuses)
+    }
+
+    /// Checks once whether the transaction with `signature` has landed at the `commitment` level, e.g. before
+    /// resending it after an ambiguous failure. A landed transaction counts even if it failed.
+    pub fn is_signature_confirmed(&self, signature: &Signature, commitment: CommitmentConfig) -> ClientResult<bool> {
+        Ok(self
+            .get_transaction_status(signature)?
+            .map_or(false, |status| status.satisfies_commitment(commitment)))
+    }
+
+    /// Waits for the transaction with `signature` to be finalized, recording how long it took to reach each
+    /// commitment level.
+    pub fn confirm_with_timings(&self, signature: &Signature) -> ClientResult<ConfirmTimings> {
+        let started = Instant::now();
+        let mut reached: [Option<Duration>; 3] = [None; 3];
+
+        for _ in 0..FINALIZE_STATUS_RETRIES {
+            if let Some(status) = self.get_transaction_status(signature)? {
+                if let Some(err) = &status.err {
+                    return Err(err.clone().into());
+                }
+
+                let elapsed = started.elapsed();
+                for level in reached.iter_mut().take(confirmation_rank(&status) + 1) {
+                    level.get_or_insert(elapsed);
+                }
+
+                if let [Some(processed), Some(confirmed), Some(finalized)] = reached {
+                    return Ok(ConfirmTimings {
+                        processed,
+                        confirmed,
+                        finalized,
+                    });
+                }
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+
+        Err(RpcError::ForUser(format!("transaction {} was not finalized", signature)).into())
+    }
+
+    /// Waits for the transaction with `signature` to be finalized within the `timeout`, returning the finalized
+    /// commitment. On timeout, the highest commitment reached is returned instead of an error if downgrading is
+    /// enabled, see `with_downgrade_on_timeout`.
+    pub fn confirm_finalized(&self, signature: &Signature, timeout: Duration) -> ClientResult<CommitmentConfig> {
+        let started = Instant::now();
+        let mut highest_rank = None;
+
+        loop {
+            if let Some(status) = self.get_transaction_status(signature)? {
+                if let Some(err) = &status.err {
+                    return Err(err.clone().into());
+                }
+
+                let rank = confirmation_rank(&status);
+                if rank == 2 {
+                    return Ok(CommitmentConfig::finalized());
+                }
+                highest_rank = highest_rank.max(Some(rank));
+            }
+
+            if started.elapsed() >= timeout {
+                return match highest_rank {
+                    Some(rank) if self.downgrade_on_timeout => Ok([
+                        CommitmentConfig::processed(),
+                        CommitmentConfig::confirmed(),
+                        CommitmentConfig::finalized(),
+                    ][rank]),
+                    _ => Err(RpcError::ForUser(format!("transaction {} was not finalized", signature)).into()),
+                };
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+    }
+
+    /// Waits until at least `quorum` of this and the other `clients` report the transaction with `signature` as
+    /// confirmed at their commitment, returning the number of confirming clients. Each client is counted once and
+    /// polled until it confirms, an error is returned if the quorum isn't met within the `timeout`.
+    pub fn confirm_with_quorum(
+        &self,
+        signature: &Signature,
+        clients: &[&WasmRpcClient],
+        quorum: usize,
+        timeout: Duration,
+    ) -> ClientResult<usize> {
+        let started = Instant::now();
+        let mut all_clients = vec![self];
+        for client in clients {
+            if !all_clients.iter().any(|known| ptr::eq(*known, *client)) {
+                all_clients.push(*client);
+            }
+        }
+        let mut confirmed = vec![false; all_clients.len()];
+
+        loop {
+            for (client, confirmed) in all_clients.iter().zip(&mut confirmed) {
+                if !*confirmed {
+                    *confirmed = client.is_signature_confirmed(signature, client.commitment())?;
+                }
+            }
+
+            let confirmations = confirmed.iter().filter(|confirmed| **confirmed).count();
+            if confirmations >= quorum {
+                return Ok(confirmations);
+            }
+            if started.elapsed() >= timeout {
+                return Err(RpcError::ForUser(format!(
+                    "transaction {} is confirmed by {} of {} clients, the quorum is {}",
+                    signature,
+                    confirmations,
+                    all_clients.len(),
+                    quorum
+                ))
+                .into());
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+    }
+
+    /// Waits until the transactions with `signatures` reach the client's commitment or the `timeout` elapses,
+    /// returning how many of them landed and how long their confirmation took. Failed transactions count as landed.
+    pub fn confirm_batch_with_stats(
+        &self,
+        signatures: &[Signature],
+        timeout: Duration,
+    ) -> ClientResult<BatchConfirmStats> {
+        let started = Instant::now();
+        let mut confirm_times: Vec<Option<Duration>> = vec![None; signatures.len()];
+        let mut failed = 0;
+
+        loop {
+            let pending: Vec<_> = (0..signatures.len())
+                .filter(|index| confirm_times[*index].is_none())
+                .collect();
+            if pending.is_empty() || started.elapsed() >= timeout {
+                break;
+            }
+
+            for chunk in pending.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+                let chunk_signatures: Vec<_> = chunk.iter().map(|index| signatures[*index]).collect();
+                let statuses = self.get_signature_statuses(&chunk_signatures)?.value;
+                for (index, status) in chunk.iter().zip(statuses) {
+                    if let Some(status) = status.filter(|status| status.satisfies_commitment(self.commitment())) {
+                        confirm_times[*index] = Some(started.elapsed());
+                        if status.err.is_some() {
+                            failed += 1;
+                        }
+                    }
+                }
+            }
+            if confirm_times.iter().any(Option::is_none) {
+                self.sleep(STATUS_POLL_INTERVAL);
+            }
+        }
+
+        let confirm_times: Vec<_> = confirm_times.into_iter().flatten().collect();
+        Ok(BatchConfirmStats {
+            confirmed: confirm_times.len(),
+            failed,
+            pending: signatures.len() - confirm_times.len(),
+            min_confirm_time: confirm_times.iter().min().copied(),
+            max_confirm_time: confirm_times.iter().max().copied(),
+            avg_confirm_time: (!confirm_times.is_empty())
+                .then(|| confirm_times.iter().sum::<Duration>() / confirm_times.len() as u32),
+        })
+    }
+
+    /// Waits with `confirm_signature_with_commitment` until the transaction with `signature` reaches the
+    /// `commitment` level, then fetches it and checks its status meta against the `expected` predicate. Fails if the
+    /// transaction isn't confirmed in time, failed or doesn't match.
+    pub fn confirm_and_verify(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+        expected: impl Fn(&TransactionStatusMeta) -> bool,
+    ) -> ClientResult<()> {
+        self.confirm_signature_with_commitment(signature, commitment)?;
+
+        // The history doesn't support `processed`, which the transaction has reached anyway
+        let commitment = if commitment.is_at_least_confirmed() {
+            commitment
+        } else {
+            CommitmentConfig::confirmed()
+        };
+        let meta = self.get_transaction_meta_with_commitment(signature, commitment)?;
+        if expected(&transaction_status_meta(signature, meta)?) {
+            Ok(())
+        } else {
+            Err(RpcError::ForUser(format!("transaction {} result does not match expectations", signature)).into())
+        }
+    }
+
+    /// Fetches the status meta of a confirmed transaction.
+    pub fn get_transaction_meta(&self, signature: &Signature) -> ClientResult<UiTransactionStatusMeta> {
+        self.get_transaction_meta_with_commitment(signature, CommitmentConfig::confirmed())
+    }
+
+    /// Fetches the status meta of a transaction that reached the `commitment`, `confirmed` or `finalized`.
+    pub fn get_transaction_meta_with_commitment(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<UiTransactionStatusMeta> {
+        let transaction = self.get_transaction_with_config(signature, RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(commitment),
+        })?;
+
+        transaction
+            .transaction
+            .meta
+            .ok_or_else(|| RpcError::ForUser(format!("transaction {} has no status meta", signature)).into())
+    }
+
+    /// Fetches the latest blockhash and the last block height it is valid at, like
+    /// `get_latest_blockhash_with_commitment`, remembering the slot it was fetched at for `fetched_blockhash_age`.
+    /// The client fetches the blockhashes of the transactions it builds or re-signs with it.
+    pub fn fetch_latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        let Response { context, value } =
+            self.send::<Response<RpcBlockhash>>(RpcRequest::GetLatestBlockhash, json!([commitment]))?;
+        let blockhash = value
+            .blockhash
+            .parse()
+            .map_err(|_| RpcError::ParseError(format!("invalid blockhash {}", value.blockhash)))?;
+
+        let mut blockhash_slots = self.blockhash_slots.write().unwrap();
+        if blockhash_slots.len() >= KEPT_BLOCKHASH_SLOTS {
+            blockhash_slots.pop_front();
+        }
+        blockhash_slots.push_back((blockhash, context.slot));
+        Ok((blockhash, value.last_valid_block_height))
+    }
+
+    /// Returns how many slots ago the client fetched the `blockhash` with `fetch_latest_blockhash`, or `None` if it is
+    /// expired or not among the last `KEPT_BLOCKHASH_SLOTS` fetched ones.
+    ///
+    /// This is not the age of the blockhash itself, which the node doesn't report: the blockhash was already some
+    /// slots old when fetched, and blockhashes obtained elsewhere are unknown. The slots are counted from the fetch
+    /// to the one `isBlockhashValid` checked the validity at.
+    pub fn fetched_blockhash_age(&self, blockhash: &Hash) -> ClientResult<Option<u64>> {
+        let fetched_at = self
+            .blockhash_slots
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(fetched, _)| fetched == blockhash)
+            .map(|(_, slot)| *slot);
+        let fetched_at = match fetched_at {
+            Some(fetched_at) => fetched_at,
+            None => return Ok(None),
+        };
+
+        let Response {
+            context,
+            value: is_valid,
+        } = self.send::<Response<bool>>(
+            RpcRequest::IsBlockhashValid,
+            json!([blockhash.to_string(), self.commitment()]),
+        )?;
+        Ok(is_valid.then(|| context.slot.saturating_sub(fetched_at)))
+    }
+
+    /// Summarizes the RPC activity of the client. Error count, maximum latency and per-method counts are only
+    /// available for clients created by `with_http_sender`.
+    pub fn session_summary(&self) -> SessionSummary {
+        let transport_stats = self.get_transport_stats();
+        let session_stats = self.http_sender().map(HttpSender::session_stats).unwrap_or_default();
+
+        SessionSummary {
+            request_count: transport_stats.request_count,
+            error_count: session_stats.error_count,
+            total_latency: transport_stats.elapsed_time,
+            max_latency: session_stats.max_latency,
+            average_latency: if transport_stats.request_count > 0 {
+                transport_stats.elapsed_time / transport_stats.request_count as u32
+            } else {
+                Duration::default()
+            },
+            rate_limited_time: transport_stats.rate_limited_time,
+            method_counts: session_stats.method_counts,
+        }
+    }
+
+    /// Returns `true` if the account at `pubkey` is an executable program, `false` if it isn't or doesn't exist.
+    pub fn is_program(&self, pubkey: &Pubkey) -> ClientResult<bool> {
+        Ok(self
+            .get_account_with_commitment(pubkey, self.commitment())?
+            .value
+            .map_or(false, |account| account.executable))
+    }
+
+    /// Derives the program address from the `seeds` and `program_id`, then fetches the account at it.
+    pub fn get_pda_account(&self, seeds: &[&[u8]], program_id: &Pubkey) -> ClientResult<(Pubkey, Option<Account>)> {
+        let (address, _) = Pubkey::find_program_address(seeds, program_id);
+        let account = self.get_account_with_commitment(&address, self.commitment())?.value;
+        Ok((address, account))
+    }
+
+    /// Returns the rent epoch of the account at `pubkey`, or `None` if the account doesn't exist.
+    pub fn rent_epoch(&self, pubkey: &Pubkey) -> ClientResult<Option<Epoch>> {
+        Ok(self
+            .get_account_with_commitment(pubkey, self.commitment())?
+            .value
+            .map(|account| account.rent_epoch))
+    }
+
+    /// Returns the minimum balance of a rent-exempt account with `data_len` bytes of data, cached per data length.
+    pub fn rent_exempt_minimum(&self, data_len: usize) -> ClientResult<u64> {
+        if let Some(minimum) = self.rent_exempt_minimums.read().unwrap().get(&data_len) {
+            return Ok(*minimum);
+        }
+
+        let minimum = self.get_minimum_balance_for_rent_exemption(data_len)?;
+        self.rent_exempt_minimums.write().unwrap().insert(data_len, minimum);
+        Ok(minimum)
+    }
+
+    /// Returns whether the account at `pubkey` holds enough lamports to be rent-exempt, or `None` if the account
+    /// doesn't exist.
+    pub fn is_rent_exempt(&self, pubkey: &Pubkey) -> ClientResult<Option<bool>> {
+        match self.get_account_with_commitment(pubkey, self.commitment())?.value {
+            Some(account) => Ok(Some(account.lamports >= self.rent_exempt_minimum(account.data.len())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the account at `pubkey` at the confirmed and then at the finalized commitment, returning the finalized
+    /// state. If the confirmed state differs, e.g. it is from a fork about to be dropped, the mismatch callback is
+    /// called with both of them.
+    pub fn get_account_finalized_safe(&self, pubkey: &Pubkey) -> ClientResult<Option<Account>> {
+        let confirmed = self
+            .get_account_with_commitment(pubkey, CommitmentConfig::confirmed())?
+            .value;
+        let finalized = self
+            .get_account_with_commitment(pubkey, CommitmentConfig::finalized())?
+            .value;
+
+        if confirmed != finalized {
+            if let Some(on_commitment_mismatch) = &self.on_commitment_mismatch {
+                on_commitment_mismatch(pubkey, confirmed.as_ref(), finalized.as_ref());
+            }
+        }
+        Ok(finalized)
+    }
+
+    /// Fetches the account at `pubkey` from this and the `other` endpoint, returning whether both report the same
+    /// state (or both report the account missing).
+    pub fn cross_check_account(&self, other: &WasmRpcClient, pubkey: &Pubkey) -> ClientResult<bool> {
+        let account = self.get_account_with_commitment(pubkey, self.commitment())?.value;
+        let other_account = other.get_account_with_commitment(pubkey, other.commitment())?.value;
+        Ok(account == other_account)
+    }
+
+    /// Fetches the account in the UI representation with the requested data `encoding`.
+    pub fn get_ui_account(&self, pubkey: &Pubkey, encoding: UiAccountEncoding) -> ClientResult<Option<UiAccount>> {
+        let response: Response<Option<UiAccount>> = self.send(
+            RpcRequest::GetAccountInfo,
+            json!([pubkey.to_string(), { "encoding": encoding, "commitment": self.commitment().commitment }]),
+        )?;
+        Ok(response.value)
+    }
+
+    /// Fetches the account with `jsonParsed` encoding and returns the parsed `info` object. Fails if the node has no
+    /// parser for the account's program and returns it in a binary encoding.
+    pub fn get_account_parsed(&self, pubkey: &Pubkey) -> ClientResult<Option<Value>> {
+        match self.get_ui_account(pubkey, UiAccountEncoding::JsonParsed)? {
+            Some(UiAccount {
+                data: UiAccountData::Json(mut parsed_account),
+                ..
+            }) => Ok(Some(parsed_account.parsed["info"].take())),
+            Some(_) => Err(RpcError::ParseError(format!("account {} data is not parseable to JSON", pubkey)).into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetches the account with `jsonParsed` encoding and returns it typed for its program, e.g. a stake or a token
+    /// account.
+    pub fn get_parsed_program_account(&self, pubkey: &Pubkey) -> ClientResult<Option<ParsedProgramAccount>> {
+        self.get_ui_account(pubkey, UiAccountEncoding::JsonParsed)?
+            .map(|account| parse_program_account(&account.data))
+            .transpose()
+    }
+
+    /// Fetches the account with its data in the most compact encoding, `base64+zstd` with the `zstd` feature. If
+    /// the node doesn't support it or the data fails to decode, the account is requested once more in the fallback
+    /// encoding.
+    pub fn get_account_compact(&self, pubkey: &Pubkey) -> ClientResult<Option<Account>> {
+        let encoding = compact_account_encoding();
+        let account = self
+            .get_ui_account(pubkey, encoding)
+            .and_then(|account| account.map(|account| self.decode_ui_account(account)).transpose());
+        match (account, self.fallback_account_encoding) {
+            (Err(_), Some(fallback_encoding)) if fallback_encoding != encoding => self
+                .get_ui_account(pubkey, fallback_encoding)?
+                .map(|account| self.decode_ui_account(account))
+                .transpose(),
+            (account, _) => account,
+        }
+    }
+
+    /// Fetches the account in the most compact encoding and deserializes its data with `bincode` into `T`.
+    pub fn get_account_bincode<T: DeserializeOwned>(&self, pubkey: &Pubkey) -> ClientResult<Option<T>> {
+        match self.get_account_compact(pubkey)? {
+            Some(account) => bincode::deserialize(&account.data).map(Some).map_err(|err| {
+                RpcError::ParseError(format!("failed to deserialize account {}: {}", pubkey, err)).into()
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the owner, the mint and the raw amount of the token account, parsed from a `jsonParsed` fetch.
+    pub fn token_account_info(&self, token_account: &Pubkey) -> ClientResult<(Pubkey, Pubkey, u64)> {
+        let info = self
+            .get_account_parsed(token_account)?
+            .ok_or_else(|| RpcError::ForUser(format!("token account {} not found", token_account)))?;
+        let invalid_field =
+            |field| RpcError::ParseError(format!("token account {} has invalid {}", token_account, field));
+
+        let owner = info["owner"]
+            .as_str()
+            .and_then(|owner| owner.parse().ok())
+            .ok_or_else(|| invalid_field("owner"))?;
+        let mint = info["mint"]
+            .as_str()
+            .and_then(|mint| mint.parse().ok())
+            .ok_or_else(|| invalid_field("mint"))?;
+        let amount = info["tokenAmount"]["amount"]
+            .as_str()
+            .and_then(|amount| amount.parse().ok())
+            .ok_or_else(|| invalid_field("amount"))?;
+        Ok((owner, mint, amount))
+    }
+
+    /// Fetches the account data in the requested `encoding` and decodes it with the configured decoder. If it fails
+    /// to decode, the data is requested once more in the fallback encoding.
+    pub fn get_account_data_with_encoding(
+        &self,
+        pubkey: &Pubkey,
+        encoding: UiAccountEncoding,
+    ) -> ClientResult<Option<Vec<u8>>> {
+        let data = self
+            .get_ui_account(pubkey, encoding)?
+            .map(|account| decode_account_data(self.data_decoder.as_ref(), &account.data))
+            .transpose();
+        match (data, self.fallback_account_encoding) {
+            (Err(_), Some(fallback_encoding)) if fallback_encoding != encoding => self
+                .get_ui_account(pubkey, fallback_encoding)?
+                .map(|account| decode_account_data(self.data_decoder.as_ref(), &account.data))
+                .transpose(),
+            (data, _) => data,
+        }
+    }
+
+    /// Fetches the account and deserializes its data, after skipping `discriminator_len` leading bytes, into `T`.
+    #[cfg(feature = "borsh")]
+    pub fn get_account_borsh<T: BorshDeserialize>(
+        &self,
+        pubkey: &Pubkey,
+        discriminator_len: usize,
+    ) -> ClientResult<Option<T>> {
+        let account = match self.get_account_with_commitment(pubkey, self.commitment())?.value {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+
+        let mut data = account.data.get(discriminator_len..).ok_or_else(|| {
+            RpcError::ParseError(format!(
+                "account {} data is shorter than the {} bytes discriminator",
+                pubkey, discriminator_len
+            ))
+        })?;
+        T::deserialize(&mut data)
+            .map(Some)
+            .map_err(|err| RpcError::ParseError(format!("failed to deserialize account {}: {}", pubkey, err)).into())
+    }
+
+    /// Converts the account in the UI representation, decoding its data with the configured decoder.
+    pub fn decode_ui_account(&self, account: UiAccount) -> ClientResult<Account> {
+        decode_account(self.data_decoder.as_ref(), &account)
+    }
+
+    /// Returns the accounts owned by `program_id` whose data contains `bytes` at `offset`.
+    pub fn program_accounts_memcmp(
+        &self,
+        program_id: &Pubkey,
+        offset: usize,
+        bytes: &[u8],
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        let accounts: Vec<RpcKeyedAccount> = self.send(
+            RpcRequest::GetProgramAccounts,
+            json!([program_id.to_string(), {
+                "encoding": UiAccountEncoding::Base64,
+                "commitment": self.commitment().commitment,
+                "filters": [{ "memcmp": { "offset": offset, "bytes": bs58::encode(bytes).into_string() } }],
+            }]),
+        )?;
+
+        accounts
+            .into_iter()
+            .map(
+                |RpcKeyedAccount { pubkey, account }| -> ClientResult<(Pubkey, Account)> {
+                    let pubkey = pubkey
+                        .parse()
+                        .map_err(|_| RpcError::ParseError(format!("invalid account pubkey {}", pubkey)))?;
+                    Ok((pubkey, self.decode_ui_account(account)?))
+                },
+            )
+            .collect()
+    }
+
+    /// Returns the blockhash currently stored in the durable nonce account.
+    pub fn get_nonce_blockhash(&self, nonce_account: &Pubkey) -> ClientResult<Hash> {
+        let account = self
+            .get_account_with_commitment(nonce_account, self.nonce_blockhash_commitment)?
+            .value
+            .ok_or_else(|| RpcError::ForUser(format!("nonce account {} not found", nonce_account)))?;
+        let versions: NonceVersions = account
+            .deserialize_data()
+            .map_err(|err| RpcError::ForUser(format!("invalid nonce account {}: {}", nonce_account, err)))?;
+
+        match versions.convert_to_current() {
+            NonceState::Initialized(data) => Ok(data.blockhash),
+            NonceState::Uninitialized => {
+                Err(RpcError::ForUser(format!("nonce account {} is not initialized", nonce_account)).into())
+            },
+        }
+    }
+
+    /// Polls the nonce account until its stored blockhash differs from `previous`, returning the new one.
+    pub fn wait_for_nonce_advance(
+        &self,
+        nonce_account: &Pubkey,
+        previous: &Hash,
+        timeout: Duration,
+    ) -> ClientResult<Hash> {
+        let started = Instant::now();
+        loop {
+            let blockhash = self.get_nonce_blockhash(nonce_account)?;
+            if blockhash != *previous {
+                return Ok(blockhash);
+            }
+            if started.elapsed() >= timeout {
+                return Err(RpcError::ForUser(format!("nonce account {} has not advanced", nonce_account)).into());
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+    }
+
+    /// Estimates the time until a blockhash with `last_valid_block_height` expires, assuming the default slot time.
+    /// Returns zero if it is already expired.
+    pub fn blockhash_expiry_eta(&self, last_valid_block_height: u64) -> ClientResult<Duration> {
+        let blocks_left = last_valid_block_height.saturating_sub(self.get_block_height()?);
+        Ok(Duration::from_millis(blocks_left * DEFAULT_MS_PER_SLOT))
+    }
+
+    /// Returns the current slot at the `commitment`, retrying while the node reports it is behind the cluster.
+    pub fn current_slot(&self, commitment: CommitmentConfig) -> ClientResult<Slot> {
+        let mut retry = 0;
+        loop {
+            match self.get_slot_with_commitment(commitment) {
+                Err(err) if is_node_unhealthy(&err) && retry < NODE_UNHEALTHY_RETRIES => {
+                    retry += 1;
+                    self.sleep(STATUS_POLL_INTERVAL);
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Polls the balance of `pubkey` until it reaches `min_lamports`, returning the observed balance.
+    pub fn wait_for_min_balance(&self, pubkey: &Pubkey, min_lamports: u64, timeout: Duration) -> ClientResult<u64> {
+        let started = Instant::now();
+        loop {
+            let balance = self.get_balance(pubkey)?;
+            if balance >= min_lamports {
+                return Ok(balance);
+            }
+            if started.elapsed() >= timeout {
+                return Err(RpcError::ForUser(format!(
+                    "balance of {} is {} lamports, less than the expected {}",
+                    pubkey, balance, min_lamports
+                ))
+                .into());
+            }
+            self.sleep(STATUS_POLL_INTERVAL);
+        }
+    }
+
+    /// Polls the epoch info until the epoch number increases, returning the new epoch.
+    pub fn wait_for_next_epoch(&self, timeout: Duration) -> ClientResult<Epoch> {
+        let started = Instant::now();
+        let initial_epoch = self.get_epoch_info()?.epoch;
+        loop {
+            self.sleep(EPOCH_POLL_INTERVAL);
+
+            let epoch = self.get_epoch_info()?.epoch;
+            if epoch > initial_epoch {
+                return Ok(epoch);
+            }
+            if started.elapsed() >= timeout {
+                return Err(RpcError::ForUser(format!("epoch {} has not ended", initial_epoch)).into());
+            }
+        }
+    }
+
+    /// Polls the finalized slot, passing each new one to `on_slot` until it returns false.
+    pub fn poll_slots(&self, mut on_slot: impl FnMut(Slot) -> bool) -> ClientResult<()> {
+        let mut last_slot = None;
+        loop {
+            let slot = self.current_slot(CommitmentConfig::finalized())?;
+            if last_slot.map_or(true, |last_slot| slot > last_slot) {
+                last_slot = Some(slot);
+                if !on_slot(slot) {
+                    return Ok(());
+                }
+            }
+            self.sleep(self.slot_poll_interval);
+        }
+    }
+
+    /// Returns the version of the node software, fetched once and cached.
+    pub fn node_version(&self) -> ClientResult<NodeVersion> {
+        if let Some(version) = *self.node_version.read().unwrap() {
+            return Ok(version);
+        }
+
+        let version_info = self.get_version()?;
+        let version = NodeVersion::parse(&version_info.solana_core)
+            .ok_or_else(|| RpcError::ParseError(format!("invalid node version {}", version_info.solana_core)))?;
+        *self.node_version.write().unwrap() = Some(version);
+        Ok(version)
+    }
+
+    /// Returns whether the `method`, introduced in the `min_version` of the node software, is available on the node.
+    /// The result is cached per method.
+    pub fn method_available_by_version(&self, method: &str, min_version: &str) -> ClientResult<bool> {
+        if let Some(available) = self.method_availability.read().unwrap().get(method) {
+            return Ok(*available);
+        }
+
+        let min_version = NodeVersion::parse(min_version)
+            .ok_or_else(|| RpcError::ForUser(format!("invalid minimum version {} of {}", min_version, method)))?;
+        let available = self.node_version()? >= min_version;
+        self.method_availability
+            .write()
+            .unwrap()
+            .insert(method.to_string(), available);
+        Ok(available)
+    }
+
+    /// Returns the commitment array and the total active stake for the block at `slot`.
+    pub fn block_commitment(&self, slot: Slot) -> ClientResult<RpcBlockCommitment<BlockCommitmentArray>> {
+        self.send(RpcRequest::GetBlockCommitment, json!([slot]))
+    }
+
+    /// Recommends the commitment for the quickest safe confirmation: `confirmed` if the latest confirmed block is
+    /// voted by a clear majority of the stake, so forks are unlikely, `finalized` otherwise. This is a heuristic.
+    pub fn recommend_commitment(&self) -> ClientResult<CommitmentConfig> {
+        let slot = self.current_slot(CommitmentConfig::confirmed())?;
+        let block_commitment = self.block_commitment(slot)?;
+
+        let voted_stake: u64 = block_commitment.commitment.iter().flatten().sum();
+        let is_confirmed_safe = block_commitment.total_stake > 0
+            && voted_stake as u128 * 100 >= block_commitment.total_stake as u128 * SAFE_CONFIRMED_STAKE_PERCENT;
+        Ok(if is_confirmed_safe {
+            CommitmentConfig::confirmed()
+        } else {
+            CommitmentConfig::finalized()
+        })
+    }
+
+    /// Fetches the program logs of the failed transaction for `failure_logs`, if they can be fetched, and returns the
+    /// transaction error.
+    fn record_failure_logs(&self, signature: &Signature, err: TransactionError) -> ClientError {
+        // The transaction is not available for history queries until confirmed, failed or not
+        let _ = self.confirm_signature_with_commitment(signature, CommitmentConfig::confirmed());
+
+        let logs = self.get_transaction_raw_meta(signature).ok().and_then(|meta| {
+            meta["logMessages"]
+                .as_array()
+                .map(|logs| logs.iter().filter_map(|log| log.as_str().map(String::from)).collect())
+        });
+        if let Some(logs) = logs {
+            let mut failure_logs = self.failure_logs.write().unwrap();
+            if failure_logs.len() >= KEPT_FAILURE_LOGS {
+                failure_logs.pop_front();
+            }
+            failure_logs.push_back((*signature, logs));
+        }
+
+        err.into()
+    }
+
+    /// Fetches a confirmed transaction, including versioned ones, as raw JSON.
+    pub fn get_transaction_raw(&self, signature: &Signature) -> ClientResult<Value> {
+        self.fetch_transaction_raw(signature)?
+            .ok_or_else(|| RpcError::ForUser(format!("transaction {} not found", signature)).into())
+    }
+
+    /// Fetches a confirmed transaction as raw JSON like `get_transaction_raw`, telling why it is not found: either
+    /// it is older than the historical floor of the node and so purged, or it is not yet confirmed, or the node
+    /// doesn't know it at all.
+    pub fn get_transaction_or_reason(&self, signature: &Signature) -> ClientResult<Result<Value, NotFoundReason>> {
+        if let Some(transaction) = self.fetch_transaction_raw(signature)? {
+            return Ok(Ok(transaction));
+        }
+
+        let status = self
+            .get_signature_statuses_with_history(&[*signature])?
+            .value
+            .into_iter()
+            .next()
+            .flatten();
+        let reason = match status {
+            Some(status) if status.slot < self.historical_floor()? => NotFoundReason::TooOld,
+            Some(_) => NotFoundReason::Unconfirmed,
+            None => NotFoundReason::Unknown,
+        };
+        Ok(Err(reason))
+    }
+
+    fn fetch_transaction_raw(&self, signature: &Signature) -> ClientResult<Option<Value>> {
+        let transaction: Value = self.send(
+            RpcRequest::GetTransaction,
+            json!([signature.to_string(), {
+                "encoding": UiTransactionEncoding::Json,
+                "commitment": CommitmentConfig::confirmed().commitment,
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        Ok((!transaction.is_null()).then(|| transaction))
+    }
+
+    /// Fetches the status meta of a confirmed transaction as raw JSON, which includes the fields newer than the
+    /// `UiTransactionStatusMeta` known to the client.
+    pub fn get_transaction_raw_meta(&self, signature: &Signature) -> ClientResult<Value> {
+        match self.get_transaction_raw(signature)?["meta"].take() {
+            Value::Null => Err(RpcError::ForUser(format!("transaction {} has no status meta", signature)).into()),
+            meta => Ok(meta),
+        }
+    }
+
+    /// Returns the account keys of a confirmed transaction, including the addresses loaded from lookup tables, in
+    /// the order instructions refer to them: static keys, then loaded writable and loaded readonly addresses.
+    pub fn resolved_account_keys(&self, signature: &Signature) -> ClientResult<Vec<Pubkey>> {
+        let transaction = self.get_transaction_raw(signature)?;
+        let loaded_addresses = &transaction["meta"]["loadedAddresses"];
+
+        [
+            &transaction["transaction"]["message"]["accountKeys"],
+            &loaded_addresses["writable"],
+            &loaded_addresses["readonly"],
+        ]
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .map(|key| {
+            key.as_str().and_then(|key| key.parse().ok()).ok_or_else(|| {
+                ClientError::from(RpcError::ParseError(format!(
+                    "invalid account key {} in transaction {}",
+                    key, signature
+                )))
+            })
+        })
+        .collect()
+    }
+
+    /// Returns the client commitment, raised to `confirmed` for history queries that don't support `processed`.
+    fn history_commitment(&self) -> CommitmentConfig {
+        let commitment = self.commitment();
+        if commitment.is_at_least_confirmed() {
+            commitment
+        } else {
+            CommitmentConfig::confirmed()
+        }
+    }
+
+    /// Returns the block at `slot` with the requested level of transaction details.
+    pub fn get_block_with_detail(&self, slot: Slot, detail: TransactionDetails) -> ClientResult<UiConfirmedBlock> {
+        self.send(
+            RpcRequest::GetBlock,
+            json!([slot, {
+                "encoding": UiTransactionEncoding::Json,
+                "transactionDetails": detail,
+                "rewards": false,
+                "commitment": self.history_commitment().commitment,
+            }]),
+        )
+    }
+
+    /// Calls the custom or provider-specific `method`, e.g. one of the DAS `getAsset` family, and deserializes its
+    /// result into `T`.
+    pub fn call_custom<T: DeserializeOwned>(&self, method: &str, params: Value) -> ClientResult<T> {
+        self.send(custom_request(method), params)
+    }
+
+    /// Calls each of the `attempts` methods in order, falling back to the next one only when the endpoint doesn't
+    /// support the method.
+    pub fn try_methods(&self, attempts: &[(&str, Value)]) -> ClientResult<Value> {
+        let mut last_error = None;
+        for (method, params) in attempts {
+            match self.send(custom_request(method), params.clone()) {
+                Err(err) if is_method_not_found(&err) => last_error = Some(err),
+                result => return result,
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| RpcError::ForUser("no RPC methods to try".to_string()).into()))
+    }
+
+    /// Resolves a name, e.g. an SNS domain, to its address with the provider's name resolution extension method.
+    pub fn resolve_name(&self, name: &str) -> ClientResult<Pubkey> {
+        let method = &self.name_resolution_method;
+        let result: Value = self.send(custom_request(method), json!([name])).map_err(|err| {
+            if is_method_not_found(&err) {
+                RpcError::ForUser(format!("the endpoint doesn't support name resolution with {}", method)).into()
+            } else {
+                err
+            }
+        })?;
+
+        let address = result
+            .as_str()
+            .or_else(|| result["value"].as_str())
+            .ok_or_else(|| RpcError::ParseError(format!("{} result has no address: {}", method, result)))?;
+        address
+            .parse()
+            .map_err(|_| RpcError::ParseError(format!("{} result has an invalid address {}", method, address)).into())
+    }
+
+    /// Returns up to `limit` recent performance samples, newest first.
+    pub fn recent_performance_samples(&self, limit: Option<usize>) -> ClientResult<Vec<RpcPerfSample>> {
+        self.send(RpcRequest::GetRecentPerformanceSamples, json!([limit]))
+    }
+
+    /// Estimates the time for a transaction to reach the `commitment`, from the average slot time of the recent
+    /// performance samples and the typical number of slots each commitment level takes.
+    pub fn estimate_confirmation_time(&self, commitment: CommitmentConfig) -> ClientResult<Duration> {
+        let samples = self.recent_performance_samples(Some(ESTIMATE_PERFORMANCE_SAMPLES))?;
+        let (slots, secs) = samples.iter().fold((0_u64, 0_u64), |(slots, secs), sample| {
+            (slots + sample.num_slots, secs + sample.sample_period_secs as u64)
+        });
+        let slot_time = if slots == 0 {
+            Duration::from_millis(DEFAULT_MS_PER_SLOT)
+        } else {
+            Duration::from_secs_f64(secs as f64 / slots as f64)
+        };
+
+        let depth = if commitment.is_finalized() {
+            FINALIZED_DEPTH_SLOTS
+        } else if commitment.is_confirmed() {
+            CONFIRMED_DEPTH_SLOTS
+        } else {
+            1
+        };
+        Ok(slot_time * depth)
+    }
+
+    /// Returns the lowest slot the node has information about in its ledger.
+    pub fn minimum_ledger_slot(&self) -> ClientResult<Slot> {
+        self.send(RpcRequest::MinimumLedgerSlot, Value::Null)
+    }
+
+    /// Returns the lowest slot for which the node can serve `getBlock`, the higher of the first available block and
+    /// the minimum ledger slot. The value is cached for a short time, since the floor moves as the ledger is purged.
+    pub fn historical_floor(&self) -> ClientResult<Slot> {
+        if let Some((fetched, floor)) = *self.historical_floor.read().unwrap() {
+            if fetched.elapsed() < HISTORICAL_FLOOR_CACHE_TTL {
+                return Ok(floor);
+            }
+        }
+
+        let floor = self.get_first_available_block()?.max(self.minimum_ledger_slot()?);
+        *self.historical_floor.write().unwrap() = Some((Instant::now(), floor));
+        Ok(floor)
+    }
+
+    /// Returns the largest accounts of the token `mint`, with their addresses and UI token amounts.
+    pub fn token_largest_accounts(&self, mint: &Pubkey) -> ClientResult<Vec<RpcTokenAccountBalance>> {
+        let response: Response<Vec<RpcTokenAccountBalance>> = self.send(
+            RpcRequest::GetTokenLargestAccounts,
+            json!([mint.to_string(), { "commitment": self.commitment().commitment }]),
+        )?;
+        Ok(response.value)
+    }
+
+    /// Returns the epoch schedule of the cluster, which maps the slots to their epochs.
+    pub fn epoch_schedule(&self) -> ClientResult<EpochSchedule> {
+        self.send(RpcRequest::GetEpochSchedule, Value::Null)
+    }
+
+    /// Returns the epoch containing the `slot` according to the cluster's epoch schedule.
+    pub fn epoch_of_slot(&self, slot: Slot) -> ClientResult<Epoch> {
+        Ok(self.epoch_schedule()?.get_epoch(slot))
+    }
+}
+
+/// Average transactions per second over the sample period.
+pub fn transactions_per_second(sample: &RpcPerfSample) -> f64 {
+    if sample.sample_period_secs == 0 {
+        0.0
+    } else {
+        sample.num_transactions as f64 / sample.sample_period_secs as f64
+    }
+}
+
+/// Aggregated RPC activity of a client session.
+#[derive(Debug, Default, Clone)]
+pub struct SessionSummary {
+    pub request_count: usize,
+    pub error_count: u64,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+    pub average_latency: Duration,
+    pub rate_limited_time: Duration,
+    pub method_counts: HashMap<String, u64>,
+}
+
+/// Returns `true` if the error reports that the endpoint doesn't support the called method.
+pub fn is_method_not_found(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: JSON_RPC_METHOD_NOT_FOUND,
+            ..
+        })
+    )
+}
+
+/// Returns `true` if the error reports that the node is unhealthy, e.g. lagging behind the cluster.
+pub fn is_node_unhealthy(err: &ClientError) -> bool {
+    matches!(
+        err.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+            ..
+        })
+    )
+}
+
+/// Returns `true` if the error is the preflight failure of a transaction with a blockhash unknown to the node.
+pub fn is_blockhash_not_found(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.err == Some(TransactionError::BlockhashNotFound),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { message, .. }) => {
+            message.contains("Blockhash not found")
+        },
+        ClientErrorKind::TransactionError(TransactionError::BlockhashNotFound) => true,
+        _ => false,
+    }
+}
+
+/// Returns `true` if the error reports that the transaction has already been processed, e.g. when it was delivered
+/// through another endpoint first.
+pub fn is_already_processed(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.err == Some(TransactionError::AlreadyProcessed),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { message, .. }) => {
+            message.contains("already been processed")
+        },
+        ClientErrorKind::TransactionError(TransactionError::AlreadyProcessed) => true,
+        _ => false,
+    }
+}
+
+fn parse_signature(signature: &str) -> ClientResult<Signature> {
+    signature
+        .parse()
+        .map_err(|_| RpcError::ParseError(format!("invalid transaction signature {}", signature)).into())
+}
+
+/// Serializes the transaction, legacy or versioned, in the wire `encoding`.
+fn encode_transaction(transaction: &impl Serialize, encoding: UiTransactionEncoding) -> ClientResult<String> {
+    let serialized = bincode::serialize(transaction)
+        .map_err(|err| ClientErrorKind::Custom(format!("transaction serialization failed: {}", err)))?;
+    match encoding {
+        UiTransactionEncoding::Base58 | UiTransactionEncoding::Binary => Ok(bs58::encode(serialized).into_string()),
+        UiTransactionEncoding::Base64 => Ok(base64::encode(serialized)),
+        encoding => {
+            Err(ClientErrorKind::Custom(format!("unsupported transaction wire encoding {:?}", encoding)).into())
+        },
+    }
+}
+
+/// Converts the status meta of the transaction with `signature`, fetched in the `json` encoding, to the runtime one.
+fn transaction_status_meta(
+    signature: &Signature,
+    meta: UiTransactionStatusMeta,
+) -> ClientResult<TransactionStatusMeta> {
+    let invalid_instruction = || {
+        ClientError::from(RpcError::ParseError(format!(
+            "transaction {} has an invalid inner instruction",
+            signature
+        )))
+    };
+    let inner_instructions = meta
+        .inner_instructions
+        .map(|inner_instructions| {
+            inner_instructions
+                .into_iter()
+                .map(|inner| {
+                    let instructions = inner
+                        .instructions
+                        .into_iter()
+                        .map(|instruction| match instruction {
+                            UiInstruction::Compiled(instruction) => Ok(CompiledInstruction {
+                                program_id_index: instruction.program_id_index,
+                                accounts: instruction.accounts,
+                                data: bs58::decode(&instruction.data)
+                                    .into_vec()
+                                    .map_err(|_| invalid_instruction())?,
+                            }),
+                            UiInstruction::Parsed(_) => Err(invalid_instruction()),
+                        })
+                        .collect::<ClientResult<_>>()?;
+                    Ok(InnerInstructions {
+                        index: inner.index,
+                        instructions,
+                    })
+                })
+                .collect::<ClientResult<_>>()
+        })
+        .transpose()?;
+    let token_balances = |balances: Option<Vec<UiTransactionTokenBalance>>| {
+        balances.map(|balances| {
+            balances
+                .into_iter()
+                .map(|balance| TransactionTokenBalance {
+                    account_index: balance.account_index,
+                    mint: balance.mint,
+                    ui_token_amount: balance.ui_token_amount,
+                    owner: balance.owner.unwrap_or_default(),
+                })
+                .collect()
+        })
+    };
+
+    Ok(TransactionStatusMeta {
+        status: meta.status,
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions,
+        log_messages: meta.log_messages,
+        pre_token_balances: token_balances(meta.pre_token_balances),
+        post_token_balances: token_balances(meta.post_token_balances),
+        rewards: meta.rewards,
+    })
+}
+
+/// Returns `true` if the first instruction of a message with the static `account_keys` advances a nonce account,
+/// which makes the message use a durable nonce instead of a recent blockhash.
+fn uses_durable_nonce_instruction(account_keys: &[Pubkey], first_instruction: Option<&CompiledInstruction>) -> bool {
+    first_instruction.map_or(false, |instruction| {
+        account_keys.get(instruction.program_id_index as usize) == Some(&system_program::id())
+            && matches!(
+                bincode::deserialize(&instruction.data),
+                Ok(SystemInstruction::AdvanceNonceAccount)
+            )
+    })
+}
+
+/// Version of the node software as `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl NodeVersion {
+    /// Parses a version like `1.9.2`, ignoring pre-release and build suffixes. Missing components are zeros.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.split(|c| c == '-' || c == '+' || c == ' ').next()?;
+        let mut components = version.split('.').map(str::parse::<u64>);
+
+        Some(Self {
+            major: components.next()?.ok()?,
+            minor: components.next().transpose().ok()?.unwrap_or(0),
+            patch: components.next().transpose().ok()?.unwrap_or(0),
+        })
+    }
+}
+
+/// Time elapsed until a transaction reached each commitment level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmTimings {
+    pub processed: Duration,
+    pub confirmed: Duration,
+    pub finalized: Duration,
+}
+
+/// Builder of a JSON-RPC batch, see `WasmRpcClient::batch`.
+pub struct RpcBatch<'a> {
+    client: &'a WasmRpcClient,
+    requests: Vec<(RpcRequest, Value)>,
+}
+
+impl<'a> RpcBatch<'a> {
+    pub fn add(mut self, request: RpcRequest, params: Value) -> Self {
+        self.requests.push((request, params));
+        self
+    }
+
+    pub fn get_balance(self, pubkey: &Pubkey) -> Self {
+        let params = json!([pubkey.to_string(), { "commitment": self.client.commitment().commitment }]);
+        self.add(RpcRequest::GetBalance, params)
+    }
+
+    pub fn get_account_info(self, pubkey: &Pubkey) -> Self {
+        let params = json!([pubkey.to_string(), {
+            "encoding": UiAccountEncoding::Base64,
+            "commitment": self.client.commitment().commitment,
+        }]);
+        self.add(RpcRequest::GetAccountInfo, params)
+    }
+
+    pub fn get_signature_statuses(self, signatures: &[Signature]) -> Self {
+        let signatures: Vec<_> = signatures.iter().map(|signature| signature.to_string()).collect();
+        self.add(RpcRequest::GetSignatureStatuses, json!([signatures]))
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Sends the batch, returning the result of each request in the order they were added. A request failing
+    /// doesn't fail the others.
+    pub fn send(self) -> ClientResult<Vec<ClientResult<Value>>> {
+        let http_sender = self.client.http_sender().ok_or_else(|| {
+            ClientError::from(RpcError::ForUser(
+                "batches are only supported by clients created with an HTTP sender".to_string(),
+            ))
+        })?;
+        http_sender.send_batch(self.requests)
+    }
+}
+
+/// Aggregate outcome of `WasmRpcClient::confirm_batch_with_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfirmStats {
+    /// Number of landed transactions, including the failed ones.
+    pub confirmed: usize,
+    pub failed: usize,
+    /// Number of transactions not landed before the timeout.
+    pub pending: usize,
+    pub min_confirm_time: Option<Duration>,
+    pub max_confirm_time: Option<Duration>,
+    pub avg_confirm_time: Option<Duration>,
+}
+
+/// Index of the commitment level reached by the transaction: 0 - processed, 1 - confirmed, 2 - finalized.
+fn confirmation_rank(status: &TransactionStatus) -> usize {
+    match status.confirmation_status {
+        Some(TransactionConfirmationStatus::Processed) => 0,
+        Some(TransactionConfirmationStatus::Confirmed) => 1,
+        Some(TransactionConfirmationStatus::Finalized) => 2,
+        // Nodes without confirmation status report `None` confirmations for rooted transactions
+        None if status.confirmations.is_none() => 2,
+        None => 0,
+    }
+}
+
+/// Change of a token account balance made by a transaction, see `WasmRpcClient::send_confirm_and_token_deltas`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBalanceDelta {
+    pub account_index: usize,
+    pub account: Option<Pubkey>,
+    pub mint: Pubkey,
+    pub owner: Option<Pubkey>,
+    pub decimals: u8,
+    /// Change of the raw token amount.
+    pub delta: i128,
+}
+
+/// Reason of a transaction not being found, see `WasmRpcClient::get_transaction_or_reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotFoundReason {
+    /// The transaction landed before the historical floor of the node, so it has been purged.
+    TooOld,
+    /// The transaction has landed but hasn't been confirmed yet.
+    Unconfirmed,
+    /// The node doesn't know the transaction, it has never existed or its status has been purged as well.
+    Unknown,
+}
+
+/// Outcome of `WasmRpcClient::send_and_confirm_detecting_duplicate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmOutcome {
+    pub signature: Signature,
+    /// Whether the send was rejected because the transaction had already been processed.
+    pub was_already_processed: bool,
+}
+
+/// Options of sending a transaction, mirroring `RpcSendTransactionConfig`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SendTransactionOptions {
+    pub skip_preflight: bool,
+
+    /// Commitment of the preflight simulation, the client's commitment if `None`.
+    pub preflight_commitment: Option<CommitmentLevel>,
+
+    /// Maximum number of times the node retries forwarding the transaction to the leader, the node default if
+    /// `None`.
+    pub max_retries: Option<usize>,
+
+    /// Minimum slot the node must have reached to accept the transaction.
+    pub min_context_slot: Option<Slot>,
+}
+
+impl SendTransactionOptions {
+    pub fn skip_preflight(mut self, skip_preflight: bool) -> Self {
+        self.skip_preflight = skip_preflight;
+        self
+    }
+
+    pub fn preflight_commitment(mut self, preflight_commitment: CommitmentLevel) -> Self {
+        self.preflight_commitment = Some(preflight_commitment);
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    pub fn min_context_slot(mut self, min_context_slot: Slot) -> Self {
+        self.min_context_slot = Some(min_context_slot);
+        self
+    }
+}
+
+/// Report of a transaction simulation, see `WasmRpcClient::simulate_with_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationReport {
+    pub error: Option<TransactionError>,
+
+    /// Index of the failed instruction with its error, if the failure is attributed to an instruction.
+    pub instruction_error: Option<(u8, InstructionError)>,
+
+    /// Compute units consumed, `None` if the node doesn't report them.
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+    pub balance_changes: Vec<BalanceChange>,
+}
+
+impl SimulationReport {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Lamport balance of an account before and after a simulated transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub pubkey: Pubkey,
+    pub pre_lamports: u64,
+    pub post_lamports: u64,
+}
+
+impl BalanceChange {
+    pub fn delta(&self) -> i128 {
+        i128::from(self.post_lamports) - i128::from(self.pre_lamports)
+    }
+}
+
+/// Progress of a transaction confirmation, see `WasmRpcClient::send_and_confirm_transaction_with_commitment`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationProgress {
+    pub signature: Signature,
+
+    /// Confirmation status of the transaction, `None` until it is processed.
+    pub confirmation_status: Option<TransactionConfirmationStatus>,
+
+    /// Number of blocks confirming the transaction, `None` once it is rooted.
+    pub confirmations: Option<usize>,
+
+    /// Slot the transaction was processed in.
+    pub slot: Option<Slot>,
+    pub elapsed: Duration,
+}
+
+/// Token cancelling the confirmation loops of the clients it is set on, see
+/// `WasmRpcClient::with_cancellation_token`. Clones share the cancellation.
+#[derive(Debug, Default, Clone)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears the cancellation, so the clients can confirm transactions again.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Signature of a sent transaction together with the blockhash that bounds its lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedSignature {
+    pub signature: Signature,
+    pub recent_blockhash: Hash,
+}
+
+impl TrackedSignature {
+    /// Runs the confirmation loop for the tracked transaction.
+    pub fn confirm(&self, client: &WasmRpcClient) -> ClientResult<Signature> {
+        let confirmed = client.wait_for_confirmation(&self.signature, &self.recent_blockhash, None);
+        client.settle_pending(&self.signature, &confirmed);
+        if confirmed? {
+            Ok(self.signature)
+        } else {
+            Err(unable_to_confirm_error())
+        }
+    }
+}
+
+/// Returns the commitment of the latest blockhash bounding the lifetime of the `transaction`, `None` if its own
+/// recent blockhash bounds it. The recent blockhash of a durable nonce transaction is the nonce, which doesn't expire,
+/// so the confirmation of both clients waits on the latest blockhash fetched at the `nonce_blockhash_commitment`.
+pub(crate) fn nonce_lifetime_commitment(
+    transaction: &Transaction,
+    nonce_blockhash_commitment: CommitmentConfig,
+) -> Option<CommitmentConfig> {
+    uses_durable_nonce(transaction).map(|_| nonce_blockhash_commitment)
+}
+
+pub(crate) fn unable_to_confirm_error() -> ClientError {
+    RpcError::ForUser(
+        "unable to confirm transaction. \
+         This can happen in situations such as transaction expiration \
+         and insufficient fee-payer funds"
+            .to_string(),
+    )
+    .into()
+}
+
+impl Deref for WasmRpcClient {
+    type Target = RpcClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for WasmRpcClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        nonce::state::Data as NonceData,
+        signature::{Keypair, Signer},
+        system_instruction,
+    };
+
+    use super::*;
+    use crate::{
+        mock_sender::{MockError, MockSender},
+        retry::RetryPolicy,
+        transport::HostHttpResponse,
+    };
+
+    const NODE_VERSION: &str = "1.9.0";
+
+    fn mock_client(mock: &MockSender) -> WasmRpcClient {
+        WasmRpcClient::new(RpcClient::new_sender(
+            mock.clone(),
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        ))
+        .with_sleeper(|_| {})
+    }
+
+    /// Client over an `HttpSender` whose transport answers the requests with the result or the error object
+    /// returned by `respond` for their method and params.
+    fn http_client(respond: impl Fn(&str, &Value) -> Result<Value, Value> + Send + Sync + 'static) -> WasmRpcClient {
+        let sender = HttpSender::new("http://localhost:8899")
+            .with_sleeper(|_| {})
+            .with_transport(move |request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                let method = body["method"].as_str().unwrap();
+                let response = match method {
+                    "getVersion" => json!({ "id": body["id"], "result": { "solana-core": NODE_VERSION } }),
+                    _ => match respond(method, &body["params"]) {
+                        Ok(result) => json!({ "id": body["id"], "result": result }),
+                        Err(error) => json!({ "id": body["id"], "error": error }),
+                    },
+                };
+                Ok(HostHttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: response.to_string().into_bytes(),
+                })
+            });
+        WasmRpcClient::with_http_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed()))
+    }
+
+    fn context_response(slot: Slot, value: Value) -> Value {
+        json!({"context": {"slot": slot}, "value": value})
+    }
+
+    /// Mock of a node answering the version checks of the `RpcClient` methods.
+    fn rpc_mock() -> MockSender {
+        MockSender::new().with_response("getVersion", json!({ "solana-core": NODE_VERSION }))
+    }
+
+    /// Mock of a node whose blockhashes are never valid, so unprocessed transactions expire at once.
+    fn node_mock() -> MockSender {
+        rpc_mock().with_response("isBlockhashValid", context_response(1, json!(false)))
+    }
+
+    fn transaction_status(err: Option<&TransactionError>) -> Value {
+        json!({
+            "slot": 5,
+            "confirmations": null,
+            "status": match err {
+                Some(err) => json!({ "Err": err }),
+                None => json!({ "Ok": null }),
+            },
+            "err": err,
+            "confirmationStatus": "confirmed",
+        })
+    }
+
+    /// Mock of a node accepting the `transaction` and reporting it confirmed at the first status poll.
+    fn landing_mock(transaction: &Transaction) -> MockSender {
+        rpc_mock()
+            .with_response("sendTransaction", json!(transaction.signatures[0].to_string()))
+            .with_response("isBlockhashValid", context_response(1, json!(true)))
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([transaction_status(None)])),
+            )
+    }
+
+    /// Successful transaction status at the `confirmation_status` level.
+    fn status_at(confirmation_status: &str) -> Value {
+        let mut status = transaction_status(None);
+        status["confirmationStatus"] = json!(confirmation_status);
+        if confirmation_status != "finalized" {
+            status["confirmations"] = json!(1);
+        }
+        status
+    }
+
+    fn ui_account(account: &Account) -> Value {
+        json!({
+            "lamports": account.lamports,
+            "data": [base64::encode(&account.data), "base64"],
+            "owner": account.owner.to_string(),
+            "executable": account.executable,
+            "rentEpoch": account.rent_epoch,
+        })
+    }
+
+    /// `jsonParsed` UI account of an SPL token account.
+    fn parsed_token_account(owner: &Pubkey, mint: &Pubkey, amount: u64) -> Value {
+        json!({
+            "lamports": 2_039_280,
+            "data": {
+                "program": "spl-token",
+                "parsed": {
+                    "type": "account",
+                    "info": {
+                        "isNative": false,
+                        "mint": mint.to_string(),
+                        "owner": owner.to_string(),
+                        "state": "initialized",
+                        "tokenAmount": {
+                            "amount": amount.to_string(),
+                            "decimals": 6,
+                            "uiAmount": amount as f64 / 1e6,
+                            "uiAmountString": (amount as f64 / 1e6).to_string(),
+                        },
+                    },
+                },
+                "space": 165,
+            },
+            "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "executable": false,
+            "rentEpoch": 0,
+        })
+    }
+
+    fn nonce_account(blockhash: Hash) -> Account {
+        let versions = NonceVersions::new_current(NonceState::Initialized(NonceData {
+            authority: Pubkey::new_unique(),
+            blockhash,
+            ..NonceData::default()
+        }));
+        Account {
+            lamports: 1_000_000,
+            data: bincode::serialize(&versions).unwrap(),
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn transaction_meta(fee: u64) -> Value {
+        json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": fee,
+            "preBalances": [10_000, 0],
+            "postBalances": [10_000 - fee - 1, 1],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": [],
+        })
+    }
+
+    fn confirmed_transaction(meta: Value) -> Value {
+        json!({"slot": 5, "transaction": ["", "base64"], "meta": meta, "blockTime": null})
+    }
+
+    fn transfer(payer: &Keypair, blockhash: Hash) -> Transaction {
+        let recipient = Pubkey::new_from_array([7; 32]);
+        Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(&payer.pubkey(), &recipient, 1)],
+            Some(&payer.pubkey()),
+            &[payer],
+            blockhash,
+        )
+    }
+
+    #[test]
+    fn fetched_blockhash_age_counts_the_slots_since_the_fetch() {
+        let blockhash = Hash::new_unique();
+        let mock = MockSender::new().with_response(
+            "getLatestBlockhash",
+            context_response(
+                100,
+                json!({"blockhash": blockhash.to_string(), "lastValidBlockHeight": 250}),
+            ),
+        );
+        mock.push_response("isBlockhashValid", Ok(context_response(112, json!(true))));
+        mock.push_response("isBlockhashValid", Ok(context_response(260, json!(false))));
+        let client = mock_client(&mock);
+
+        assert_eq!(client.fetched_blockhash_age(&blockhash).unwrap(), None);
+        assert_eq!(
+            client.fetch_latest_blockhash(CommitmentConfig::confirmed()).unwrap(),
+            (blockhash, 250)
+        );
+        assert_eq!(client.fetched_blockhash_age(&blockhash).unwrap(), Some(12));
+        assert_eq!(client.fetched_blockhash_age(&blockhash).unwrap(), None);
+        assert_eq!(client.fetched_blockhash_age(&Hash::new_unique()).unwrap(), None);
+        assert_eq!(mock.call_count("isBlockhashValid"), 2);
+    }
+
+    #[test]
+    fn resigned_attempts_settle_the_ledger() {
+        let payer = Keypair::new();
+        let fresh_blockhash = Hash::new_unique();
+        let mut transaction = transfer(&payer, Hash::new_unique());
+        let resigned = transfer(&payer, fresh_blockhash);
+        let mock = node_mock().with_response(
+            "getLatestBlockhash",
+            context_response(
+                1,
+                json!({"blockhash": fresh_blockhash.to_string(), "lastValidBlockHeight": 300}),
+            ),
+        );
+        mock.push_response("sendTransaction", Ok(json!(transaction.signatures[0].to_string())));
+        mock.push_response("sendTransaction", Ok(json!(resigned.signatures[0].to_string())));
+        mock.push_response("getSignatureStatuses", Ok(context_response(1, json!([null]))));
+        mock.push_response(
+            "getSignatureStatuses",
+            Ok(context_response(5, json!([transaction_status(None)]))),
+        );
+        let ledger = Arc::new(SignatureLedger::new());
+        let client = mock_client(&mock).with_signature_ledger(ledger.clone());
+
+        let signature = client
+            .send_and_confirm_with_resign(&mut transaction, |transaction, blockhash| {
+                transaction
+                    .try_sign(&[&payer], blockhash)
+                    .map_err(|err| ClientErrorKind::SigningError(err).into())
+            })
+            .unwrap();
+        assert_eq!(signature, resigned.signatures[0]);
+        assert_eq!(mock.call_count("sendTransaction"), 2);
+        assert!(ledger.pending().is_empty());
+    }
+
+    #[test]
+    fn durable_nonce_transactions_are_resent_while_pending() {
+        let payer = Keypair::new();
+        let nonce_account = Pubkey::new_unique();
+        let mut transaction = Transaction::new_signed_with_payer(
+            &[
+                system_instruction::advance_nonce_account(&nonce_account, &payer.pubkey()),
+                system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::new_unique(),
+        );
+        let signature = transaction.signatures[0];
+        let mock = node_mock()
+            .with_response(
+                "getLatestBlockhash",
+                context_response(
+                    1,
+                    json!({"blockhash": Hash::new_unique().to_string(), "lastValidBlockHeight": 300}),
+                ),
+            )
+            .with_response("sendTransaction", json!(signature.to_string()))
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([transaction_status(None)])),
+            );
+        mock.push_response("getSignatureStatuses", Ok(context_response(1, json!([null]))));
+        let ledger = Arc::new(SignatureLedger::new());
+        let client = mock_client(&mock)
+            .with_signature_ledger(ledger.clone())
+            .with_resign_attempts(1);
+
+        let confirmed = client
+            .send_and_confirm_with_resign(&mut transaction, |_, _| {
+                panic!("durable-nonce transactions are not re-signed")
+            })
+            .unwrap();
+        assert_eq!(confirmed, signature);
+        assert_eq!(mock.call_count("sendTransaction"), 2);
+        assert!(ledger.pending().is_empty());
+    }
+
+    #[test]
+    fn failed_confirmation_keeps_the_transaction_error_and_the_logs() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let signature = transaction.signatures[0];
+        let error = TransactionError::InstructionError(0, InstructionError::Custom(7));
+        let mock = node_mock()
+            .with_response("sendTransaction", json!(signature.to_string()))
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([transaction_status(Some(&error))])),
+            )
+            .with_response(
+                "getTransaction",
+                json!({"slot": 5, "meta": {"err": error, "logMessages": ["Program log: insufficient funds"]}}),
+            );
+        let ledger = Arc::new(SignatureLedger::new());
+        let client = mock_client(&mock)
+            .with_signature_ledger(ledger.clone())
+            .with_attach_logs_on_failure(true);
+
+        let err = client.send_and_confirm_transaction(&transaction).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::TransactionError(err) if *err == error));
+        assert_eq!(
+            client.failure_logs(&signature),
+            Some(vec!["Program log: insufficient funds".to_string()])
+        );
+        assert!(ledger.pending().is_empty());
+
+        // A failed transaction can't land anymore, so it may be sent once more
+        mock.clear_calls();
+        assert!(client.send_and_confirm_transaction(&transaction).is_err());
+        assert_eq!(mock.call_count("sendTransaction"), 1);
+    }
+
+    #[test]
+    fn block_commitment_is_fetched_for_the_slot() {
+        let mut commitment = [0_u64; 32];
+        commitment[30] = 10;
+        commitment[31] = 32;
+        let mock = rpc_mock().with_response(
+            "getBlockCommitment",
+            json!({"commitment": commitment, "totalStake": 50}),
+        );
+        let client = mock_client(&mock);
+
+        let block_commitment = client.block_commitment(42).unwrap();
+        assert_eq!(block_commitment.commitment, Some(commitment));
+        assert_eq!(block_commitment.total_stake, 50);
+        mock.assert_called_with("getBlockCommitment", &json!([42]));
+    }
+
+    #[test]
+    fn confirmed_transaction_meta_is_checked_against_the_predicate() {
+        let signature = Signature::new_unique();
+        let mock = rpc_mock()
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([transaction_status(None)])),
+            )
+            .with_response("getTransaction", confirmed_transaction(transaction_meta(5000)));
+        let client = mock_client(&mock);
+
+        assert!(client
+            .confirm_and_verify(&signature, CommitmentConfig::confirmed(), |meta| meta.fee == 5000)
+            .is_ok());
+        let err = client
+            .confirm_and_verify(&signature, CommitmentConfig::confirmed(), |meta| meta.fee == 0)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not match expectations"), "{}", err);
+    }
+
+    #[test]
+    fn unconfirmed_transaction_fails_the_verification() {
+        let signature = Signature::new_unique();
+        let mock = rpc_mock()
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([status_at("processed")])),
+            )
+            .with_response("getTransaction", confirmed_transaction(transaction_meta(5000)));
+        let client = mock_client(&mock);
+
+        let err = client
+            .confirm_and_verify(&signature, CommitmentConfig::confirmed(), |_| true)
+            .unwrap_err();
+        assert!(err.to_string().contains("unable to confirm"), "{}", err);
+        mock.assert_not_called("getTransaction");
+
+        assert!(client
+            .confirm_and_verify(&signature, CommitmentConfig::processed(), |meta| meta.status.is_ok())
+            .is_ok());
+        let params = &mock.calls_to("getTransaction")[0];
+        assert_eq!(params[1]["commitment"], "confirmed");
+    }
+
+    #[test]
+    fn performance_samples_give_the_transactions_per_second() {
+        let mock = rpc_mock().with_response(
+            "getRecentPerformanceSamples",
+            json!([
+                {"slot": 200, "numTransactions": 6000, "numSlots": 150, "samplePeriodSecs": 60},
+                {"slot": 50, "numTransactions": 0, "numSlots": 0, "samplePeriodSecs": 0},
+            ]),
+        );
+        let client = mock_client(&mock);
+
+        let samples = client.recent_performance_samples(Some(2)).unwrap();
+        mock.assert_called_with("getRecentPerformanceSamples", &json!([2]));
+        assert_eq!(samples.len(), 2);
+        assert_eq!(transactions_per_second(&samples[0]), 100.0);
+        assert_eq!(transactions_per_second(&samples[1]), 0.0);
+    }
+
+    #[test]
+    fn tracked_transactions_are_confirmed_through_the_handle() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let signature = transaction.signatures[0];
+        let mock = rpc_mock()
+            .with_response("sendTransaction", json!(signature.to_string()))
+            .with_response("isBlockhashValid", context_response(1, json!(true)))
+            .with_response(
+                "getSignatureStatuses",
+                context_response(5, json!([transaction_status(None)])),
+            );
+        mock.push_response("getSignatureStatuses", Ok(context_response(1, json!([null]))));
+        let client = mock_client(&mock);
+
+        let tracked = client.send_tracked(&transaction).unwrap();
+        assert_eq!(tracked, TrackedSignature {
+            signature,
+            recent_blockhash: transaction.message.recent_blockhash,
+        });
+        mock.assert_not_called("getSignatureStatuses");
+
+        assert_eq!(tracked.confirm(&client).unwrap(), signature);
+        assert_eq!(mock.call_count("getSignatureStatuses"), 2);
+    }
+
+    #[test]
+    fn minimum_ledger_slot_is_fetched() {
+        let mock = rpc_mock().with_response("minimumLedgerSlot", json!(1234));
+        let client = mock_client(&mock);
+
+        assert_eq!(client.minimum_ledger_slot().unwrap(), 1234);
+        mock.assert_called_with("minimumLedgerSlot", &Value::Null);
+    }
+
+    #[test]
+    fn durable_nonce_blockhash_is_fetched_at_the_configured_commitment() {
+        let payer = Keypair::new();
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                system_instruction::advance_nonce_account(&Pubkey::new_unique(), &payer.pubkey()),
+                system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::new_unique(),
+        );
+        let blockhash = Hash::new_unique();
+        let mock = rpc_mock()
+            .with_response("sendTransaction", json!(transaction.signatures[0].to_string()))
+            .with_response(
+                "getLatestBlockhash",
+                context_response(
+                    1,
+                    json!({"blockhash": blockhash.to_string(), "lastValidBlockHeight": 300}),
+                ),
+            );
+        let client = mock_client(&mock).with_nonce_blockhash_commitment(CommitmentConfig::finalized());
+
+        let tracked = client.send_tracked(&transaction).unwrap();
+        assert_eq!(tracked.recent_blockhash, blockhash);
+        assert_eq!(mock.calls_to("getLatestBlockhash"), [
+            json!([{ "commitment": "finalized" }])
+        ]);
+    }
+
+    #[test]
+    fn nonce_advance_is_polled_until_the_blockhash_changes() {
+        let nonce_pubkey = Pubkey::new_unique();
+        let previous = Hash::new_unique();
+        let advanced = Hash::new_unique();
+        let mock = rpc_mock();
+        for blockhash in [previous, advanced] {
+            mock.push_response(
+                "getAccountInfo",
+                Ok(context_response(1, ui_account(&nonce_account(blockhash)))),
+            );
+        }
+        let client = mock_client(&mock);
+
+        let blockhash = client
+            .wait_for_nonce_advance(&nonce_pubkey, &previous, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(blockhash, advanced);
+        assert_eq!(mock.call_count("getAccountInfo"), 2);
+    }
+
+    #[test]
+    fn session_summary_reflects_the_issued_requests() {
+        let client = http_client(|method, _| match method {
+            "getSlot" => Ok(json!(10)),
+            _ => Err(json!({ "code": -32005, "message": "Node is behind" })),
+        });
+
+        client.get_slot().unwrap();
+        client.get_slot().unwrap();
+        assert!(client.get_balance(&Pubkey::new_unique()).is_err());
+
+        let summary = client.session_summary();
+        assert_eq!(summary.method_counts["getSlot"], 2);
+        assert_eq!(summary.method_counts["getBalance"], 1);
+        assert_eq!(
+            summary.request_count as u64,
+            summary.method_counts.values().sum::<u64>()
+        );
+        assert_eq!(summary.error_count, 1);
+        assert!(summary.max_latency <= summary.total_latency);
+        assert_eq!(
+            summary.average_latency,
+            summary.total_latency / summary.request_count as u32
+        );
+    }
+
+    #[test]
+    fn account_data_is_decoded_with_the_configured_decoder() {
+        #[derive(Default)]
+        struct RecordingDecoder(Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl DataDecoder for RecordingDecoder {
+            fn decode_base58(&self, data: &str) -> Result<Vec<u8>, String> {
+                self.0.lock().unwrap().push(format!("base58 {}", data));
+                DefaultDataDecoder.decode_base58(data)
+            }
+
+            fn decode_base64(&self, data: &str) -> Result<Vec<u8>, String> {
+                self.0.lock().unwrap().push(format!("base64 {}", data));
+                DefaultDataDecoder.decode_base64(data)
+            }
+        }
+
+        let account = Account {
+            lamports: 1,
+            data: vec![1, 2, 3],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response("getAccountInfo", context_response(1, ui_account(&account)));
+        let decoder = RecordingDecoder::default();
+        let decoded = decoder.0.clone();
+        let client = mock_client(&mock).with_data_decoder(decoder);
+
+        let data = client
+            .get_account_data_with_encoding(&Pubkey::new_unique(), UiAccountEncoding::Base64)
+            .unwrap();
+        assert_eq!(data, Some(vec![1, 2, 3]));
+        assert_eq!(*decoded.lock().unwrap(), ["base64 AQID"]);
+    }
+
+    #[test]
+    fn slots_are_mapped_to_epochs_with_the_cluster_schedule() {
+        let mock = rpc_mock().with_response(
+            "getEpochSchedule",
+            json!({
+                "slotsPerEpoch": 8192,
+                "leaderScheduleSlotOffset": 8192,
+                "warmup": true,
+                "firstNormalEpoch": 8,
+                "firstNormalSlot": 8160,
+            }),
+        );
+        let client = mock_client(&mock);
+
+        assert_eq!(client.epoch_schedule().unwrap(), EpochSchedule::new(8192));
+        for (slot, epoch) in [
+            (0, 0),
+            (31, 0),
+            (32, 1),
+            (95, 1),
+            (96, 2),
+            (8159, 7),
+            (8160, 8),
+            (16352, 9),
+        ] {
+            assert_eq!(client.epoch_of_slot(slot).unwrap(), epoch, "slot {}", slot);
+        }
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn account_data_is_deserialized_after_the_discriminator_with_borsh() {
+        #[derive(Debug, PartialEq, BorshDeserialize)]
+        struct Counter {
+            count: u64,
+            bump: u8,
+        }
+
+        let mut data = vec![0xAA; 8];
+        data.extend_from_slice(&42_u64.to_le_bytes());
+        data.push(254);
+        let account = Account {
+            lamports: 1,
+            data,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response("getAccountInfo", context_response(1, ui_account(&account)));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(
+            client.get_account_borsh::<Counter>(&pubkey, 8).unwrap(),
+            Some(Counter { count: 42, bump: 254 })
+        );
+        assert!(client.get_account_borsh::<Counter>(&pubkey, 64).is_err());
+
+        mock.set_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        assert_eq!(client.get_account_borsh::<Counter>(&pubkey, 8).unwrap(), None);
+    }
+
+    #[test]
+    fn blockhash_expiry_eta_counts_the_blocks_left() {
+        let mock = rpc_mock().with_response("getBlockHeight", json!(200));
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client.blockhash_expiry_eta(250).unwrap(),
+            Duration::from_millis(50 * DEFAULT_MS_PER_SLOT)
+        );
+        assert_eq!(client.blockhash_expiry_eta(150).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn commitment_timings_are_monotonic() {
+        let mock = rpc_mock();
+        mock.push_response("getSignatureStatuses", Ok(context_response(1, json!([null]))));
+        for level in ["processed", "confirmed", "finalized"] {
+            mock.push_response(
+                "getSignatureStatuses",
+                Ok(context_response(1, json!([status_at(level)]))),
+            );
+        }
+        let client = mock_client(&mock).with_sleeper(|_| std::thread::sleep(Duration::from_millis(2)));
+
+        let timings = client.confirm_with_timings(&Signature::new_unique()).unwrap();
+        assert!(timings.processed < timings.confirmed, "{:?}", timings);
+        assert!(timings.confirmed < timings.finalized, "{:?}", timings);
+        assert_eq!(mock.call_count("getSignatureStatuses"), 4);
+    }
+
+    #[test]
+    fn token_largest_accounts_are_fetched_for_the_mint() {
+        let mint = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let mock = rpc_mock().with_response(
+            "getTokenLargestAccounts",
+            context_response(
+                1,
+                json!([{
+                    "address": holder.to_string(),
+                    "amount": "1000",
+                    "decimals": 2,
+                    "uiAmount": 10.0,
+                    "uiAmountString": "10",
+                }]),
+            ),
+        );
+        let client = mock_client(&mock);
+
+        let accounts = client.token_largest_accounts(&mint).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].address, holder.to_string());
+        assert_eq!(accounts[0].amount.amount, "1000");
+        assert_eq!(accounts[0].amount.decimals, 2);
+        mock.assert_called_with(
+            "getTokenLargestAccounts",
+            &json!([mint.to_string(), { "commitment": "confirmed" }]),
+        );
+    }
+
+    #[test]
+    fn blocks_are_fetched_with_the_requested_detail_level() {
+        let signature = Signature::new_unique();
+        let block = |details: Value| {
+            let mut block = json!({
+                "previousBlockhash": Hash::new_unique().to_string(),
+                "blockhash": Hash::new_unique().to_string(),
+                "parentSlot": 9,
+                "blockTime": null,
+                "blockHeight": 8,
+            });
+            block
+                .as_object_mut()
+                .unwrap()
+                .extend(details.as_object().unwrap().clone());
+            block
+        };
+        let mock = rpc_mock();
+        mock.push_response("getBlock", Ok(block(json!({ "signatures": [signature.to_string()] }))));
+        mock.push_response("getBlock", Ok(block(json!({}))));
+        let client = mock_client(&mock);
+
+        let with_signatures = client
+            .get_block_with_detail(10, TransactionDetails::Signatures)
+            .unwrap();
+        assert_eq!(with_signatures.signatures, Some(vec![signature.to_string()]));
+        assert!(with_signatures.transactions.is_none());
+
+        let without_details = client.get_block_with_detail(10, TransactionDetails::None).unwrap();
+        assert!(without_details.signatures.is_none());
+        assert!(without_details.transactions.is_none());
+
+        let details: Vec<_> = mock
+            .calls_to("getBlock")
+            .iter()
+            .map(|params| params[1]["transactionDetails"].clone())
+            .collect();
+        assert_eq!(details, [json!("signatures"), json!("none")]);
+    }
+
+    #[test]
+    fn confirmed_transaction_is_followed_by_the_account_fetch() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let account = Account {
+            lamports: 1,
+            data: vec![],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let fetched = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mock = landing_mock(&transaction).with_response(
+            "getMultipleAccounts",
+            context_response(6, json!([ui_account(&account), null])),
+        );
+        let client = mock_client(&mock);
+
+        let (signature, accounts) = client.send_confirm_and_fetch(&transaction, &fetched).unwrap();
+        assert_eq!(signature, transaction.signatures[0]);
+        assert_eq!(accounts, [Some(account), None]);
+        assert_eq!(
+            mock.calls_to("getMultipleAccounts")[0][0],
+            json!(fetched.map(|pubkey| pubkey.to_string()))
+        );
+    }
+
+    #[test]
+    fn unsupported_methods_fall_back_to_the_next_one() {
+        let mock = rpc_mock()
+            .with_error(
+                "getPriorityFeeEstimate",
+                MockError::rpc(JSON_RPC_METHOD_NOT_FOUND, "Method not found"),
+            )
+            .with_response(
+                "getRecentPrioritizationFees",
+                json!([{"slot": 1, "prioritizationFee": 10}]),
+            )
+            .with_error("getSlot", MockError::rpc(-32005, "Node is behind"));
+        let client = mock_client(&mock);
+
+        let result = client
+            .try_methods(&[
+                ("getPriorityFeeEstimate", json!([])),
+                ("getRecentPrioritizationFees", json!([])),
+            ])
+            .unwrap();
+        assert_eq!(result[0]["prioritizationFee"], 10);
+
+        // Other errors don't fall back
+        let err = client
+            .try_methods(&[("getSlot", Value::Null), ("getRecentPrioritizationFees", json!([]))])
+            .unwrap_err();
+        assert!(is_node_unhealthy(&err));
+        assert_eq!(mock.call_count("getRecentPrioritizationFees"), 1);
+
+        let err = client
+            .try_methods(&[("getPriorityFeeEstimate", json!([]))])
+            .unwrap_err();
+        assert!(is_method_not_found(&err));
+    }
+
+    #[test]
+    fn prewarm_orders_the_endpoints_from_the_fastest() {
+        let sender = HttpSender::new("http://primary")
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport(|request| {
+                let latency = match request.url.as_str() {
+                    "http://fast" => 0,
+                    "http://primary" => 20,
+                    "http://slow" => 40,
+                    _ => return Err("connection refused".to_string()),
+                };
+                std::thread::sleep(Duration::from_millis(latency));
+                Ok(HostHttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: br#"{"jsonrpc":"2.0","id":0,"result":"ok"}"#.to_vec(),
+                })
+            });
+        let client = WasmRpcClient::with_http_sender(sender, RpcClientConfig::default()).with_endpoints([
+            "http://slow",
+            "http://down",
+            "http://fast",
+        ]);
+        assert!(client.endpoint_latencies().is_empty());
+
+        let latencies = client.prewarm().unwrap();
+        let urls: Vec<_> = latencies.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(urls, ["http://fast", "http://primary", "http://slow"]);
+        assert_eq!(client.endpoint_latencies(), latencies);
+
+        let client = WasmRpcClient::with_http_sender(
+            HttpSender::new("http://down").with_transport(|_| Err("connection refused".to_string())),
+            RpcClientConfig::default(),
+        );
+        assert!(client.prewarm().is_err());
+    }
+
+    #[test]
+    fn only_executable_accounts_are_programs() {
+        let account = |executable| Account {
+            lamports: 1,
+            data: vec![],
+            owner: Pubkey::new_unique(),
+            executable,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock();
+        mock.push_response("getAccountInfo", Ok(context_response(1, ui_account(&account(true)))));
+        mock.push_response("getAccountInfo", Ok(context_response(1, ui_account(&account(false)))));
+        mock.push_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert!(client.is_program(&pubkey).unwrap());
+        assert!(!client.is_program(&pubkey).unwrap());
+        assert!(!client.is_program(&pubkey).unwrap());
+    }
+
+    #[test]
+    fn slot_is_retried_while_the_node_is_behind() {
+        let mock = rpc_mock().with_response("getSlot", json!(321));
+        mock.push_response(
+            "getSlot",
+            Err(MockError::Rpc(json!({
+                "code": rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+                "message": "Node is behind by 42 slots",
+                "data": { "numSlotsBehind": 42 },
+            }))),
+        );
+        let client = mock_client(&mock);
+
+        assert_eq!(client.current_slot(CommitmentConfig::finalized()).unwrap(), 321);
+        assert_eq!(mock.calls_to("getSlot"), [
+            json!([{ "commitment": "finalized" }]),
+            json!([{ "commitment": "finalized" }])
+        ]);
+
+        mock.set_response("getSlot", Err(MockError::rpc(-32602, "Invalid params")));
+        assert!(client.current_slot(CommitmentConfig::finalized()).is_err());
+        assert_eq!(mock.call_count("getSlot"), 3);
+    }
+
+    #[test]
+    fn balance_is_polled_until_it_reaches_the_minimum() {
+        let mock = rpc_mock().with_response("getBalance", context_response(3, json!(2_500)));
+        mock.push_response("getBalance", Ok(context_response(1, json!(0))));
+        mock.push_response("getBalance", Ok(context_response(2, json!(900))));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(
+            client
+                .wait_for_min_balance(&pubkey, 1_000, Duration::from_secs(60))
+                .unwrap(),
+            2_500
+        );
+        assert_eq!(mock.call_count("getBalance"), 3);
+
+        let err = client.wait_for_min_balance(&pubkey, 5_000, Duration::ZERO).unwrap_err();
+        assert!(matches!(err.kind(), ClientErrorKind::RpcError(RpcError::ForUser(_))));
+        assert_eq!(mock.call_count("getBalance"), 4);
+    }
+
+    #[test]
+    fn consumed_compute_units_are_read_from_the_meta() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mut meta = transaction_meta(5000);
+        meta["computeUnitsConsumed"] = json!(1_850);
+        let mock = landing_mock(&transaction).with_response("getTransaction", confirmed_transaction(meta));
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client.send_confirm_and_units(&transaction).unwrap(),
+            (transaction.signatures[0], 1_850)
+        );
+
+        // Nodes before 1.10 don't report the consumed units
+        mock.set_response("getTransaction", Ok(confirmed_transaction(transaction_meta(5000))));
+        let err = client.send_confirm_and_units(&transaction).unwrap_err();
+        assert!(err.to_string().contains("no consumed compute units"), "{}", err);
+    }
+
+    #[test]
+    fn account_keys_include_the_addresses_loaded_from_lookup_tables() {
+        let keys: Vec<_> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let key_strings: Vec<_> = keys.iter().map(Pubkey::to_string).collect();
+        let mut meta = transaction_meta(5000);
+        meta["loadedAddresses"] = json!({"writable": [key_strings[2]], "readonly": [key_strings[3], key_strings[4]]});
+        let transaction = json!({
+            "slot": 5,
+            "version": 0,
+            "transaction": {
+                "signatures": [Signature::new_unique().to_string()],
+                "message": {
+                    "accountKeys": [key_strings[0], key_strings[1]],
+                    "addressTableLookups": [{
+                        "accountKey": Pubkey::new_unique().to_string(),
+                        "writableIndexes": [0],
+                        "readonlyIndexes": [1, 2],
+                    }],
+                },
+            },
+            "meta": meta,
+            "blockTime": null,
+        });
+        let mock = rpc_mock().with_response("getTransaction", transaction);
+        let client = mock_client(&mock);
+        let signature = Signature::new_unique();
+
+        assert_eq!(client.resolved_account_keys(&signature).unwrap(), keys);
+        assert_eq!(
+            mock.calls_to("getTransaction")[0][1]["maxSupportedTransactionVersion"],
+            0
+        );
+    }
+
+    #[test]
+    fn rent_epoch_is_read_from_the_account() {
+        let account = Account {
+            lamports: 1,
+            data: vec![],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 361,
+        };
+        let mock = rpc_mock();
+        mock.push_response("getAccountInfo", Ok(context_response(1, ui_account(&account))));
+        mock.push_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(client.rent_epoch(&pubkey).unwrap(), Some(361));
+        assert_eq!(client.rent_epoch(&pubkey).unwrap(), None);
+    }
+
+    #[test]
+    fn transaction_is_rebuilt_when_its_blockhash_is_not_found() {
+        let payer = Keypair::new();
+        let blockhashes = [Hash::new_unique(), Hash::new_unique()];
+        let latest_blockhash = |blockhash: Hash| {
+            context_response(
+                10,
+                json!({"blockhash": blockhash.to_string(), "lastValidBlockHeight": 160}),
+            )
+        };
+        let rebuilt = transfer(&payer, blockhashes[1]);
+        let mock = landing_mock(&rebuilt).with_response("getLatestBlockhash", latest_blockhash(blockhashes[1]));
+        mock.push_response("getLatestBlockhash", Ok(latest_blockhash(blockhashes[0])));
+        mock.push_response(
+            "sendTransaction",
+            Err(MockError::rpc(
+                -32002,
+                "Transaction simulation failed: Blockhash not found",
+            )),
+        );
+        let client = mock_client(&mock);
+
+        let mut built = Vec::new();
+        let signature = client
+            .send_and_confirm_with_rebuild(|blockhash| {
+                built.push(blockhash);
+                Ok(transfer(&payer, blockhash))
+            })
+            .unwrap();
+        assert_eq!(signature, rebuilt.signatures[0]);
+        assert_eq!(built, blockhashes);
+        assert_eq!(mock.call_count("sendTransaction"), 2);
+    }
+
+    #[test]
+    fn epoch_change_is_awaited() {
+        let epoch_info = |epoch: Epoch, slot_index: u64| {
+            json!({
+                "absoluteSlot": epoch * 432_000 + slot_index,
+                "blockHeight": epoch * 400_000 + slot_index,
+                "epoch": epoch,
+                "slotIndex": slot_index,
+                "slotsInEpoch": 432_000,
+                "transactionCount": null,
+            })
+        };
+        let mock = rpc_mock().with_response("getEpochInfo", epoch_info(301, 2));
+        mock.push_response("getEpochInfo", Ok(epoch_info(300, 431_990)));
+        mock.push_response("getEpochInfo", Ok(epoch_info(300, 431_995)));
+        let client = mock_client(&mock);
+
+        assert_eq!(client.wait_for_next_epoch(Duration::from_secs(60)).unwrap(), 301);
+        assert_eq!(mock.call_count("getEpochInfo"), 3);
+
+        let err = client.wait_for_next_epoch(Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("epoch 301 has not ended"), "{}", err);
+    }
+
+    #[test]
+    fn accounts_are_cross_checked_between_endpoints() {
+        let account = |lamports| Account {
+            lamports,
+            data: vec![1, 2, 3],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response("getAccountInfo", context_response(1, ui_account(&account(10))));
+        let other_mock = rpc_mock().with_response("getAccountInfo", context_response(1, ui_account(&account(10))));
+        let client = mock_client(&mock);
+        let other = mock_client(&other_mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert!(client.cross_check_account(&other, &pubkey).unwrap());
+
+        other_mock.set_response("getAccountInfo", Ok(context_response(1, ui_account(&account(11)))));
+        assert!(!client.cross_check_account(&other, &pubkey).unwrap());
+
+        other_mock.set_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        assert!(!client.cross_check_account(&other, &pubkey).unwrap());
+        mock.set_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        assert!(client.cross_check_account(&other, &pubkey).unwrap());
+    }
+
+    #[test]
+    fn program_accounts_are_filtered_by_memcmp() {
+        let program_id = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let account = Account {
+            lamports: 1_000,
+            data: vec![0, 0, 0, 0, 9, 8, 7],
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response(
+            "getProgramAccounts",
+            json!([{ "pubkey": pubkey.to_string(), "account": ui_account(&account) }]),
+        );
+        let client = mock_client(&mock);
+
+        assert_eq!(client.program_accounts_memcmp(&program_id, 4, &[9, 8, 7]).unwrap(), [(
+            pubkey, account
+        )]);
+        let params = &mock.calls_to("getProgramAccounts")[0];
+        assert_eq!(params[0], program_id.to_string());
+        assert_eq!(
+            params[1]["filters"],
+            json!([{ "memcmp": { "offset": 4, "bytes": bs58::encode([9, 8, 7]).into_string() } }])
+        );
+    }
+
+    #[test]
+    fn method_availability_follows_the_node_version() {
+        let new_mock = MockSender::new().with_response("getVersion", json!({ "solana-core": "1.10.2" }));
+        let new_client = mock_client(&new_mock);
+        assert!(new_client
+            .method_available_by_version("getBlocksWithLimit", "1.10.0")
+            .unwrap());
+        assert!(new_client
+            .method_available_by_version("getBlocksWithLimit", "1.10.0")
+            .unwrap());
+        assert!(!new_client
+            .method_available_by_version("getRecentPrioritizationFees", "1.12.0")
+            .unwrap());
+        assert_eq!(new_mock.call_count("getVersion"), 1);
+
+        let old_mock = MockSender::new().with_response("getVersion", json!({ "solana-core": "1.9.29" }));
+        let old_client = mock_client(&old_mock);
+        assert!(!old_client
+            .method_available_by_version("getBlocksWithLimit", "1.10.0")
+            .unwrap());
+        assert!(old_client
+            .method_available_by_version("getBlockHeight", "1.7.0")
+            .unwrap());
+    }
+
+    #[test]
+    fn new_slots_are_passed_until_the_callback_stops() {
+        let mock = rpc_mock().with_response("getSlot", json!(104));
+        for slot in [100, 100, 101, 103] {
+            mock.push_response("getSlot", Ok(json!(slot)));
+        }
+        let client = mock_client(&mock);
+
+        let mut slots = Vec::new();
+        client
+            .poll_slots(|slot| {
+                slots.push(slot);
+                slots.len() < 3
+            })
+            .unwrap();
+        assert_eq!(slots, [100, 101, 103]);
+        assert_eq!(mock.call_count("getSlot"), 4);
+    }
+
+    #[test]
+    fn landed_transactions_are_detected_without_sending() {
+        let mock = rpc_mock().with_response(
+            "getSignatureStatuses",
+            context_response(5, json!([status_at("confirmed")])),
+        );
+        let client = mock_client(&mock);
+        let signature = Signature::new_unique();
+
+        assert!(client
+            .is_signature_confirmed(&signature, CommitmentConfig::confirmed())
+            .unwrap());
+        assert!(!client
+            .is_signature_confirmed(&signature, CommitmentConfig::finalized())
+            .unwrap());
+
+        mock.set_response(
+            "getSignatureStatuses",
+            Ok(context_response(5, json!([status_at("processed")]))),
+        );
+        assert!(!client
+            .is_signature_confirmed(&signature, CommitmentConfig::confirmed())
+            .unwrap());
+
+        mock.set_response("getSignatureStatuses", Ok(context_response(5, json!([null]))));
+        assert!(!client
+            .is_signature_confirmed(&signature, CommitmentConfig::processed())
+            .unwrap());
+        mock.assert_not_called("sendTransaction");
+    }
+
+    #[test]
+    fn transactions_are_validated_before_sending() {
+        let client = mock_client(&rpc_mock());
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+
+        assert!(client.validate_transaction(&transaction, &payer.pubkey()).is_ok());
+
+        let err = client
+            .validate_transaction(&transaction, &Pubkey::new_unique())
+            .unwrap_err();
+        assert!(err.to_string().contains("fee payer"), "{}", err);
+
+        let mut forged = transaction.clone();
+        forged.signatures[0] = Keypair::new().sign_message(&forged.message_data());
+        let err = client.validate_transaction(&forged, &payer.pubkey()).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::TransactionError(TransactionError::SignatureFailure)
+        ));
+
+        let unsigned = Transaction::new_unsigned(transaction.message.clone());
+        let err = client.validate_transaction(&unsigned, &payer.pubkey()).unwrap_err();
+        assert!(
+            err.to_string().contains(&format!("not signed by {}", payer.pubkey())),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn broadcast_succeeds_if_any_endpoint_accepts_the_transaction() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let signature = transaction.signatures[0].to_string();
+        let broadcast_urls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://primary")
+            .with_retry_policy(RetryPolicy::none())
+            .with_transport({
+                let broadcast_urls = broadcast_urls.clone();
+                move |request| {
+                    let body: Value = serde_json::from_slice(&request.body).unwrap();
+                    let response = match (body["method"].as_str().unwrap(), request.url.as_str()) {
+                        ("getVersion", _) => json!({ "id": body["id"], "result": { "solana-core": NODE_VERSION } }),
+                        (_, url) => {
+                            broadcast_urls
+                                .lock()
+                                .unwrap()
+                                .push((url.to_string(), body["params"][1].clone()));
+                            match url {
+                                "http://accepting" => json!({ "id": body["id"], "result": signature }),
+                                _ => {
+                                    json!({ "id": body["id"], "error": { "code": -32002, "message": "Node is busy" } })
+                                },
+                            }
+                        },
+                    };
+                    Ok(HostHttpResponse {
+                        status: 200,
+                        headers: Vec::new(),
+                        body: response.to_string().into_bytes(),
+                    })
+                }
+            });
+        let client = WasmRpcClient::with_http_sender(sender, RpcClientConfig::default())
+            .with_endpoints(["http://rejecting", "http://accepting"]);
+
+        assert_eq!(
+            client.broadcast_transaction(&transaction).unwrap(),
+            transaction.signatures[0]
+        );
+        let broadcasts = broadcast_urls.lock().unwrap().clone();
+        let urls: Vec<_> = broadcasts.iter().map(|(url, _)| url.as_str()).collect();
+        assert_eq!(urls, ["http://primary", "http://rejecting", "http://accepting"]);
+        // Every endpoint is sent the config of the primary one
+        for (_, config) in &broadcasts {
+            assert_eq!(config, &broadcasts[0].1);
+        }
+        assert_eq!(broadcasts[0].1["preflightCommitment"], "finalized");
+
+        let client = client.with_endpoints(["http://rejecting"]);
+        let err = client.broadcast_transaction(&transaction).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code: -32002, .. })
+        ));
+    }
+
+    #[test]
+    fn parsed_account_info_is_returned() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mock = rpc_mock();
+        mock.push_response(
+            "getAccountInfo",
+            Ok(context_response(1, parsed_token_account(&owner, &mint, 1_500_000))),
+        );
+        mock.push_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        let info = client.get_account_parsed(&pubkey).unwrap().unwrap();
+        assert_eq!(info["owner"], owner.to_string());
+        assert_eq!(info["mint"], mint.to_string());
+        assert_eq!(info["tokenAmount"]["amount"], "1500000");
+        assert_eq!(mock.calls_to("getAccountInfo")[0][1]["encoding"], "jsonParsed");
+
+        assert_eq!(client.get_account_parsed(&pubkey).unwrap(), None);
+    }
+
+    #[test]
+    fn rent_exemption_compares_the_balance_with_the_cached_minimum() {
+        let account = |lamports| Account {
+            lamports,
+            data: vec![0; 10],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response("getMinimumBalanceForRentExemption", json!(960_480));
+        mock.push_response(
+            "getAccountInfo",
+            Ok(context_response(1, ui_account(&account(1_000_000)))),
+        );
+        mock.push_response("getAccountInfo", Ok(context_response(1, ui_account(&account(900_000)))));
+        mock.push_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(client.is_rent_exempt(&pubkey).unwrap(), Some(true));
+        assert_eq!(client.is_rent_exempt(&pubkey).unwrap(), Some(false));
+        assert_eq!(client.is_rent_exempt(&pubkey).unwrap(), None);
+        assert_eq!(mock.calls_to("getMinimumBalanceForRentExemption"), [json!([10])]);
+    }
+
+    #[test]
+    fn already_processed_sends_are_reported_as_duplicates() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mock = landing_mock(&transaction);
+        mock.push_response(
+            "sendTransaction",
+            Err(MockError::rpc(
+                -32002,
+                "Transaction simulation failed: This transaction has already been processed",
+            )),
+        );
+        let client = mock_client(&mock);
+
+        let outcome = client.send_and_confirm_detecting_duplicate(&transaction).unwrap();
+        assert_eq!(outcome.signature, transaction.signatures[0]);
+        assert!(outcome.was_already_processed);
+
+        let outcome = client.send_and_confirm_detecting_duplicate(&transaction).unwrap();
+        assert!(!outcome.was_already_processed);
+    }
+
+    #[test]
+    fn overall_deadline_bounds_a_confirmation_that_never_comes() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mock = landing_mock(&transaction).with_response("getSignatureStatuses", context_response(5, json!([null])));
+        let client = mock_client(&mock).with_overall_deadline(Some(Duration::from_millis(50)));
+
+        let started = Instant::now();
+        let err = client.send_and_confirm_transaction(&transaction).unwrap_err();
+        assert!(err.to_string().contains("overall deadline"), "{}", err);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(mock.call_count("sendTransaction"), 1);
+        assert!(mock.call_count("getSignatureStatuses") > 0);
+    }
+
+    #[test]
+    fn status_is_queried_at_each_commitment() {
+        let mock = rpc_mock();
+        for level in ["processed", "confirmed", "confirmed"] {
+            mock.push_response(
+                "getSignatureStatuses",
+                Ok(context_response(5, json!([status_at(level)]))),
+            );
+        }
+        let client = mock_client(&mock);
+
+        assert_eq!(client.status_across_commitments(&Signature::new_unique()).unwrap(), [
+            Some(TransactionConfirmationStatus::Processed),
+            Some(TransactionConfirmationStatus::Confirmed),
+            None
+        ]);
+        assert_eq!(mock.call_count("getSignatureStatuses"), 3);
+    }
+
+    #[test]
+    fn failed_sends_are_retried_after_the_resend_delay() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mock = landing_mock(&transaction);
+        mock.push_response(
+            "sendTransaction",
+            Err(MockError::Transport("connection reset".to_string())),
+        );
+        let sleeps = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = mock_client(&mock)
+            .with_send_retries(2, Duration::from_millis(300))
+            .with_sleeper({
+                let sleeps = sleeps.clone();
+                move |duration| sleeps.lock().unwrap().push(duration)
+            });
+
+        assert_eq!(
+            client.send_and_confirm_transaction(&transaction).unwrap(),
+            transaction.signatures[0]
+        );
+        assert_eq!(mock.call_count("sendTransaction"), 2);
+        assert_eq!(*sleeps.lock().unwrap(), [Duration::from_millis(300)]);
+    }
+
+    #[test]
+    fn commitment_is_recommended_from_the_voted_stake() {
+        let block_commitment = |voted_stake: u64| {
+            let mut commitment = [0_u64; 32];
+            commitment[31] = voted_stake;
+            json!({"commitment": commitment, "totalStake": 100})
+        };
+        let mock = rpc_mock().with_response("getSlot", json!(42));
+        mock.push_response("getBlockCommitment", Ok(block_commitment(85)));
+        mock.push_response("getBlockCommitment", Ok(block_commitment(60)));
+        mock.push_response("getBlockCommitment", Ok(json!({"commitment": null, "totalStake": 0})));
+        let client = mock_client(&mock);
+
+        assert_eq!(client.recommend_commitment().unwrap(), CommitmentConfig::confirmed());
+        assert_eq!(client.recommend_commitment().unwrap(), CommitmentConfig::finalized());
+        assert_eq!(client.recommend_commitment().unwrap(), CommitmentConfig::finalized());
+        mock.assert_called_with("getBlockCommitment", &json!([42]));
+    }
+
+    #[test]
+    fn names_are_resolved_with_the_extension_method() {
+        let address = Pubkey::new_unique();
+        let mock = rpc_mock().with_response("sns_resolveDomain", json!(address.to_string()));
+        let client = mock_client(&mock).with_name_resolution_method("sns_resolveDomain");
+
+        assert_eq!(client.resolve_name("lapp.sol").unwrap(), address);
+        mock.assert_called_with("sns_resolveDomain", &json!(["lapp.sol"]));
+
+        let mock = rpc_mock().with_error(
+            DEFAULT_NAME_RESOLUTION_METHOD,
+            MockError::rpc(JSON_RPC_METHOD_NOT_FOUND, "Method not found"),
+        );
+        let err = mock_client(&mock).resolve_name("lapp.sol").unwrap_err();
+        assert!(err.to_string().contains("doesn't support name resolution"), "{}", err);
+    }
+
+    #[test]
+    fn batch_confirmation_counts_landed_failed_and_pending_transactions() {
+        let mock = rpc_mock().with_response("getSignatureStatuses", context_response(6, json!([null])));
+        mock.push_response(
+            "getSignatureStatuses",
+            Ok(context_response(
+                5,
+                json!([
+                    transaction_status(None),
+                    null,
+                    transaction_status(Some(&TransactionError::AccountNotFound)),
+                ]),
+            )),
+        );
+        let client = mock_client(&mock).with_sleeper(|_| std::thread::sleep(Duration::from_millis(1)));
+        let signatures = [
+            Signature::new_unique(),
+            Signature::new_unique(),
+            Signature::new_unique(),
+        ];
+
+        let stats = client
+            .confirm_batch_with_stats(&signatures, Duration::from_millis(20))
+            .unwrap();
+        assert_eq!((stats.confirmed, stats.failed, stats.pending), (2, 1, 1));
+        assert!(stats.min_confirm_time <= stats.avg_confirm_time && stats.avg_confirm_time <= stats.max_confirm_time);
+        assert!(stats.max_confirm_time.is_some());
+        // Only the pending signature is polled again
+        assert_eq!(
+            mock.calls_to("getSignatureStatuses")[1][0],
+            json!([signatures[1].to_string()])
+        );
+    }
+
+    #[test]
+    fn undecodable_account_data_is_refetched_in_the_fallback_encoding() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let account = Account {
+            lamports: 2_039_280,
+            data: vec![5; 165],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock();
+        mock.push_response(
+            "getAccountInfo",
+            Ok(context_response(1, parsed_token_account(&owner, &mint, 10))),
+        );
+        mock.push_response("getAccountInfo", Ok(context_response(1, ui_account(&account))));
+        let client = mock_client(&mock);
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(
+            client
+                .get_account_data_with_encoding(&pubkey, UiAccountEncoding::JsonParsed)
+                .unwrap(),
+            Some(account.data)
+        );
+        let encodings: Vec<_> = mock
+            .calls_to("getAccountInfo")
+            .iter()
+            .map(|params| params[1]["encoding"].clone())
+            .collect();
+        assert_eq!(encodings, [json!("jsonParsed"), json!("base64")]);
+
+        mock.push_response(
+            "getAccountInfo",
+            Ok(context_response(1, parsed_token_account(&owner, &mint, 10))),
+        );
+        let client = client.with_fallback_account_encoding(None);
+        assert!(client
+            .get_account_data_with_encoding(&pubkey, UiAccountEncoding::JsonParsed)
+            .is_err());
+        assert_eq!(mock.call_count("getAccountInfo"), 3);
+    }
+
+    #[test]
+    fn pda_account_is_fetched_at_the_derived_address() {
+        let program_id = Pubkey::new_unique();
+        let seeds: &[&[u8]] = &[b"vault", &[7; 32]];
+        let account = Account {
+            lamports: 5_000,
+            data: vec![1, 2, 3],
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock().with_response("getAccountInfo", context_response(1, ui_account(&account)));
+        let client = mock_client(&mock);
+
+        let (address, fetched) = client.get_pda_account(seeds, &program_id).unwrap();
+        assert_eq!(address, Pubkey::find_program_address(seeds, &program_id).0);
+        assert_eq!(fetched, Some(account));
+        assert_eq!(mock.calls_to("getAccountInfo")[0][0], address.to_string());
+    }
+
+    #[test]
+    fn charged_fee_is_read_from_the_meta() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mock =
+            landing_mock(&transaction).with_response("getTransaction", confirmed_transaction(transaction_meta(10_000)));
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client.send_confirm_and_fee(&transaction).unwrap(),
+            (transaction.signatures[0], 10_000)
+        );
+        mock.assert_called("getTransaction");
+    }
+
+    #[test]
+    fn versioned_transactions_are_sent_and_confirmed() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let message = crate::alt::compile_v0_message(
+            &payer.pubkey(),
+            &[system_instruction::transfer(&payer.pubkey(), &recipient, 1)],
+            &[],
+            Hash::new_unique(),
+        )
+        .unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::new_unique()],
+            message,
+        };
+        let mock = rpc_mock()
+            .with_response("sendTransaction", json!(transaction.signatures[0].to_string()))
+            .with_response("isBlockhashValid", context_response(1, json!(true)))
+            .with_response("getSignatureStatuses", context_response(5, json!([null])));
+        mock.push_response("getSignatureStatuses", Ok(context_response(4, json!([null]))));
+        mock.push_response(
+            "getSignatureStatuses",
+            Ok(context_response(5, json!([transaction_status(None)]))),
+        );
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client.send_and_confirm_versioned(&transaction).unwrap(),
+            transaction.signatures[0]
+        );
+        assert_eq!(mock.call_count("getSignatureStatuses"), 2);
+        let params = &mock.calls_to("sendTransaction")[0];
+        assert_eq!(
+            params[0],
+            encode_transaction(&transaction, UiTransactionEncoding::Base64).unwrap()
+        );
+        mock.assert_not_called("getLatestBlockhash");
+    }
+
+    #[test]
+    fn transactions_are_sent_in_the_wire_encoding() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mock = landing_mock(&transaction);
+        let client = mock_client(&mock).with_tx_wire_encoding(UiTransactionEncoding::Base58);
+
+        client.send_tracked(&transaction).unwrap();
+        client
+            .send_tracked_with_options(&transaction, SendTransactionOptions::default())
+            .unwrap();
+
+        let base58_transaction = bs58::encode(bincode::serialize(&transaction).unwrap()).into_string();
+        for params in mock.calls_to("sendTransaction") {
+            assert_eq!(params[0], base58_transaction);
+            assert_eq!(params[1]["encoding"], "base58");
+        }
+        assert_eq!(mock.call_count("sendTransaction"), 2);
+    }
+
+    #[test]
+    fn finalization_timeout_downgrades_to_the_reached_commitment() {
+        let mock = rpc_mock().with_response(
+            "getSignatureStatuses",
+            context_response(5, json!([status_at("confirmed")])),
+        );
+        let signature = Signature::new_unique();
+
+        let client = mock_client(&mock).with_downgrade_on_timeout(true);
+        assert_eq!(
+            client.confirm_finalized(&signature, Duration::ZERO).unwrap(),
+            CommitmentConfig::confirmed()
+        );
+
+        let client = mock_client(&mock);
+        let err = client.confirm_finalized(&signature, Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("was not finalized"), "{}", err);
+
+        mock.set_response(
+            "getSignatureStatuses",
+            Ok(context_response(40, json!([status_at("finalized")]))),
+        );
+        assert_eq!(
+            client.confirm_finalized(&signature, Duration::ZERO).unwrap(),
+            CommitmentConfig::finalized()
+        );
+    }
+
+    #[test]
+    fn historical_floor_is_the_higher_of_both_bounds_and_cached() {
+        let mock = rpc_mock()
+            .with_response("getFirstAvailableBlock", json!(1_000))
+            .with_response("minimumLedgerSlot", json!(1_250));
+        let client = mock_client(&mock);
+        assert_eq!(client.historical_floor().unwrap(), 1_250);
+
+        mock.set_response("minimumLedgerSlot", Ok(json!(1_500)));
+        assert_eq!(client.historical_floor().unwrap(), 1_250);
+        assert_eq!(mock.call_count("minimumLedgerSlot"), 1);
+
+        let mock = rpc_mock()
+            .with_response("getFirstAvailableBlock", json!(2_000))
+            .with_response("minimumLedgerSlot", json!(1_250));
+        assert_eq!(mock_client(&mock).historical_floor().unwrap(), 2_000);
+    }
+
+    #[test]
+    fn finalized_state_is_returned_and_differences_reported() {
+        let account = |data: Vec<u8>| Account {
+            lamports: 1_000,
+            data,
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let (confirmed, finalized) = (account(vec![2]), account(vec![1]));
+        let mismatches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let client = http_client({
+            let (confirmed, finalized) = (confirmed.clone(), finalized.clone());
+            move |method, params| match (method, params[1]["commitment"].as_str()) {
+                ("getAccountInfo", Some("confirmed")) => Ok(context_response(12, ui_account(&confirmed))),
+                ("getAccountInfo", Some("finalized")) => Ok(context_response(10, ui_account(&finalized))),
+                _ => Err(json!({"code": -32601, "message": "Method not found"})),
+            }
+        })
+        .with_on_commitment_mismatch({
+            let mismatches = mismatches.clone();
+            move |pubkey, confirmed, finalized| {
+                mismatches
+                    .lock()
+                    .unwrap()
+                    .push((*pubkey, confirmed.cloned(), finalized.cloned()))
+            }
+        });
+        let pubkey = Pubkey::new_unique();
+
+        assert_eq!(
+            client.get_account_finalized_safe(&pubkey).unwrap(),
+            Some(finalized.clone())
+        );
+        assert_eq!(*mismatches.lock().unwrap(), [(
+            pubkey,
+            Some(confirmed),
+            Some(finalized)
+        )]);
+    }
+
+    #[test]
+    fn token_account_owner_mint_and_amount_are_parsed() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let mock = rpc_mock().with_response(
+            "getAccountInfo",
+            context_response(1, parsed_token_account(&owner, &mint, 42_000_000)),
+        );
+        let client = mock_client(&mock);
+        let token_account = Pubkey::new_unique();
+
+        assert_eq!(
+            client.token_account_info(&token_account).unwrap(),
+            (owner, mint, 42_000_000)
+        );
+
+        mock.set_response("getAccountInfo", Ok(context_response(1, Value::Null)));
+        let err = client.token_account_info(&token_account).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{}", err);
+    }
+
+    #[test]
+    fn token_balance_deltas_are_computed_from_the_meta() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::new_unique());
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let token_balance = |account_index: u8, amount: &str| {
+            json!({
+                "accountIndex": account_index,
+                "mint": mint.to_string(),
+                "owner": owner.to_string(),
+                "uiTokenAmount": {"amount": amount, "decimals": 6, "uiAmount": null, "uiAmountString": "0"},
+            })
+        };
+        let mut meta = transaction_meta(5000);
+        meta["preTokenBalances"] = json!([token_balance(1, "500")]);
+        meta["postTokenBalances"] = json!([token_balance(1, "300"), token_balance(0, "200")]);
+        let mock = landing_mock(&transaction).with_response("getTransaction", confirmed_transaction(meta));
+        let client = mock_client(&mock);
+
+        let (signature, deltas) = client.send_confirm_and_token_deltas(&transaction).unwrap();
+        assert_eq!(signature, transaction.signatures[0]);
+        let delta = |account_index: usize, delta| TokenBalanceDelta {
+            account_index,
+            account: Some(transaction.message.account_keys[account_index]),
+            mint,
+            owner: Some(owner),
+            decimals: 6,
+            delta,
+        };
+        assert_eq!(deltas, [delta(1, -200), delta(0, 200)]);
+    }
+
+    #[test]
+    fn missing_signers_of_a_partially_signed_transaction_are_listed() {
+        let client = mock_client(&rpc_mock());
+        let payer = Keypair::new();
+        let sender = Keypair::new();
+        let mut transaction = Transaction::new_with_payer(
+            &[system_instruction::transfer(&sender.pubkey(), &Pubkey::new_unique(), 1)],
+            Some(&payer.pubkey()),
+        );
+        assert_eq!(client.missing_signers(&transaction), [payer.pubkey(), sender.pubkey()]);
+
+        let blockhash = Hash::new_unique();
+        transaction.partial_sign(&[&payer], blockhash);
+        assert_eq!(client.missing_signers(&transaction), [sender.pubkey()]);
+
+        transaction.partial_sign(&[&sender], blockhash);
+        assert!(client.missing_signers(&transaction).is_empty());
+    }
+
+    #[test]
+    fn confirmation_time_is_estimated_from_the_slot_time() {
+        let mock = rpc_mock().with_response(
+            "getRecentPerformanceSamples",
+            json!([
+                {"slot": 400, "numTransactions": 9000, "numSlots": 120, "samplePeriodSecs": 60},
+                {"slot": 280, "numTransactions": 9000, "numSlots": 120, "samplePeriodSecs": 60},
+            ]),
+        );
+        let client = mock_client(&mock);
+
+        assert_eq!(
+            client
+                .estimate_confirmation_time(CommitmentConfig::processed())
+                .unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            client
+                .estimate_confirmation_time(CommitmentConfig::confirmed())
+                .unwrap(),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            client
+                .estimate_confirmation_time(CommitmentConfig::finalized())
+                .unwrap(),
+            Duration::from_secs(16)
+        );
+        mock.assert_called_with("getRecentPerformanceSamples", &json!([ESTIMATE_PERFORMANCE_SAMPLES]));
+
+        mock.set_response("getRecentPerformanceSamples", Ok(json!([])));
+        assert_eq!(
+            client
+                .estimate_confirmation_time(CommitmentConfig::confirmed())
+                .unwrap(),
+            Duration::from_millis(DEFAULT_MS_PER_SLOT * 2)
+        );
+    }
+
+    #[test]
+    fn missing_transactions_are_told_apart() {
+        let mock = rpc_mock()
+            .with_response("getTransaction", confirmed_transaction(transaction_meta(5000)))
+            .with_response("getFirstAvailableBlock", json!(100))
+            .with_response("minimumLedgerSlot", json!(50));
+        let client = mock_client(&mock);
+        let signature = Signature::new_unique();
+
+        let transaction = client.get_transaction_or_reason(&signature).unwrap().unwrap();
+        assert_eq!(transaction["meta"]["fee"], 5000);
+
+        mock.set_response("getTransaction", Ok(Value::Null));
+        let mut old_status = transaction_status(None);
+        old_status["slot"] = json!(40);
+        mock.push_response("getSignatureStatuses", Ok(context_response(300, json!([old_status]))));
+        let mut recent_status = status_at("processed");
+        recent_status["slot"] = json!(290);
+        mock.push_response(
+            "getSignatureStatuses",
+            Ok(context_response(300, json!([recent_status]))),
+        );
+        mock.push_response("getSignatureStatuses", Ok(context_response(300, json!([null]))));
+
+        assert_eq!(
+            client.get_transaction_or_reason(&signature).unwrap(),
+            Err(NotFoundReason::TooOld)
+        );
+        assert_eq!(
+            client.get_transaction_or_reason(&signature).unwrap(),
+            Err(NotFoundReason::Unconfirmed)
+        );
+        assert_eq!(
+            client.get_transaction_or_reason(&signature).unwrap(),
+            Err(NotFoundReason::Unknown)
+        );
+        assert_eq!(
+            mock.calls_to("getSignatureStatuses")[0][1],
+            json!({ "searchTransactionHistory": true })
+        );
+    }
+
+    #[test]
+    fn quorum_of_confirming_clients_is_awaited() {
+        let confirming = rpc_mock().with_response(
+            "getSignatureStatuses",
+            context_response(5, json!([status_at("confirmed")])),
+        );
+        let lagging = rpc_mock().with_response(
+            "getSignatureStatuses",
+            context_response(5, json!([status_at("confirmed")])),
+        );
+        lagging.push_response("getSignatureStatuses", Ok(context_response(4, json!([null]))));
+        let unaware = rpc_mock().with_response("getSignatureStatuses", context_response(5, json!([null])));
+        let (client, lagging_client, unaware_client) =
+            (mock_client(&confirming), mock_client(&lagging), mock_client(&unaware));
+        let signature = Signature::new_unique();
+
+        assert_eq!(
+            client
+                .confirm_with_quorum(
+                    &signature,
+                    &[&lagging_client, &unaware_client],
+                    2,
+                    Duration::from_secs(60)
+                )
+                .unwrap(),
+            2
+        );
+        assert_eq!(lagging.call_count("getSignatureStatuses"), 2);
+
+        let err = client
+            .confirm_with_quorum(&signature, &[&lagging_client, &unaware_client], 3, Duration::ZERO)
+            .unwrap_err();
+        assert!(err.to_string().contains("confirmed by 2 of 3 clients"), "{}", err);
+    }
+
+    #[test]
+    fn built_transactions_get_the_compute_budget_after_the_nonce_advance() {
+        let payer = Keypair::new();
+        let nonce_account = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        // 1000 simulated units with the 10% margin, at the median of the recent fees
+        let expected = Transaction::new_signed_with_payer(
+            &[
+                system_instruction::advance_nonce_account(&nonce_account, &payer.pubkey()),
+                fees::set_compute_unit_limit(1100),
+                fees::set_compute_unit_price(7),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer],
+            blockhash,
+        );
+        let mock = landing_mock(&expected)
+            .with_response(
+                "getLatestBlockhash",
+                context_response(
+                    1,
+                    json!({"blockhash": blockhash.to_string(), "lastValidBlockHeight": 300}),
+                ),
+            )
+            .with_response(
+                "simulateTransaction",
+                context_response(1, json!({ "err": null, "logs": [], "unitsConsumed": 1000 })),
+            )
+            .with_response(
+                "getRecentPrioritizationFees",
+                json!([
+                    { "slot": 1, "prioritizationFee": 3 },
+                    { "slot": 2, "prioritizationFee": 7 },
+                    { "slot": 3, "prioritizationFee": 9 },
+                ]),
+            );
+        let client = mock_client(&mock)
+            .with_tx_wire_encoding(UiTransactionEncoding::Base64)
+            .with_priority_fee_percentile(Some(50));
+
+        let signature = client
+            .advance_nonce_account(&[&payer], &payer.pubkey(), &nonce_account, &payer.pubkey())
+            .unwrap();
+        assert_eq!(signature, expected.signatures[0]);
+        assert_eq!(
+            mock.calls_to("sendTransaction")[0][0],
+            encode_transaction(&expected, UiTransactionEncoding::Base64).unwrap()
+        );
+
+        assert_eq!(mock.call_count("simulateTransaction"), 1);
+    }
+
+    #[test]
+    fn concurrent_sends_share_the_batched_status_polls() {
+        let payers: Vec<_> = (0..3).map(|_| Keypair::new()).collect();
+        let transactions: Vec<_> = payers.iter().map(|payer| transfer(payer, Hash::new_unique())).collect();
+        let signatures: Vec<_> = transactions
+            .iter()
+            .map(|transaction| transaction.signatures[0])
+            .collect();
+        let mocks: Vec<_> = transactions
+            .iter()
+            .map(|transaction| {
+                landing_mock(transaction).with_response(
+                    "getSignatureStatuses",
+                    context_response(5, json!(vec![transaction_status(None); 3])),
+                )
+            })
+            .collect();
+
+        let hub = Arc::new(ConfirmationHub::new(Duration::from_secs(60)));
+        // Registered upfront, so the first poll covers the three whichever send polls first
+        for signature in &signatures {
+            hub.register(*signature);
+        }
+        std::thread::scope(|scope| {
+            for (mock, transaction) in mocks.iter().zip(&transactions) {
+                let client = mock_client(mock).with_confirmation_hub(hub.clone());
+                scope.spawn(move || {
+                    assert_eq!(
+                        client.send_and_confirm_transaction(transaction).unwrap(),
+                        transaction.signatures[0]
+                    );
+                });
+            }
+        });
+
+        let polls: Vec<_> = mocks
+            .iter()
+            .flat_map(|mock| mock.calls_to("getSignatureStatuses"))
+            .collect();
+        assert_eq!(polls.len(), 1);
+        let mut polled: Vec<_> = polls[0][0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|signature| signature.as_str().unwrap().to_string())
+            .collect();
+        let mut expected: Vec<_> = signatures.iter().map(Signature::to_string).collect();
+        polled.sort();
+        expected.sort();
+        assert_eq!(polled, expected);
+        assert_eq!(hub.pending_count(), 0);
+    }
+
+    #[test]
+    fn batch_results_are_matched_to_the_requests_by_id() {
+        let bodies = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sender = HttpSender::new("http://localhost:8899").with_transport({
+            let bodies = bodies.clone();
+            move |request| {
+                let requests: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+                bodies.lock().unwrap().push(requests.clone());
+                // Answered out of order, as the JSON-RPC spec allows
+                let responses: Vec<_> = requests
+                    .iter()
+                    .rev()
+                    .map(|request| {
+                        let mut response = json!({"jsonrpc": "2.0", "id": request["id"]});
+                        match request["method"].as_str().unwrap() {
+                            "getBalance" => response["result"] = context_response(3, json!(42)),
+                            "getAccountInfo" => response["error"] = json!({"code": -32602, "message": "Invalid param"}),
+                            _ => response["result"] = context_response(3, json!([null])),
+                        }
+                        response
+                    })
+                    .collect();
+                Ok(HostHttpResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: json!(responses).to_string().into_bytes(),
+                })
+            }
+        });
+        let client =
+            WasmRpcClient::with_http_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed()));
+
+        let results = client
+            .batch()
+            .get_balance(&Pubkey::new_unique())
+            .get_account_info(&Pubkey::new_unique())
+            .get_signature_statuses(&[Signature::new_unique()])
+            .send()
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()["value"], 42);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()["value"], json!([null]));
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        let methods: Vec<_> = bodies[0].iter().map(|request| request["method"].clone()).collect();
+        assert_eq!(methods, ["getBalance", "getAccountInfo", "getSignatureStatuses"]);
+    }
+
+    #[test]
+    fn simulation_report_has_the_balance_changes_of_the_accounts() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::default());
+        let recipient = Pubkey::new_from_array([7; 32]);
+        let account = |lamports| Account {
+            lamports,
+            data: vec![],
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mock = rpc_mock()
+            .with_response(
+                "getMultipleAccounts",
+                context_response(1, json!([ui_account(&account(10_000)), null])),
+            )
+            .with_response(
+                "simulateTransaction",
+                context_response(
+                    2,
+                    json!({
+                        "err": null,
+                        "logs": ["Program 11111111111111111111111111111111 success"],
+                        "accounts": [ui_account(&account(4_000)), ui_account(&account(1))],
+                        "unitsConsumed": 150,
+                    }),
+                ),
+            );
+        let client = mock_client(&mock);
+
+        let report = client
+            .simulate_with_report(&transaction, &[payer.pubkey(), recipient])
+            .unwrap();
+        assert!(report.succeeded());
+        assert_eq!(report.units_consumed, Some(150));
+        assert_eq!(report.logs.len(), 1);
+        let deltas: Vec<_> = report.balance_changes.iter().map(BalanceChange::delta).collect();
+        assert_eq!(deltas, [-6_000, 1]);
+
+        let config = &mock.calls_to("simulateTransaction")[0][1];
+        assert_eq!(config["replaceRecentBlockhash"], true);
+        assert_eq!(config["sigVerify"], false);
+        assert_eq!(
+            config["accounts"]["addresses"],
+            json!([payer.pubkey().to_string(), recipient.to_string()])
+        );
+    }
+
+    #[test]
+    fn failed_simulation_reports_the_instruction_error_and_keeps_the_balances() {
+        let payer = Keypair::new();
+        let transaction = transfer(&payer, Hash::default());
+        let mock = rpc_mock()
+            .with_response(
+                "getMultipleAccounts",
+                context_response(
+                    1,
+                    json!([{
+                        "lamports": 10_000,
+                        "data": ["", "base64"],
+                        "owner": system_program::id().to_string(),
+                        "executable": false,
+                        "rentEpoch": 0,
+                    }]),
+                ),
+            )
+            .with_response(
+                "simulateTransaction",
+                context_response(
+                    2,
+                    json!({
+                        "err": {"InstructionError": [0, {"Custom": 1}]},
+                        "logs": [],
+                        "accounts": null,
+                        "unitsConsumed": 300,
+                    }),
+                ),
+            );
+        let client = mock_client(&mock);
+
+        let report = client.simulate_with_report(&transaction, &[payer.pubkey()]).unwrap();
+        assert!(!report.succeeded());
+        assert_eq!(report.instruction_error, Some((0, InstructionError::Custom(1))));
+        // The accounts are not returned by a failed simulation
+        assert_eq!(report.balance_changes[0].post_lamports, 10_000);
+        assert_eq!(report.balance_changes[0].delta(), 0);
     }
 }